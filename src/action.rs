@@ -0,0 +1,108 @@
+//! One-shot mode for GitHub Actions: handles the event that triggered the workflow with
+//! `GITHUB_TOKEN`, the same way the webhook server would, for orgs that can't install the app.
+
+use crate::{
+    webhook::{dispatch_event, WebhookError},
+    State,
+};
+use derive_more::{Display, Error};
+use std::{env, fs};
+use tracing::info;
+
+/// Creator of the issues opened with `GITHUB_TOKEN`
+pub static DEFAULT_ACTION_USERNAME: &str = "app/github-actions";
+pub static DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+
+#[derive(Debug, Display, Error)]
+pub enum ActionError {
+    #[display("The environment variable `{name}` is not set")]
+    MissingVariable { name: &'static str },
+    #[display("Failed to read the event payload `{path}`. {source}")]
+    ReadEvent {
+        path: String,
+        source: std::io::Error,
+    },
+    #[display("Failed to create the GitHub client. {source}")]
+    Client { source: octocrab::Error },
+    #[display("Failed to handle the `{event}` event. {source}")]
+    Webhook { event: String, source: WebhookError },
+    #[display("Handling the `{event}` event failed with status {status}, see the comments on the issue or pull request")]
+    Failed { event: String, status: u16 },
+}
+
+/// Event of the workflow run, read from the environment set by GitHub Actions
+#[derive(Debug, Clone)]
+pub struct ActionEvent {
+    /// `GITHUB_EVENT_NAME`
+    pub name: String,
+    /// Content of the file at `GITHUB_EVENT_PATH`
+    pub payload: Vec<u8>,
+    /// `GITHUB_TOKEN`
+    pub token: String,
+    /// `GITHUB_API_URL`, set on GitHub Enterprise Server
+    pub api_url: String,
+    /// `RELEASE_BUTLER_USERNAME`, only needed if the workflow uses another token than
+    /// `GITHUB_TOKEN` (Default: [`DEFAULT_ACTION_USERNAME`])
+    pub username: String,
+}
+
+impl ActionEvent {
+    pub fn from_env() -> Result<Self, ActionError> {
+        let var = |name: &'static str| {
+            env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .ok_or(ActionError::MissingVariable { name })
+        };
+
+        let path = var("GITHUB_EVENT_PATH")?;
+        let payload = fs::read(&path).map_err(|source| ActionError::ReadEvent { path, source })?;
+
+        Ok(Self {
+            name: var("GITHUB_EVENT_NAME")?,
+            payload,
+            token: var("GITHUB_TOKEN")?,
+            api_url: var("GITHUB_API_URL").unwrap_or_else(|_| DEFAULT_GITHUB_API_URL.to_owned()),
+            username: var("RELEASE_BUTLER_USERNAME")
+                .unwrap_or_else(|_| DEFAULT_ACTION_USERNAME.to_owned()),
+        })
+    }
+
+    /// Handles the event, events that release-butler doesn't act on are ignored
+    pub async fn run(self) -> Result<(), ActionError> {
+        let state = State::from_token(self.token, self.username, &self.api_url)
+            .map_err(|source| ActionError::Client { source })?;
+
+        handle_event(&state, &self.name, &self.payload).await
+    }
+}
+
+/// Handles `payload` with `state`, like [`crate::webhook::parse_event`] once the signature is
+/// verified
+pub async fn handle_event(
+    state: &State,
+    event_name: &str,
+    payload: &[u8],
+) -> Result<(), ActionError> {
+    // Same payload, but the workflow runs in the context of the base repository
+    let github_event = match event_name {
+        "pull_request_target" => "pull_request",
+        event_name => event_name,
+    };
+
+    match dispatch_event(github_event, payload, state).await {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(ActionError::Failed {
+            event: event_name.to_owned(),
+            status: response.status().as_u16(),
+        }),
+        Err(WebhookError::UnsupportedEvent) => {
+            info!("Nothing to do for the `{}` event", event_name);
+            Ok(())
+        }
+        Err(source) => Err(ActionError::Webhook {
+            event: event_name.to_owned(),
+            source,
+        }),
+    }
+}
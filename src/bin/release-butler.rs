@@ -0,0 +1,206 @@
+//! Runs the release flow against a local git checkout, without the GitHub App.
+
+use chrono::{NaiveDate, Utc};
+use clap::{Parser, Subcommand};
+use release_butler::{
+    action::{ActionError, ActionEvent, DEFAULT_ACTION_USERNAME, DEFAULT_GITHUB_API_URL},
+    issue_form::{self, ISSUE_FORM_PATH},
+    local::{LocalError, LocalRepository},
+    replay::Delivery,
+    State, DEFAULT_CONFIG_FILE_PATH,
+};
+use std::{env, error::Error, fs, path::PathBuf, process::ExitCode};
+
+/// Prepares releases in a local git checkout, the same way the release-butler app does
+#[derive(Parser)]
+#[command(name = "release-butler", version, about)]
+struct Cli {
+    /// Path inside the git repository to release from
+    #[arg(short = 'C', long, default_value = ".")]
+    repository: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Bumps the version and updates the changelog, then commits them on a release branch
+    Prepare {
+        /// Package and version, same as the release issue title (e.g. `foo@1.2.3` or `v1.2.3`)
+        release: String,
+        /// Markdown file with the changes of this release, added to the changelog
+        #[arg(long)]
+        changelog: Option<PathBuf>,
+        /// Release date written in the changelog (Default: today)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// Print a diff of the changes instead of committing them. Implied by `dry_run = true` in
+        /// the configuration.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Creates the release tag, i.e. on the merge commit of the release branch
+    Tag {
+        /// Package and version, same as the release issue title (e.g. `foo@1.2.3` or `v1.2.3`)
+        release: String,
+        /// The commit to tag
+        #[arg(long, default_value = "HEAD")]
+        rev: String,
+    },
+    /// Checks the configuration, and the manifests and changelogs of every package. Exits with a
+    /// non-zero status if a problem is found, i.e. for a pre-commit hook.
+    Validate,
+    /// Generates the release issue form from the configuration, with a dropdown of the packages
+    IssueForm {
+        /// Print the form instead of writing it to `.github/ISSUE_TEMPLATE/release.yml`
+        #[arg(long)]
+        stdout: bool,
+    },
+    /// Handles the event that triggered the GitHub Actions workflow, like the app would. Reads
+    /// `GITHUB_EVENT_NAME`, `GITHUB_EVENT_PATH` and `GITHUB_TOKEN`.
+    GithubAction,
+    /// Replays a saved GitHub webhook delivery, either posting it to a running server or handling
+    /// it in-process with `GITHUB_TOKEN`
+    Replay {
+        /// JSON file with the delivery, as returned by the GitHub deliveries API or only the payload
+        delivery: PathBuf,
+        /// Event name, required if the delivery doesn't contain the `X-GitHub-Event` header
+        #[arg(long)]
+        event: Option<String>,
+        /// Webhook endpoint to post the delivery to (e.g. `http://localhost:8000/github/webhook/`)
+        #[arg(long, requires = "secret")]
+        url: Option<String>,
+        /// Webhook secret used to sign the delivery
+        #[arg(long)]
+        secret: Option<String>,
+        /// GitHub API used when handling the delivery in-process
+        #[arg(long, default_value = DEFAULT_GITHUB_API_URL)]
+        api_url: String,
+    },
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Command::Prepare {
+            release,
+            changelog,
+            date,
+            dry_run,
+        } => {
+            let notes = match changelog {
+                Some(path) => fs::read_to_string(&path).map_err(|source| LocalError::Read {
+                    path: path.display().to_string(),
+                    source,
+                })?,
+                None => String::new(),
+            };
+            let date = date.unwrap_or_else(|| Utc::now().date_naive());
+
+            let repository = LocalRepository::discover(&cli.repository)?;
+            let plan = repository.plan(&release, notes.trim_end(), date)?;
+            if dry_run || plan.dry_run {
+                for edit in &plan.edits {
+                    print!("{}", edit.unified_diff());
+                }
+                println!(
+                    "\nDry run: nothing was committed. The changes would be committed on `{}` and \
+                    the tag `{}` created once it is merged.",
+                    plan.branch, plan.tag
+                );
+                return Ok(());
+            }
+
+            let prepared = repository.prepare(&plan)?;
+            let paths: Vec<_> = plan.edits.iter().map(|edit| edit.path.as_str()).collect();
+            println!(
+                "Committed {} on `{}` ({})",
+                paths.join(", "),
+                prepared.branch,
+                prepared.commit
+            );
+        }
+        Command::Tag { release, rev } => {
+            let repository = LocalRepository::discover(&cli.repository)?;
+            let tag = repository.tag(&release, &rev)?;
+            println!("Created tag `{}` on {}", tag, rev);
+        }
+        Command::Validate => {
+            let diagnostics = LocalRepository::discover(&cli.repository)?.validate();
+            if !diagnostics.is_empty() {
+                for diagnostic in &diagnostics {
+                    eprintln!("error: {}", diagnostic);
+                }
+                return Err(LocalError::Invalid {
+                    count: diagnostics.len(),
+                }
+                .into());
+            }
+            println!("`{}` is valid", DEFAULT_CONFIG_FILE_PATH);
+        }
+        Command::IssueForm { stdout } => {
+            let repository = LocalRepository::discover(&cli.repository)?;
+            if stdout {
+                print!("{}", issue_form::render(&repository.config()?));
+            } else {
+                repository.write_issue_form()?;
+                println!("Wrote `{}`", ISSUE_FORM_PATH);
+            }
+        }
+        Command::GithubAction => {
+            tracing_subscriber::fmt().with_target(false).init();
+
+            let event = ActionEvent::from_env()?;
+            tokio::runtime::Runtime::new()?.block_on(event.run())?;
+        }
+        Command::Replay {
+            delivery,
+            event,
+            url,
+            secret,
+            api_url,
+        } => {
+            let content = fs::read(&delivery).map_err(|source| LocalError::Read {
+                path: delivery.display().to_string(),
+                source,
+            })?;
+            let delivery = Delivery::parse(&content, event.as_deref())?;
+
+            let system = actix_web::rt::System::new();
+            let response = match (url, secret) {
+                (Some(url), Some(secret)) => system.block_on(delivery.post(&url, &secret))?,
+                _ => {
+                    tracing_subscriber::fmt().with_target(false).init();
+
+                    let token =
+                        env::var("GITHUB_TOKEN").map_err(|_| ActionError::MissingVariable {
+                            name: "GITHUB_TOKEN",
+                        })?;
+                    let username = env::var("RELEASE_BUTLER_USERNAME")
+                        .unwrap_or_else(|_| DEFAULT_ACTION_USERNAME.to_owned());
+                    let state = State::from_token(token, username, &api_url)
+                        .map_err(|source| ActionError::Client { source })?;
+                    system.block_on(delivery.replay(state))
+                }
+            };
+
+            println!("{} {}", response.status, response.body);
+            if !(200..300).contains(&response.status) {
+                return Err(
+                    format!("The delivery was rejected with status {}", response.status).into(),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
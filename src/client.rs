@@ -0,0 +1,940 @@
+//! A thin layer over [`Octocrab`] which retries transient GitHub API failures.
+//!
+//! Every request is sent through [`GitHubClient::send`], which retries server errors (`5xx`),
+//! primary and secondary rate limits with exponential backoff, honouring the `Retry-After` and
+//! `X-RateLimit-Reset` headers whenever GitHub provides them. Requests which aren't idempotent
+//! (`POST`, `PATCH`) may have been applied before failing, so they are only retried when they
+//! were rejected by a rate limit or couldn't connect at all.
+
+use crate::common::encode_path;
+use base64::Engine;
+use derive_more::{Display, Error};
+use http::{HeaderMap, Method, StatusCode};
+use octocrab::{models::InstallationId, Octocrab};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, warn};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every subsequent retry
+    pub base_delay: Duration,
+    /// Upper bound of the exponential backoff delay
+    pub max_delay: Duration,
+    /// Longest time we are willing to wait for a rate limit to reset, before giving up
+    pub max_rate_limit_wait: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            max_rate_limit_wait: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy which never retries, useful for tests
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+
+    /// Computes how long to wait before retrying a request that failed with `status`, or `None`
+    /// if the request shouldn't be retried.
+    pub(crate) fn retry_delay(
+        &self,
+        attempt: u32,
+        method: &Method,
+        status: StatusCode,
+        headers: &HeaderMap,
+        message: &str,
+        now: u64,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        let header_u64 = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+
+        let retry_after = header_u64("retry-after").map(Duration::from_secs);
+        let rate_limit_exhausted = header_u64("x-ratelimit-remaining") == Some(0);
+        let rate_limit_reset = header_u64("x-ratelimit-reset")
+            .map(|reset| Duration::from_secs(reset.saturating_sub(now)));
+
+        let delay = if status.is_server_error() {
+            if !is_idempotent(method) {
+                return None;
+            }
+            retry_after.unwrap_or_else(|| self.backoff(attempt))
+        } else if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+            let secondary_rate_limit = message.to_lowercase().contains("secondary rate limit");
+
+            if let Some(retry_after) = retry_after {
+                retry_after
+            } else if rate_limit_exhausted {
+                rate_limit_reset.unwrap_or_else(|| self.backoff(attempt))
+            } else if secondary_rate_limit {
+                // GitHub asks to wait at least a minute when no header is provided
+                Duration::from_secs(60).max(self.backoff(attempt))
+            } else if status == StatusCode::TOO_MANY_REQUESTS {
+                self.backoff(attempt)
+            } else {
+                // A plain `403` is a permission problem, retrying won't help
+                return None;
+            }
+        } else {
+            return None;
+        };
+
+        if delay > self.max_rate_limit_wait {
+            return None;
+        }
+
+        Some(delay)
+    }
+}
+
+/// Whether sending the request again has the same effect as sending it once
+pub(crate) fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::PUT | Method::DELETE)
+}
+
+/// Whether the request failed before reaching GitHub, so it can always be sent again
+fn is_connect_error(err: &octocrab::Error) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if err
+            .downcast_ref::<hyper_util::client::legacy::Error>()
+            .is_some_and(|err| err.is_connect())
+        {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+#[derive(Debug, Display, Error, Clone)]
+pub enum ClientError {
+    #[display("API responded with status {status}. {message}")]
+    Api { status: u16, message: String },
+    #[display("Failed to reach the API. {msg}")]
+    Transport { msg: String },
+    #[display("Failed to decode the API response. {msg}")]
+    Decode { msg: String },
+    #[display("GitHub GraphQL API returned errors. {message}")]
+    GraphQL { message: String },
+}
+
+impl ClientError {
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ClientError::Api { status: 404, .. })
+    }
+}
+
+/// File returned by the repository contents API
+#[derive(Deserialize, Debug, Clone)]
+pub struct FileContent {
+    pub name: String,
+    pub path: String,
+    pub sha: String,
+    pub encoding: Option<String>,
+    /// File content, Base64 encoded
+    pub content: Option<String>,
+}
+
+impl FileContent {
+    pub fn decoded_content(&self) -> Option<String> {
+        let mut content = self.content.as_ref()?.as_bytes().to_owned();
+        content.retain(|b| !b" \n\t\r\x0b\x0c".contains(b));
+
+        let content = base64::prelude::BASE64_STANDARD.decode(content).ok()?;
+        String::from_utf8(content).ok()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct User {
+    pub login: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub body: Option<String>,
+    pub html_url: String,
+    /// Author of the issue
+    pub user: User,
+}
+
+/// A comment on an issue or pull request (a note on GitLab)
+#[derive(Deserialize, Debug, Clone)]
+pub struct Comment {
+    pub id: u64,
+    pub body: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PullRequest {
+    pub number: u64,
+    pub html_url: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Release {
+    pub id: u64,
+    pub tag_name: String,
+    pub html_url: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitObject {
+    pub sha: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitRef {
+    pub object: GitObject,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TreeEntry {
+    pub path: String,
+    pub mode: String,
+    pub r#type: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Tree {
+    pub sha: String,
+    pub tree: Vec<TreeEntry>,
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+#[derive(Clone)]
+pub struct GitHubClient {
+    gh: Octocrab,
+    policy: RetryPolicy,
+}
+
+impl GitHubClient {
+    pub fn new(gh: Octocrab, policy: RetryPolicy) -> Self {
+        Self { gh, policy }
+    }
+
+    pub fn octocrab(&self) -> &Octocrab {
+        &self.gh
+    }
+
+    /// Returns a new client authenticated as the given installation, sharing the retry policy.
+    pub fn installation(&self, id: InstallationId) -> Result<Self, octocrab::Error> {
+        Ok(Self {
+            gh: self.gh.installation(id)?,
+            policy: self.policy,
+        })
+    }
+
+    /// Sends the request, retrying on transient failures as described by the [`RetryPolicy`].
+    /// Returns the raw body of the successful response.
+    pub async fn send<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        route: &str,
+        body: Option<&B>,
+    ) -> Result<String, ClientError> {
+        let mut attempt = 0;
+
+        loop {
+            let response = match method {
+                Method::GET => self.gh._get(route).await,
+                Method::POST => self.gh._post(route, body).await,
+                Method::PATCH => self.gh._patch(route, body).await,
+                Method::PUT => self.gh._put(route, body).await,
+                Method::DELETE => self.gh._delete(route, body).await,
+                _ => {
+                    return Err(ClientError::Transport {
+                        msg: format!("Unsupported method {}", method),
+                    })
+                }
+            };
+
+            let response = match response {
+                Ok(response) => response,
+                Err(err) => {
+                    let retryable = is_connect_error(&err)
+                        || (is_idempotent(&method)
+                            && matches!(
+                                err,
+                                octocrab::Error::Hyper { .. } | octocrab::Error::Service { .. }
+                            ));
+                    if retryable && attempt < self.policy.max_retries {
+                        let delay = self.policy.backoff(attempt);
+                        warn!(
+                            "{} {} failed, retrying in {:?}. Error: {}",
+                            method, route, delay, err
+                        );
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                        continue;
+                    }
+
+                    error!("{} {} failed. Error: {}", method, route, err);
+                    return Err(ClientError::Transport {
+                        msg: err.to_string(),
+                    });
+                }
+            };
+
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body =
+                self.gh
+                    .body_to_string(response)
+                    .await
+                    .map_err(|err| ClientError::Transport {
+                        msg: err.to_string(),
+                    })?;
+
+            if status.is_success() {
+                return Ok(body);
+            }
+
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| body["message"].as_str().map(str::to_owned))
+                .unwrap_or(body);
+
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|now| now.as_secs())
+                .unwrap_or_default();
+
+            match self
+                .policy
+                .retry_delay(attempt, &method, status, &headers, &message, now)
+            {
+                Some(delay) => {
+                    warn!(
+                        "{} {} responded with {}, retrying in {:?}. Message: {}",
+                        method, route, status, delay, message
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => {
+                    return Err(ClientError::Api {
+                        status: status.as_u16(),
+                        message,
+                    })
+                }
+            }
+        }
+    }
+
+    async fn send_json<B: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        route: &str,
+        body: Option<&B>,
+    ) -> Result<R, ClientError> {
+        let response = self.send(method, route, body).await?;
+
+        serde_json::from_str(&response).map_err(|err| ClientError::Decode {
+            msg: err.to_string(),
+        })
+    }
+
+    pub async fn get<R: DeserializeOwned>(&self, route: &str) -> Result<R, ClientError> {
+        self.send_json(Method::GET, route, None::<&()>).await
+    }
+
+    /// Lists every page of `route`, requesting `per_page` items at a time
+    pub async fn get_pages<R: DeserializeOwned>(
+        &self,
+        route: &str,
+        per_page: usize,
+    ) -> Result<Vec<R>, ClientError> {
+        let separator = if route.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        for page in 1.. {
+            let batch: Vec<R> = self
+                .get(&format!(
+                    "{}{}per_page={}&page={}",
+                    route, separator, per_page, page
+                ))
+                .await?;
+            let last_page = batch.len() < per_page;
+            items.extend(batch);
+            if last_page {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    pub async fn post<B: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        route: &str,
+        body: &B,
+    ) -> Result<R, ClientError> {
+        self.send_json(Method::POST, route, Some(body)).await
+    }
+
+    pub async fn patch<B: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        route: &str,
+        body: &B,
+    ) -> Result<R, ClientError> {
+        self.send_json(Method::PATCH, route, Some(body)).await
+    }
+
+    /// Executes a GraphQL query, returning the `data` of the response. GraphQL errors are
+    /// reported with `200 OK`, so they are converted into [`ClientError::GraphQL`].
+    pub async fn graphql<R: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<R, ClientError> {
+        #[derive(Deserialize)]
+        struct GraphQLError {
+            message: String,
+        }
+
+        #[derive(Deserialize)]
+        struct GraphQLResponse<R> {
+            data: Option<R>,
+            errors: Option<Vec<GraphQLError>>,
+        }
+
+        let response: GraphQLResponse<R> = self
+            .post(
+                "/graphql",
+                &serde_json::json!({
+                    "query": query,
+                    "variables": variables
+                }),
+            )
+            .await?;
+
+        match (response.data, response.errors) {
+            (Some(data), None) => Ok(data),
+            (_, Some(errors)) if !errors.is_empty() => Err(ClientError::GraphQL {
+                message: errors
+                    .into_iter()
+                    .map(|err| err.message)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            }),
+            _ => Err(ClientError::GraphQL {
+                message: String::from("Response contained neither data nor errors"),
+            }),
+        }
+    }
+
+    pub async fn delete(&self, route: &str) -> Result<(), ClientError> {
+        self.send(Method::DELETE, route, None::<&()>).await?;
+        Ok(())
+    }
+
+    /// Returns `None` if the file doesn't exist. Reads the default branch if `ref_` is `None`.
+    pub async fn get_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        ref_: Option<&str>,
+    ) -> Result<Option<FileContent>, ClientError> {
+        let route = match ref_ {
+            Some(ref_) => format!(
+                "/repos/{}/{}/contents/{}?ref={}",
+                owner,
+                repo,
+                path,
+                encode_path(ref_)
+            ),
+            None => format!("/repos/{}/{}/contents/{}", owner, repo, path),
+        };
+        match self.get(&route).await {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if err.is_not_found() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn list_issues(
+        &self,
+        owner: &str,
+        repo: &str,
+        creator: &str,
+        label: &str,
+    ) -> Result<Vec<Issue>, ClientError> {
+        self.get(&format!(
+            "/repos/{}/{}/issues?creator={}&labels={}",
+            owner,
+            repo,
+            encode_path(creator),
+            encode_path(label)
+        ))
+        .await
+    }
+
+    pub async fn get_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Issue, ClientError> {
+        self.get(&format!("/repos/{}/{}/issues/{}", owner, repo, number))
+            .await
+    }
+
+    pub async fn create_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        title: &str,
+        body: &str,
+        labels: &[&str],
+    ) -> Result<Issue, ClientError> {
+        self.post(
+            &format!("/repos/{}/{}/issues", owner, repo),
+            &serde_json::json!({
+                "title": title,
+                "body": body,
+                "labels": labels
+            }),
+        )
+        .await
+    }
+
+    pub async fn create_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> Result<(), ClientError> {
+        self.post::<_, serde_json::Value>(
+            &format!("/repos/{}/{}/issues/{}/comments", owner, repo, number),
+            &serde_json::json!({ "body": body }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Oldest first, following every page
+    pub async fn list_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<Comment>, ClientError> {
+        self.get_pages(
+            &format!("/repos/{}/{}/issues/{}/comments", owner, repo, number),
+            100,
+        )
+        .await
+    }
+
+    pub async fn update_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<(), ClientError> {
+        self.patch::<_, serde_json::Value>(
+            &format!("/repos/{}/{}/issues/comments/{}", owner, repo, comment_id),
+            &serde_json::json!({ "body": body }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn update_issue_title(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        title: &str,
+    ) -> Result<(), ClientError> {
+        self.patch::<_, serde_json::Value>(
+            &format!("/repos/{}/{}/issues/{}", owner, repo, number),
+            &serde_json::json!({ "title": title }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn close_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<(), ClientError> {
+        self.patch::<_, serde_json::Value>(
+            &format!("/repos/{}/{}/issues/{}", owner, repo, number),
+            &serde_json::json!({ "state": "closed" }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// `content` is one of the reactions supported by GitHub, i.e. `+1` or `confused`
+    pub async fn create_comment_reaction(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        content: &str,
+    ) -> Result<(), ClientError> {
+        self.post::<_, serde_json::Value>(
+            &format!(
+                "/repos/{}/{}/issues/comments/{}/reactions",
+                owner, repo, comment_id
+            ),
+            &serde_json::json!({ "content": content }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn add_labels(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        labels: &[&str],
+    ) -> Result<(), ClientError> {
+        self.post::<_, serde_json::Value>(
+            &format!("/repos/{}/{}/issues/{}/labels", owner, repo, number),
+            &serde_json::json!({ "labels": labels }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_label(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        label: &str,
+    ) -> Result<(), ClientError> {
+        self.delete(&format!(
+            "/repos/{}/{}/issues/{}/labels/{}",
+            owner, repo, number, label
+        ))
+        .await
+    }
+
+    /// Returns `None` if the reference doesn't exist. `ref_` must be fully qualified, without
+    /// the `refs/` prefix, i.e. `heads/main`
+    pub async fn get_ref(
+        &self,
+        owner: &str,
+        repo: &str,
+        ref_: &str,
+    ) -> Result<Option<GitRef>, ClientError> {
+        match self
+            .get(&format!("/repos/{}/{}/git/ref/{}", owner, repo, ref_))
+            .await
+        {
+            Ok(ref_) => Ok(Some(ref_)),
+            Err(err) if err.is_not_found() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// `tree_ish` can be the sha of a tree or a commit
+    pub async fn get_tree(
+        &self,
+        owner: &str,
+        repo: &str,
+        tree_ish: &str,
+        recursive: bool,
+    ) -> Result<Tree, ClientError> {
+        let mut route = format!("/repos/{}/{}/git/trees/{}", owner, repo, tree_ish);
+        if recursive {
+            route.push_str("?recursive=1");
+        }
+        self.get(&route).await
+    }
+
+    /// `ref_` must be fully qualified, without the `refs/` prefix, i.e. `tags/v1.0.0`
+    pub async fn create_ref(
+        &self,
+        owner: &str,
+        repo: &str,
+        ref_: &str,
+        sha: &str,
+    ) -> Result<GitRef, ClientError> {
+        self.post(
+            &format!("/repos/{}/{}/git/refs", owner, repo),
+            &serde_json::json!({
+                "ref": format!("refs/{}", ref_),
+                "sha": sha
+            }),
+        )
+        .await
+    }
+
+    pub async fn update_ref(
+        &self,
+        owner: &str,
+        repo: &str,
+        ref_: &str,
+        sha: &str,
+    ) -> Result<GitRef, ClientError> {
+        self.patch(
+            &format!("/repos/{}/{}/git/refs/{}", owner, repo, ref_),
+            &serde_json::json!({
+                "sha": sha,
+                "force": true
+            }),
+        )
+        .await
+    }
+
+    /// `ref_` must be fully qualified, without the `refs/` prefix, i.e. `heads/main`
+    pub async fn delete_ref(&self, owner: &str, repo: &str, ref_: &str) -> Result<(), ClientError> {
+        self.delete(&format!("/repos/{}/{}/git/refs/{}", owner, repo, ref_))
+            .await
+    }
+
+    pub async fn close_pull(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<(), ClientError> {
+        self.patch::<_, serde_json::Value>(
+            &format!("/repos/{}/{}/pulls/{}", owner, repo, number),
+            &serde_json::json!({ "state": "closed" }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_open_pulls(
+        &self,
+        owner: &str,
+        repo: &str,
+        base: &str,
+        head: &str,
+    ) -> Result<Vec<PullRequest>, ClientError> {
+        self.get(&format!(
+            "/repos/{}/{}/pulls?state=open&base={}&head={}",
+            owner,
+            repo,
+            encode_path(base),
+            encode_path(&format!("{}:{}", owner, head))
+        ))
+        .await
+    }
+
+    pub async fn create_pull(
+        &self,
+        owner: &str,
+        repo: &str,
+        title: &str,
+        head: &str,
+        base: &str,
+        body: &str,
+    ) -> Result<PullRequest, ClientError> {
+        self.post(
+            &format!("/repos/{}/{}/pulls", owner, repo),
+            &serde_json::json!({
+                "title": title,
+                "head": head,
+                "base": base,
+                "body": body,
+                "maintainer_can_modify": true
+            }),
+        )
+        .await
+    }
+
+    /// Newest first, following every page
+    pub async fn list_releases(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Vec<Release>, ClientError> {
+        self.get_pages(&format!("/repos/{}/{}/releases", owner, repo), 100)
+            .await
+    }
+
+    /// `make_latest` is one of `true`, `false` or `legacy`
+    pub async fn create_release(
+        &self,
+        owner: &str,
+        repo: &str,
+        tag: &str,
+        body: &str,
+        prerelease: bool,
+        make_latest: &str,
+    ) -> Result<Release, ClientError> {
+        self.post(
+            &format!("/repos/{}/{}/releases", owner, repo),
+            &serde_json::json!({
+                "tag_name": tag,
+                "name": tag,
+                "body": body,
+                "prerelease": prerelease,
+                "make_latest": make_latest
+            }),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_server_error_backoff() {
+        let policy = RetryPolicy::default();
+        let empty = HeaderMap::new();
+
+        assert_eq!(
+            policy.retry_delay(0, &Method::GET, StatusCode::BAD_GATEWAY, &empty, "", 0),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            policy.retry_delay(2, &Method::GET, StatusCode::BAD_GATEWAY, &empty, "", 0),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(
+            policy.retry_delay(
+                policy.max_retries,
+                &Method::GET,
+                StatusCode::BAD_GATEWAY,
+                &empty,
+                "",
+                0
+            ),
+            None
+        );
+        assert_eq!(
+            policy.retry_delay(0, &Method::GET, StatusCode::NOT_FOUND, &empty, "", 0),
+            None
+        );
+
+        // The request may have been applied before the server failed
+        assert_eq!(
+            policy.retry_delay(0, &Method::POST, StatusCode::BAD_GATEWAY, &empty, "", 0),
+            None
+        );
+        assert_eq!(
+            policy.retry_delay(0, &Method::PATCH, StatusCode::BAD_GATEWAY, &empty, "", 0),
+            None
+        );
+        assert_eq!(
+            policy.retry_delay(0, &Method::PUT, StatusCode::BAD_GATEWAY, &empty, "", 0),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_rate_limited_post_is_retried() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(
+            policy.retry_delay(
+                0,
+                &Method::POST,
+                StatusCode::TOO_MANY_REQUESTS,
+                &headers(&[("retry-after", "3")]),
+                "",
+                0
+            ),
+            Some(Duration::from_secs(3))
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_headers() {
+        let policy = RetryPolicy::default();
+
+        let retry_after = headers(&[("retry-after", "7")]);
+        assert_eq!(
+            policy.retry_delay(
+                0,
+                &Method::GET,
+                StatusCode::FORBIDDEN,
+                &retry_after,
+                "You have exceeded a secondary rate limit",
+                0
+            ),
+            Some(Duration::from_secs(7))
+        );
+
+        let reset = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1000"),
+        ]);
+        assert_eq!(
+            policy.retry_delay(0, &Method::GET, StatusCode::FORBIDDEN, &reset, "", 990),
+            Some(Duration::from_secs(10))
+        );
+        // Reset is too far in the future
+        assert_eq!(
+            policy.retry_delay(0, &Method::GET, StatusCode::FORBIDDEN, &reset, "", 0),
+            None
+        );
+
+        assert_eq!(
+            policy.retry_delay(
+                0,
+                &Method::GET,
+                StatusCode::FORBIDDEN,
+                &HeaderMap::new(),
+                "You have exceeded a secondary rate limit",
+                0
+            ),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            policy.retry_delay(
+                0,
+                &Method::GET,
+                StatusCode::FORBIDDEN,
+                &HeaderMap::new(),
+                "Resource not accessible by integration",
+                0
+            ),
+            None
+        );
+    }
+}
@@ -1,10 +1,19 @@
+use crate::{
+    client::{ClientError, GitHubClient, GitObject},
+    config::CommitMethod,
+};
+use base64::Engine;
+use derive_more::{Display, Error};
 use hmac::{Hmac, Mac};
-use octocrab::{params::repos::Reference, Octocrab};
-use serde::Serialize;
+use octocrab::params::repos::Reference;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
 use sha2::Sha256;
-use tracing::error;
+use std::collections::HashMap;
+use tracing::{error, warn};
 
 pub type HmacSha256 = Hmac<Sha256>;
+pub type HmacSha1 = Hmac<Sha1>;
 
 pub fn generate_hmac_sha256_hex(body: &[u8], key: &[u8]) -> Option<String> {
     let mut hasher = HmacSha256::new_from_slice(key).expect("Failed to create Hasher");
@@ -17,139 +26,501 @@ pub fn generate_hmac_sha256_hex(body: &[u8], key: &[u8]) -> Option<String> {
     Some(hex.to_owned())
 }
 
+/// Checks the hex encoded `signature` against the HMAC of `body` in constant time
+pub fn verify_hmac_sha256_hex(body: &[u8], key: &[u8], signature: &str) -> bool {
+    verify_hmac_hex::<HmacSha256>(body, key, signature)
+}
+
+/// Same as [`verify_hmac_sha256_hex`] with SHA-1, only used by legacy `X-Hub-Signature` headers
+pub fn verify_hmac_sha1_hex(body: &[u8], key: &[u8], signature: &str) -> bool {
+    verify_hmac_hex::<HmacSha1>(body, key, signature)
+}
+
+fn verify_hmac_hex<M: Mac + hmac::digest::KeyInit>(
+    body: &[u8],
+    key: &[u8],
+    signature: &str,
+) -> bool {
+    let mut decoded = [0u8; 64];
+    let Ok(signature) = base16ct::mixed::decode(signature, &mut decoded) else {
+        return false;
+    };
+
+    let mut hasher = <M as Mac>::new_from_slice(key).expect("Failed to create Hasher");
+    hasher.update(body);
+    hasher.verify_slice(signature).is_ok()
+}
+
+/// Joins `file` to the package directory `dir`, producing a path relative to repository root
+/// with `/` as separator regardless of the platform. An empty `dir` refers to repository root.
+pub fn repository_path(dir: &str, file: &str) -> String {
+    let dir = dir.trim_start_matches("./").trim_matches('/');
+    if dir.is_empty() || dir == "." {
+        file.to_owned()
+    } else {
+        format!("{}/{}", dir, file)
+    }
+}
+
+/// Percent-encodes everything except unreserved characters, for paths and query values in the
+/// API routes of every forge
+pub(crate) fn encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
 pub struct UpdateFiles<'a> {
-    gh: &'a Octocrab,
+    gh: &'a GitHubClient,
     files: Vec<File>,
     ref_: &'a Reference,
     commit_msg: String,
+    method: CommitMethod,
 }
 
 pub struct File {
+    /// Path of the file, relative to repository root
     pub name: String,
-    pub new_content: String,
+    /// Mode of the file. If `None`, the mode of the existing file is preserved (or
+    /// [`FileMode::Regular`] for new files)
+    pub mode: Option<FileMode>,
+    pub change: FileChange,
+}
+
+pub enum FileChange {
+    /// New content of the file as UTF-8 text
+    Text(String),
+    /// New content of the file as raw bytes, uploaded as base64
+    Binary(Vec<u8>),
+    /// The file is removed from the repository
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileMode {
+    /// `100644`
+    Regular,
+    /// `100755`
+    Executable,
+    /// `120000`, content of the file is the path the link points to
+    Symlink,
+}
+
+impl FileMode {
+    pub fn as_git_mode(&self) -> &'static str {
+        match self {
+            FileMode::Regular => "100644",
+            FileMode::Executable => "100755",
+            FileMode::Symlink => "120000",
+        }
+    }
+
+    /// Returns `None` for modes which aren't files, i.e. trees and submodules
+    pub fn from_git_mode(mode: &str) -> Option<Self> {
+        match mode {
+            "100644" => Some(FileMode::Regular),
+            "100755" => Some(FileMode::Executable),
+            "120000" => Some(FileMode::Symlink),
+            _ => None,
+        }
+    }
+}
+
+impl File {
+    pub fn text(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            mode: None,
+            change: FileChange::Text(content.into()),
+        }
+    }
+
+    pub fn binary(name: impl Into<String>, content: Vec<u8>) -> Self {
+        Self {
+            name: name.into(),
+            mode: None,
+            change: FileChange::Binary(content),
+        }
+    }
+
+    pub fn delete(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            mode: None,
+            change: FileChange::Delete,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: FileMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// The mode to write in the tree, given the mode of the file in the base tree (if it exists).
+    fn resolve_mode(&self, existing: Option<FileMode>) -> FileMode {
+        self.mode.or(existing).unwrap_or(FileMode::Regular)
+    }
+
+    fn content_bytes(&self) -> &[u8] {
+        match &self.change {
+            FileChange::Text(content) => content.as_bytes(),
+            FileChange::Binary(content) => content,
+            FileChange::Delete => &[],
+        }
+    }
 }
 
 impl<'a> UpdateFiles<'a> {
     pub fn new(
-        gh: &'a Octocrab,
+        gh: &'a GitHubClient,
         files: Vec<File>,
         ref_: &'a Reference,
         commit_msg: String,
+        method: CommitMethod,
     ) -> Self {
         Self {
             gh,
             files,
             ref_,
             commit_msg,
+            method,
+        }
+    }
+
+    /// Commits all the files on top of `base_commit_sha` and points the reference to the new
+    /// commit. Nothing is committed if any of the files fails to upload. Returns the sha of the
+    /// created commit.
+    pub async fn execute(
+        self,
+        owner: &str,
+        repo: &str,
+        base_commit_sha: &str,
+    ) -> Result<String, UpdateFilesError> {
+        if self.files.is_empty() {
+            return Err(UpdateFilesError::NoFiles);
+        }
+
+        match self.method {
+            CommitMethod::Rest => self.execute_rest(owner, repo, base_commit_sha).await,
+            CommitMethod::Graphql => self.execute_graphql(owner, repo, base_commit_sha).await,
         }
     }
 
-    pub async fn execute(self, owner: &str, repo: &str, base_commit_sha: &str) {
+    async fn execute_rest(
+        mut self,
+        owner: &str,
+        repo: &str,
+        base_commit_sha: &str,
+    ) -> Result<String, UpdateFilesError> {
         #[derive(Serialize, Debug)]
         struct BlobsTree {
             path: String,
-            mode: String,
-            r#type: String,
-            sha: serde_json::Value,
+            mode: &'static str,
+            r#type: &'static str,
+            /// `None` removes the file from the tree
+            sha: Option<String>,
         }
 
-        let mut blobs = Vec::new();
-
-        for file in self.files {
-            // Create the blob
-            match self
+        // Modes are only known by looking at the base tree
+        let existing_modes = if self.files.iter().any(|file| file.mode.is_none()) {
+            let tree = self
                 .gh
-                .post::<serde_json::Value, serde_json::Value>(
-                    format!("/repos/{}/{}/git/blobs", owner, repo),
-                    Some(&serde_json::json!({
-                        "content": file.new_content
-                    })),
-                )
+                .get_tree(owner, repo, base_commit_sha, true)
                 .await
-            {
-                Ok(mut res) => {
-                    blobs.push(BlobsTree {
-                        path: file.name,
-                        mode: String::from("100644"),
-                        r#type: String::from("blob"),
-                        sha: res["sha"].take(),
-                    });
-                }
-                Err(err) => {
-                    error!(
-                        "Failed to upload blob of file {} in repo {}/{}. Error: {}",
-                        file.name, owner, repo, err
-                    );
-                    continue;
+                .map_err(|source| {
+                    error!("Failed to get the base tree. Error: {}", source);
+                    UpdateFilesError::Tree { source }
+                })?;
+            if tree.truncated {
+                warn!(
+                    "Tree of {} in {}/{} is truncated, modes of some files may not be preserved",
+                    base_commit_sha, owner, repo
+                );
+            }
+
+            tree.tree
+                .into_iter()
+                .filter_map(|entry| Some((entry.path, FileMode::from_git_mode(&entry.mode)?)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        let mut blobs = Vec::new();
+
+        for file in std::mem::take(&mut self.files) {
+            let mode = file
+                .resolve_mode(existing_modes.get(&file.name).copied())
+                .as_git_mode();
+
+            let blob_content = match &file.change {
+                FileChange::Text(content) => Some(serde_json::json!({
+                    "content": content,
+                    "encoding": "utf-8"
+                })),
+                FileChange::Binary(content) => Some(serde_json::json!({
+                    "content": base64::prelude::BASE64_STANDARD.encode(content),
+                    "encoding": "base64"
+                })),
+                FileChange::Delete => None,
+            };
+
+            let sha = match blob_content {
+                Some(blob_content) => {
+                    // Create the blob
+                    let blob = self
+                        .gh
+                        .post::<_, GitObject>(
+                            &format!("/repos/{}/{}/git/blobs", owner, repo),
+                            &blob_content,
+                        )
+                        .await
+                        .map_err(|source| {
+                            error!(
+                                "Failed to upload blob of file {} in repo {}/{}. Error: {}",
+                                file.name, owner, repo, source
+                            );
+                            UpdateFilesError::Blob {
+                                path: file.name.clone(),
+                                source,
+                            }
+                        })?;
+                    Some(blob.sha)
                 }
+                None => None,
             };
-        }
 
-        if blobs.is_empty() {
-            return;
+            blobs.push(BlobsTree {
+                path: file.name,
+                mode,
+                r#type: "blob",
+                sha,
+            });
         }
 
         // Create a tree
-        let Ok(tree_res) = self
+        let tree = self
             .gh
-            .post::<serde_json::Value, serde_json::Value>(
-                format!("/repos/{}/{}/git/trees", owner, repo),
-                Some(&serde_json::json!({
+            .post::<_, GitObject>(
+                &format!("/repos/{}/{}/git/trees", owner, repo),
+                &serde_json::json!({
                     "base_tree": base_commit_sha,
                     "tree": blobs
-                })),
+                }),
             )
             .await
-        else {
-            error!("Failed to create tree. blobs: {:?}", blobs);
-            return;
-        };
-        let tree_sha = tree_res["sha"].as_str().unwrap_or_default();
+            .map_err(|source| {
+                error!(
+                    "Failed to create tree. blobs: {:?}, Error: {}",
+                    blobs, source
+                );
+                UpdateFilesError::Tree { source }
+            })?;
 
         // Create a commit
-        let commit_res = match self
+        let commit = self
             .gh
-            .post::<serde_json::Value, serde_json::Value>(
-                format!("/repos/{}/{}/git/commits", owner, repo),
-                Some(&serde_json::json!({
+            .post::<_, GitObject>(
+                &format!("/repos/{}/{}/git/commits", owner, repo),
+                &serde_json::json!({
                     "message": self.commit_msg,
-                    "tree": tree_sha,
+                    "tree": tree.sha,
                     "parents": [base_commit_sha]
-                })),
+                }),
             )
             .await
+            .map_err(|source| {
+                error!("Failed to create commit! Error: {}", source);
+                UpdateFilesError::Commit { source }
+            })?;
+
+        self.point_ref_to(owner, repo, self.ref_, &commit.sha)
+            .await?;
+
+        Ok(commit.sha)
+    }
+
+    /// Creates a single commit signed by GitHub with the `createCommitOnBranch` mutation. The
+    /// mutation can only commit on an existing branch, so the branch is first pointed to
+    /// `base_commit_sha`.
+    ///
+    /// The mutation can't set file modes, so files with an explicit mode other than
+    /// [`FileMode::Regular`] are rejected.
+    async fn execute_graphql(
+        self,
+        owner: &str,
+        repo: &str,
+        base_commit_sha: &str,
+    ) -> Result<String, UpdateFilesError> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            create_commit_on_branch: CreateCommitOnBranch,
+        }
+
+        #[derive(Deserialize)]
+        struct CreateCommitOnBranch {
+            commit: Commit,
+        }
+
+        #[derive(Deserialize)]
+        struct Commit {
+            oid: String,
+        }
+
+        if let Some(file) = self
+            .files
+            .iter()
+            .find(|file| file.mode.is_some_and(|mode| mode != FileMode::Regular))
         {
-            Ok(res) => res,
-            Err(err) => {
-                error!("Failed to create commit! Error: {}", err);
-                return;
+            return Err(UpdateFilesError::UnsupportedMode {
+                path: file.name.clone(),
+            });
+        }
+
+        // The mutation commits on top of a branch, so it goes on a temporary one. The branch is
+        // only created or moved once the commit succeeds, so that a failure neither leaves an
+        // empty branch behind nor a pull request without changes.
+        let commit_ref =
+            Reference::Branch(format!("release-butler-tmp/{}", self.ref_.branch_name()));
+        self.point_ref_to(owner, repo, &commit_ref, base_commit_sha)
+            .await?;
+
+        let (deletions, additions): (Vec<_>, Vec<_>) = self
+            .files
+            .iter()
+            .partition(|file| matches!(file.change, FileChange::Delete));
+
+        let additions: Vec<_> = additions
+            .into_iter()
+            .map(|file| {
+                serde_json::json!({
+                    "path": file.name,
+                    "contents": base64::prelude::BASE64_STANDARD.encode(file.content_bytes())
+                })
+            })
+            .collect();
+        let deletions: Vec<_> = deletions
+            .into_iter()
+            .map(|file| serde_json::json!({ "path": file.name }))
+            .collect();
+
+        let result = self
+            .gh
+            .graphql::<Data>(
+                "mutation ($input: CreateCommitOnBranchInput!) {
+                    createCommitOnBranch(input: $input) { commit { oid } }
+                }",
+                serde_json::json!({
+                    "input": {
+                        "branch": {
+                            "repositoryNameWithOwner": format!("{}/{}", owner, repo),
+                            "branchName": commit_ref.branch_name()
+                        },
+                        "message": { "headline": self.commit_msg },
+                        "fileChanges": { "additions": additions, "deletions": deletions },
+                        "expectedHeadOid": base_commit_sha
+                    }
+                }),
+            )
+            .await
+            .map_err(|source| {
+                error!(
+                    "Failed to create commit with GraphQL API! Error: {}",
+                    source
+                );
+                UpdateFilesError::Commit { source }
+            });
+
+        let moved = match &result {
+            Ok(data) => {
+                self.point_ref_to(
+                    owner,
+                    repo,
+                    self.ref_,
+                    &data.create_commit_on_branch.commit.oid,
+                )
+                .await
             }
+            Err(_) => Ok(()),
         };
-        let commit_sha = commit_res["sha"].as_str().unwrap_or_default();
+        if let Err(err) = self.gh.delete_ref(owner, repo, &commit_ref.ref_url()).await {
+            error!(
+                "Failed to delete the reference {}. Error: {}",
+                commit_ref.ref_url(),
+                err
+            );
+        }
+        moved?;
 
+        Ok(result?.create_commit_on_branch.commit.oid)
+    }
+
+    /// Points the reference to `sha`, creating it if it doesn't exist.
+    async fn point_ref_to(
+        &self,
+        owner: &str,
+        repo: &str,
+        ref_: &Reference,
+        sha: &str,
+    ) -> Result<(), UpdateFilesError> {
         // Check if the branch/reference already exists
-        let repos = self.gh.repos(owner, repo);
-        if repos.get_ref(self.ref_).await.is_err() {
-            // Create a branch/reference
-            if let Err(err) = repos.create_ref(self.ref_, commit_sha).await {
-                error!("Failed to create the reference. Error: {}", err);
+        let ref_url = ref_.ref_url();
+        let ref_error = |source| {
+            error!(
+                "Failed to create or update the reference {}. Error: {}",
+                ref_url, source
+            );
+            UpdateFilesError::Ref {
+                ref_: ref_url.clone(),
+                source,
             }
-        } else if let Err(err) = self
+        };
+
+        match self
             .gh
-            .patch::<serde_json::Value, String, serde_json::Value>(
-                format!("/repos/{}/{}/git/refs/{}", owner, repo, self.ref_.ref_url()),
-                Some(&serde_json::json!({
-                    "sha": commit_sha,
-                    "force": true
-                })),
-            )
+            .get_ref(owner, repo, &ref_url)
             .await
+            .map_err(ref_error)?
         {
-            error!("Failed to force update the ref. Error: {}", err);
-        }
+            // Create a branch/reference
+            None => self
+                .gh
+                .create_ref(owner, repo, &ref_url, sha)
+                .await
+                .map_err(ref_error)?,
+            Some(_) => self
+                .gh
+                .update_ref(owner, repo, &ref_url, sha)
+                .await
+                .map_err(ref_error)?,
+        };
+
+        Ok(())
     }
 }
 
+#[derive(Debug, Display, Error, Clone)]
+pub enum UpdateFilesError {
+    #[display("There are no files to commit")]
+    NoFiles,
+    #[display("Failed to upload blob of `{path}`. {source}")]
+    Blob { path: String, source: ClientError },
+    #[display("Failed to create tree. {source}")]
+    Tree { source: ClientError },
+    #[display("Failed to create commit. {source}")]
+    Commit { source: ClientError },
+    #[display("Failed to create or update reference `{ref_}`. {source}")]
+    Ref { ref_: String, source: ClientError },
+    #[display("The mode of `{path}` can't be set with the selected commit method")]
+    UnsupportedMode { path: String },
+}
+
 pub trait ReferenceExt {
     fn branch_name(&self) -> String;
 }
@@ -167,6 +538,55 @@ impl ReferenceExt for Reference {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_encode_path() {
+        assert_eq!(encode_path("group/project"), "group%2Fproject");
+        assert_eq!(
+            encode_path("crates/foo/Cargo.toml"),
+            "crates%2Ffoo%2FCargo.toml"
+        );
+        assert_eq!(
+            encode_path("release-butler/foo@1.2.3"),
+            "release-butler%2Ffoo%401.2.3"
+        );
+        assert_eq!(encode_path("a b+c"), "a%20b%2Bc");
+    }
+
+    #[test]
+    fn test_repository_path() {
+        assert_eq!(repository_path("", "Cargo.toml"), "Cargo.toml");
+        assert_eq!(repository_path(".", "Cargo.toml"), "Cargo.toml");
+        assert_eq!(
+            repository_path("crates/foo", "Cargo.toml"),
+            "crates/foo/Cargo.toml"
+        );
+        assert_eq!(
+            repository_path("./crates/foo/", "Cargo.toml"),
+            "crates/foo/Cargo.toml"
+        );
+    }
+
+    #[test]
+    fn test_file_mode() {
+        for mode in [FileMode::Regular, FileMode::Executable, FileMode::Symlink] {
+            assert_eq!(FileMode::from_git_mode(mode.as_git_mode()), Some(mode));
+        }
+        assert_eq!(FileMode::from_git_mode("040000"), None);
+
+        let file = File::text("scripts/version.sh", "echo 1.2.3");
+        assert_eq!(file.resolve_mode(None), FileMode::Regular);
+        assert_eq!(
+            file.resolve_mode(Some(FileMode::Executable)),
+            FileMode::Executable
+        );
+
+        let file = File::binary("logo.png", vec![0x89, 0x50]).with_mode(FileMode::Regular);
+        assert_eq!(
+            file.resolve_mode(Some(FileMode::Executable)),
+            FileMode::Regular
+        );
+    }
+
     #[test]
     fn test_hex() {
         let key = "abc123".as_bytes();
@@ -179,6 +599,29 @@ mod tests {
         assert_eq!(expected, actual)
     }
 
+    #[test]
+    fn test_verify_hmac_sha256_hex() {
+        let body = b"Hello, World!";
+        let key = b"abc";
+        let signature = generate_hmac_sha256_hex(body, key).unwrap();
+
+        assert!(verify_hmac_sha256_hex(body, key, &signature));
+        assert!(verify_hmac_sha256_hex(body, key, &signature.to_uppercase()));
+        assert!(!verify_hmac_sha256_hex(body, b"abd", &signature));
+        assert!(!verify_hmac_sha256_hex(body, key, &signature[..62]));
+        assert!(!verify_hmac_sha256_hex(body, key, "not hex"));
+    }
+
+    #[test]
+    fn test_verify_hmac_sha1_hex() {
+        let body = b"The quick brown fox jumps over the lazy dog";
+        let signature = "de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9";
+
+        assert!(verify_hmac_sha1_hex(body, b"key", signature));
+        assert!(!verify_hmac_sha1_hex(body, b"kex", signature));
+        assert!(!verify_hmac_sha256_hex(body, b"key", signature));
+    }
+
     #[cfg(feature = "tests")]
     #[test]
     fn test_json_signature() {
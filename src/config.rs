@@ -11,6 +11,48 @@ pub struct Config {
     pub packages: BTreeMap<String, Package>,
     /// Configuration Version
     pub version: u8,
+    /// The API used to commit the release changes
+    ///
+    /// (Default: `rest`)
+    #[serde(default = "defaults::commit_method")]
+    pub commit_method: CommitMethod,
+    /// Comment the changes on the release issue instead of committing them and opening the PR
+    ///
+    /// (Default: `false`)
+    #[serde(default = "defaults::false_")]
+    pub dry_run: bool,
+    /// Keep a single comment on the release issue with the progress of the release, edited as
+    /// each step completes, instead of commenting only on failure
+    ///
+    /// (Default: `false`)
+    #[serde(default = "defaults::false_")]
+    pub status_comment: bool,
+    /// Comment `Released in <tag>` on every issue and pull request referenced (`#123`) in the
+    /// release notes once the release is published
+    ///
+    /// (Default: `false`)
+    #[serde(default = "defaults::false_")]
+    pub notify_referenced_issues: bool,
+    /// Release the packages depending on the released ones (through path dependencies with a
+    /// version) in the same pull request, with their requirements updated and a patch bump.
+    /// Otherwise they are only listed in a comment.
+    ///
+    /// (Default: `false`)
+    #[serde(default = "defaults::false_")]
+    pub bump_dependents: bool,
+    /// Packages always released together at the same version, keyed by the name used in the
+    /// release issue
+    ///
+    /// (Default: no group)
+    #[serde(default)]
+    pub groups: BTreeMap<String, Group>,
+    /// Branches the release is based on, depending on the released version. The first pattern
+    /// matching the version is used, versions matching none of them are released from
+    /// `default_branch`.
+    ///
+    /// (Default: no pattern)
+    #[serde(default)]
+    pub branch_patterns: Vec<BranchPattern>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -49,6 +91,46 @@ pub struct Package {
     pub package_manager: PackageManager,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Group {
+    /// Names of the members in `packages`. Releasing the group or one of its members releases
+    /// every member.
+    pub packages: Vec<String>,
+    /// The path to a Changelog file shared by the members, written once per release instead of the
+    /// Changelog file of each member. This path must be relative to repository root.
+    ///
+    /// (Default: ``, i.e. the Changelog file of each member is appended with changes)
+    #[serde(default = "defaults::path")]
+    pub changelog_file: String,
+    /// Same as `changelog_file`, for pre-release versions
+    ///
+    /// (Default: ``)
+    #[serde(default = "defaults::path")]
+    pub pre_release_changelog_file: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BranchPattern {
+    /// Requirement on the released version, i.e. `1.x`. Pre-releases match the requirement of
+    /// their stable version.
+    pub versions: String,
+    /// Branch the matching releases are based on
+    pub branch: String,
+}
+
+impl BranchPattern {
+    /// Whether `version` is released from this branch, `false` if the requirement is invalid
+    pub fn matches(&self, version: &semver::Version) -> bool {
+        semver::VersionReq::parse(&self.versions).is_ok_and(|requirement| {
+            requirement.matches(&semver::Version::new(
+                version.major,
+                version.minor,
+                version.patch,
+            ))
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum PackageManager {
@@ -56,7 +138,18 @@ pub enum PackageManager {
     CargoWorkspace, // TODO: Support for more package manager
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitMethod {
+    /// Git Database REST API, i.e. one request per blob, tree, commit and reference
+    Rest,
+    /// GraphQL `createCommitOnBranch` mutation, which creates commits signed by GitHub
+    Graphql,
+}
+
 mod defaults {
+    use super::CommitMethod;
+
     pub fn unauthorized_author_comment() -> String {
         format!(
             "Hi, there you can't use the label `{}`, only some designated people are \
@@ -73,6 +166,10 @@ mod defaults {
     pub fn path() -> String {
         String::new()
     }
+
+    pub fn commit_method() -> CommitMethod {
+        CommitMethod::Rest
+    }
 }
 
 #[cfg(test)]
@@ -87,4 +184,26 @@ mod tests {
 
         assert_eq!(config.version, CONFIG_VERSION)
     }
+
+    #[test]
+    fn test_commit_method() {
+        let configuration = include_str!("../.github/release-butler.toml");
+        let config = toml::from_str::<Config>(configuration).unwrap();
+        assert_eq!(config.commit_method, CommitMethod::Rest);
+
+        let configuration = format!("commit_method = \"graphql\"\n{}", configuration);
+        let config = toml::from_str::<Config>(&configuration).unwrap();
+        assert_eq!(config.commit_method, CommitMethod::Graphql);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let configuration = include_str!("../.github/release-butler.toml");
+        let config = toml::from_str::<Config>(configuration).unwrap();
+        assert!(!config.dry_run);
+
+        let configuration = format!("dry_run = true\n{}", configuration);
+        let config = toml::from_str::<Config>(&configuration).unwrap();
+        assert!(config.dry_run);
+    }
 }
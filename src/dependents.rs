@@ -0,0 +1,272 @@
+//! Packages of the configuration depending on each other through Cargo path dependencies with a
+//! version requirement, so that the dependents of a package can be released along with it.
+
+use semver::Version;
+use std::collections::BTreeMap;
+use toml_edit::{DocumentMut, Item};
+
+/// Tables of a manifest listing dependencies
+static DEPENDENCY_TABLES: [&[&str]; 4] = [
+    &["dependencies"],
+    &["dev-dependencies"],
+    &["build-dependencies"],
+    &["workspace", "dependencies"],
+];
+
+/// A package of the configuration depending on another one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The dependent package, as named in the configuration
+    pub package: String,
+    /// The package depended on, as named in the configuration
+    pub dependency: String,
+    /// Version requirement, i.e. `0.1.0` or `=0.1.0`
+    pub requirement: String,
+}
+
+/// Dependencies between the packages of the configuration
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    pub dependencies: Vec<Dependency>,
+    /// Crate name of the packages, keyed by their name in the configuration
+    crate_names: BTreeMap<String, String>,
+}
+
+impl DependencyGraph {
+    /// Builds the graph from the manifests of the packages, keyed by their name in the
+    /// configuration. A package without `[package] name` goes by its name in the configuration.
+    pub fn new(manifests: &BTreeMap<String, String>) -> Self {
+        let mut documents = BTreeMap::new();
+        let mut crate_names = BTreeMap::new();
+        for (package, content) in manifests {
+            let Ok(document) = content.parse::<DocumentMut>() else {
+                continue;
+            };
+            let crate_name = document
+                .get("package")
+                .and_then(|package| package.get("name"))
+                .and_then(Item::as_str)
+                .unwrap_or(package);
+            crate_names.insert(package.clone(), crate_name.to_owned());
+            documents.insert(package, document);
+        }
+
+        let mut dependencies = Vec::new();
+        for (package, document) in &documents {
+            for (name, requirement) in path_dependencies(document) {
+                let Some((dependency, _)) = crate_names
+                    .iter()
+                    .find(|(other, crate_name)| **crate_name == name && other != package)
+                else {
+                    continue;
+                };
+                dependencies.push(Dependency {
+                    package: (*package).clone(),
+                    dependency: dependency.clone(),
+                    requirement,
+                });
+            }
+        }
+
+        Self {
+            dependencies,
+            crate_names,
+        }
+    }
+
+    pub fn crate_name<'a>(&'a self, package: &'a str) -> &'a str {
+        self.crate_names
+            .get(package)
+            .map_or(package, String::as_str)
+    }
+
+    /// Packages depending on one of `released` and not released themselves. With `transitive`,
+    /// also the packages depending on those dependents.
+    pub fn dependents(&self, released: &[&str], transitive: bool) -> Vec<String> {
+        let mut dependents: Vec<String> = Vec::new();
+        let mut queue: Vec<&str> = released.to_vec();
+
+        while let Some(package) = queue.pop() {
+            for dependency in self
+                .dependencies
+                .iter()
+                .filter(|dependency| dependency.dependency == package)
+            {
+                let dependent = dependency.package.as_str();
+                if released.contains(&dependent) || dependents.iter().any(|d| d == dependent) {
+                    continue;
+                }
+                dependents.push(dependent.to_owned());
+                if transitive {
+                    queue.push(dependent);
+                }
+            }
+        }
+
+        dependents
+    }
+}
+
+/// Name and version requirement of the path dependencies with a version in the manifest
+fn path_dependencies(document: &DocumentMut) -> Vec<(String, String)> {
+    let mut dependencies = Vec::new();
+
+    for path in DEPENDENCY_TABLES {
+        let Some(table) = get_path(document.as_item(), path).and_then(Item::as_table_like) else {
+            continue;
+        };
+        for (key, item) in table.iter() {
+            let Some(dependency) = item.as_table_like() else {
+                continue;
+            };
+            let (Some(_), Some(requirement)) = (
+                dependency.get("path"),
+                dependency.get("version").and_then(Item::as_str),
+            ) else {
+                continue;
+            };
+            let name = dependency
+                .get("package")
+                .and_then(Item::as_str)
+                .unwrap_or(key);
+            dependencies.push((name.to_owned(), requirement.to_owned()));
+        }
+    }
+
+    dependencies
+}
+
+fn get_path<'a>(item: &'a Item, path: &[&str]) -> Option<&'a Item> {
+    path.iter().try_fold(item, |item, key| item.get(key))
+}
+
+/// Sets the requirement of the path dependencies on `versions` (keyed by crate name) to their
+/// new version, keeping the operator of the requirement (i.e. `=0.1.0` to `=0.2.0`) and the
+/// formatting of the manifest
+pub fn update_requirements(
+    content: &str,
+    versions: &BTreeMap<String, Version>,
+) -> Result<String, toml_edit::TomlError> {
+    let mut document = content.parse::<DocumentMut>()?;
+
+    for path in DEPENDENCY_TABLES {
+        // `Item::get_mut` would insert the missing tables
+        let Some(table) = path
+            .iter()
+            .try_fold(document.as_item_mut(), |item, key| {
+                item.as_table_like_mut()?.get_mut(key)
+            })
+            .and_then(Item::as_table_like_mut)
+        else {
+            continue;
+        };
+        for (key, item) in table.iter_mut() {
+            let Some(dependency) = item.as_table_like_mut() else {
+                continue;
+            };
+            if dependency.get("path").is_none() {
+                continue;
+            }
+            let name = dependency
+                .get("package")
+                .and_then(Item::as_str)
+                .unwrap_or(key.get())
+                .to_owned();
+            let Some(version) = versions.get(&name) else {
+                continue;
+            };
+            let Some(value) = dependency.get_mut("version").and_then(Item::as_value_mut) else {
+                continue;
+            };
+            let Some(requirement) = value.as_str() else {
+                continue;
+            };
+
+            let decor = value.decor().clone();
+            *value = updated_requirement(requirement, version).into();
+            *value.decor_mut() = decor;
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// `requirement` with its version replaced by `version`, i.e. `^0.1` to `^0.2.0`
+fn updated_requirement(requirement: &str, version: &Version) -> String {
+    let operator_len = requirement.find(|c: char| c.is_ascii_digit()).unwrap_or(0);
+    format!("{}{}", &requirement[..operator_len], version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifests() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (
+                String::from("core"),
+                String::from("[package]\nname = \"example-core\"\nversion = \"0.1.0\"\n"),
+            ),
+            (
+                String::from("macros"),
+                String::from(
+                    "[package]\nname = \"example-macros\"\nversion = \"0.1.0\"\n\n\
+                    [dev-dependencies]\nexample-core = { path = \"../core\", version = \"0.1.0\" }\n",
+                ),
+            ),
+            (
+                String::from("cli"),
+                String::from(
+                    "[package]\nname = \"example-cli\"\nversion = \"1.0.0\"\n\n\
+                    [dependencies]\n\
+                    macros = { package = \"example-macros\", path = \"../macros\", version = \"=0.1.0\" }\n\
+                    serde = \"1\"\n\
+                    local = { path = \"../local\" }\n",
+                ),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_dependents() {
+        let graph = DependencyGraph::new(&manifests());
+        assert_eq!(graph.crate_name("core"), "example-core");
+        assert_eq!(
+            graph.dependencies,
+            [
+                Dependency {
+                    package: String::from("cli"),
+                    dependency: String::from("macros"),
+                    requirement: String::from("=0.1.0"),
+                },
+                Dependency {
+                    package: String::from("macros"),
+                    dependency: String::from("core"),
+                    requirement: String::from("0.1.0"),
+                },
+            ]
+        );
+
+        assert_eq!(graph.dependents(&["core"], false), ["macros"]);
+        assert_eq!(graph.dependents(&["core"], true), ["macros", "cli"]);
+        assert_eq!(graph.dependents(&["core", "macros"], true), ["cli"]);
+        assert!(graph.dependents(&["cli"], true).is_empty());
+    }
+
+    #[test]
+    fn test_update_requirements() {
+        let versions = BTreeMap::from([(String::from("example-macros"), Version::new(0, 2, 0))]);
+        assert_eq!(
+            update_requirements(&manifests()["cli"], &versions).unwrap(),
+            "[package]\nname = \"example-cli\"\nversion = \"1.0.0\"\n\n\
+            [dependencies]\n\
+            macros = { package = \"example-macros\", path = \"../macros\", version = \"=0.2.0\" }\n\
+            serde = \"1\"\n\
+            local = { path = \"../local\" }\n"
+        );
+        assert_eq!(
+            updated_requirement("^0.1", &Version::new(0, 2, 0)),
+            "^0.2.0"
+        );
+    }
+}
@@ -0,0 +1,670 @@
+//! Slash commands, i.e. `/release-butler retry`, posted by allowed authors on a release issue or
+//! its pull request.
+
+use super::{
+    issues::{find_dependents, prepare_release, preview_release, report_failure, ReleaseIssue},
+    pulls::fixed_issue,
+    *,
+};
+use crate::{
+    client::ClientError,
+    config::Config,
+    forge::{Forge, GitHubForge, Reaction},
+    release,
+    webhook::{generate_gh_from_event, get_config},
+};
+use chrono::{DateTime, Utc};
+use octocrab::models::webhook_events::{
+    payload::IssueCommentWebhookEventAction, WebhookEvent, WebhookEventPayload,
+};
+use semver::Version;
+use tracing::error;
+
+/// Prefix of the lines of a comment addressed to release-butler
+pub static COMMAND_PREFIX: &str = "/release-butler";
+
+pub struct CommentsHandler<'a> {
+    payload: &'a WebhookEvent,
+    state: &'a State,
+    repository: (&'a str, &'a str),
+}
+
+/// Part of the version incremented by `/release-butler bump`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl Bump {
+    /// Next version after `current`. A prerelease is bumped to its release when possible, i.e.
+    /// `1.3.0-rc.1` to `1.3.0` for `Minor`.
+    pub fn apply(self, current: &Version) -> Version {
+        let prerelease = !current.pre.is_empty();
+        match self {
+            Bump::Major if prerelease && current.minor == 0 && current.patch == 0 => {
+                Version::new(current.major, 0, 0)
+            }
+            Bump::Major => Version::new(current.major + 1, 0, 0),
+            Bump::Minor if prerelease && current.patch == 0 => {
+                Version::new(current.major, current.minor, 0)
+            }
+            Bump::Minor => Version::new(current.major, current.minor + 1, 0),
+            Bump::Patch if prerelease => Version::new(current.major, current.minor, current.patch),
+            Bump::Patch => Version::new(current.major, current.minor, current.patch + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Rebuilds the release branch on top of the default branch
+    Retry,
+    /// Closes the release pull request and deletes the release branch
+    Cancel,
+    /// Releases the next version after the one in the manifest instead
+    Bump(Bump),
+    /// Comments the changes the release would commit
+    Preview,
+}
+
+impl Command {
+    /// Parses the first line of `comment` starting with [`COMMAND_PREFIX`]. Returns `None` if the
+    /// comment isn't addressed to release-butler, and the unknown command as `Err`.
+    pub fn parse(comment: &str) -> Option<Result<Self, String>> {
+        let line = comment
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(COMMAND_PREFIX))?;
+        // i.e. `/release-butlers`
+        if !line.is_empty() && !line.starts_with(char::is_whitespace) {
+            return None;
+        }
+
+        let args: Vec<_> = line.split_whitespace().collect();
+        let command = match args.as_slice() {
+            ["retry"] => Command::Retry,
+            ["cancel"] => Command::Cancel,
+            ["preview"] => Command::Preview,
+            ["bump", "major"] => Command::Bump(Bump::Major),
+            ["bump", "minor"] => Command::Bump(Bump::Minor),
+            ["bump", "patch"] => Command::Bump(Bump::Patch),
+            _ => return Some(Err(args.join(" "))),
+        };
+        Some(Ok(command))
+    }
+}
+
+/// A comment on a release issue or pull request, independent of the forge it was posted on
+#[derive(Debug, Clone)]
+pub struct ReleaseComment {
+    pub id: u64,
+    /// Number of the issue or pull request commented on
+    pub number: u64,
+    pub on_pull: bool,
+    pub body: String,
+    pub author: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait::async_trait]
+impl<'a> Handler<'a> for CommentsHandler<'a> {
+    fn new(repository: (&'a str, &'a str), payload: &'a WebhookEvent, state: &'a State) -> Self {
+        Self {
+            payload,
+            state,
+            repository,
+        }
+    }
+
+    async fn execute(&self) -> Result<HttpResponse, WebhookError> {
+        let WebhookEventPayload::IssueComment(event) = &self.payload.specific else {
+            error!("Got an event, with inner payload not an issue comment. Ignoring the event...");
+            return Err(WebhookError::MalformatedBody {
+                msg: String::from("Inner Payload must be of issue_comment event"),
+            });
+        };
+
+        if event.action != IssueCommentWebhookEventAction::Created {
+            return Err(WebhookError::UnsupportedEvent);
+        }
+
+        let comment_body = event.comment.body.clone().unwrap_or_default();
+        if Command::parse(&comment_body).is_none() {
+            return Ok(HttpResponse::Ok().finish());
+        }
+
+        let gh = generate_gh_from_event(self.payload, self.state)?;
+        let forge = GitHubForge::new(gh, self.repository.0, self.repository.1);
+
+        let comment = ReleaseComment {
+            id: event.comment.id.into_inner(),
+            number: event.issue.number,
+            on_pull: event.issue.pull_request.is_some(),
+            body: comment_body,
+            author: event.comment.user.login.clone(),
+            created_at: event.comment.created_at,
+        };
+
+        let issue = if comment.on_pull {
+            // Commands on the release PR act on the issue it fixes
+            let is_release_pull = event.issue.title.starts_with("RELEASE ");
+            let Some(number) = event
+                .issue
+                .body
+                .as_deref()
+                .filter(|_| is_release_pull)
+                .and_then(fixed_issue)
+            else {
+                return Ok(HttpResponse::Ok().finish());
+            };
+
+            match forge.get_issue(number).await {
+                Ok(issue) => ReleaseIssue {
+                    number,
+                    title: issue.title,
+                    body: issue.body.unwrap_or_default(),
+                    author: issue.user.login,
+                    updated_at: comment.created_at,
+                },
+                Err(err) => {
+                    error!(
+                        "Failed to fetch the release issue #{} of PR #{}. Error: {}",
+                        number, comment.number, err
+                    );
+                    return Ok(HttpResponse::InternalServerError().finish());
+                }
+            }
+        } else {
+            if !event
+                .issue
+                .labels
+                .iter()
+                .any(|label| label.name == crate::RELEASE_ISSUE_LABEL)
+            {
+                return Ok(HttpResponse::Ok().finish());
+            }
+
+            ReleaseIssue {
+                number: event.issue.number,
+                title: event.issue.title.clone(),
+                body: event.issue.body.clone().unwrap_or_default(),
+                author: event.issue.user.login.clone(),
+                updated_at: comment.created_at,
+            }
+        };
+
+        Ok(handle_command(&forge, &self.state.app_username, &issue, &comment).await)
+    }
+}
+
+async fn react(forge: &dyn Forge, comment: &ReleaseComment, reaction: Reaction) {
+    let result = if comment.on_pull {
+        forge
+            .react_on_pull(comment.number, comment.id, reaction)
+            .await
+    } else {
+        forge.react(comment.number, comment.id, reaction).await
+    };
+
+    if let Err(err) = result {
+        error!(
+            "Failed to react to comment {} on #{}. Error: {}",
+            comment.id, comment.number, err
+        );
+    }
+}
+
+/// Runs the command in `comment` on the release requested by `issue`. Comments of authors not in
+/// `allowed_authors` are ignored. The comment gets a reaction once the command is accepted,
+/// problems are reported as comments on the issue.
+pub async fn handle_command(
+    forge: &dyn Forge,
+    app_username: &str,
+    issue: &ReleaseIssue,
+    comment: &ReleaseComment,
+) -> HttpResponse {
+    let Some(command) = Command::parse(&comment.body) else {
+        return HttpResponse::Ok().finish();
+    };
+
+    let config = match get_config(forge, app_username).await {
+        Ok(Some(config)) => config,
+        Ok(None) => return HttpResponse::InternalServerError().finish(),
+        Err(err) => {
+            report_failure(forge, issue.number, "fetch the configuration", &err).await;
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    if !config
+        .issues_meta_data
+        .allowed_authors
+        .iter()
+        .any(|author| author.to_lowercase() == comment.author.to_lowercase())
+    {
+        react(forge, comment, Reaction::ThumbsDown).await;
+        return HttpResponse::Ok().finish();
+    }
+
+    let command = match command {
+        Ok(command) => command,
+        Err(unknown) => {
+            react(forge, comment, Reaction::Confused).await;
+            if let Err(err) = forge
+                .comment(
+                    issue.number,
+                    &format!(
+                        "I don't know the command `{}`. The following commands are supported:\n\
+                        - `{prefix} retry`: rebuild the release branch\n\
+                        - `{prefix} cancel`: close the release PR and delete its branch\n\
+                        - `{prefix} bump major|minor|patch`: release the next major, minor or patch version instead\n\
+                        - `{prefix} preview`: comment the changes that would be committed",
+                        unknown,
+                        prefix = COMMAND_PREFIX
+                    ),
+                )
+                .await
+            {
+                error!(
+                    "Failed to comment on issue #{} regarding an unknown command. Error: {}",
+                    issue.number, err
+                );
+            }
+            return HttpResponse::Ok().finish();
+        }
+    };
+
+    react(forge, comment, Reaction::ThumbsUp).await;
+
+    match command {
+        Command::Retry => prepare_release(forge, app_username, issue).await,
+        Command::Preview => preview_release(forge, app_username, issue).await,
+        Command::Cancel => {
+            if let Err(err) = close_release_pull(forge, &config, issue).await {
+                report_failure(forge, issue.number, "cancel the release", &err).await;
+                return HttpResponse::Ok().finish();
+            }
+
+            // Otherwise the next edit of the issue opens the PR again
+            if let Err(err) = forge
+                .remove_label(issue.number, crate::RELEASE_ISSUE_LABEL)
+                .await
+            {
+                error!(
+                    "Failed to remove the label `{}` on issue #{}. Error: {}",
+                    crate::RELEASE_ISSUE_LABEL,
+                    issue.number,
+                    err
+                );
+            }
+
+            HttpResponse::Ok().finish()
+        }
+        Command::Bump(bump) => bump_release(forge, app_username, &config, issue, bump).await,
+    }
+}
+
+/// Closes the open pull request of the release requested by `issue` and deletes its branch
+async fn close_release_pull(
+    forge: &dyn Forge,
+    config: &Config,
+    issue: &ReleaseIssue,
+) -> Result<(), ClientError> {
+    let Ok(releases) = issue.releases() else {
+        return Ok(());
+    };
+    // Same as the branch opened by `prepare_release`
+    let mut releases = release::expand_groups(config, &releases);
+    let base = release::base_branch(config, issue.base(), &releases);
+    let dependents = find_dependents(forge, config, base, &releases).await?;
+    releases.extend(dependents.bumped);
+    let branch = release::batch_branch(&releases);
+
+    if let Some(pull) = forge.find_open_pull(&branch, base).await? {
+        forge.close_pull(pull.number).await?;
+    }
+    if forge.branch_head(&branch).await?.is_some() {
+        forge.delete_branch(&branch).await?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the release requested by `issue` with the `bump` of the version in the manifest,
+/// renaming the issue accordingly
+async fn bump_release(
+    forge: &dyn Forge,
+    app_username: &str,
+    config: &Config,
+    issue: &ReleaseIssue,
+    bump: Bump,
+) -> HttpResponse {
+    let Ok(releases) = issue.releases() else {
+        // Explains the title format
+        return prepare_release(forge, app_username, issue).await;
+    };
+    let [(package, requested)] = releases.as_slice() else {
+        if let Err(err) = forge
+            .comment(
+                issue.number,
+                "This issue releases several packages, so I can't tell which version to bump. Edit the \
+                versions in the issue instead.",
+            )
+            .await
+        {
+            error!(
+                "Failed to comment on issue #{} regarding a bump of several packages. Error: {}",
+                issue.number, err
+            );
+        }
+        return HttpResponse::Ok().finish();
+    };
+    // Members of a group share the version
+    let member = release::find_group(config, package)
+        .and_then(|(_, group)| group.packages.first())
+        .unwrap_or(package);
+    let package_information = if member.is_empty() {
+        config.packages.values().next()
+    } else {
+        config.packages.get(member)
+    };
+    let Some(package_information) = package_information else {
+        // Explains that the package doesn't exist
+        return prepare_release(forge, app_username, issue).await;
+    };
+
+    let manifest_path = release::manifest_path(package_information);
+    let base = release::base_branch(config, issue.base(), &releases);
+    let current = match forge.read_branch_file(base, &manifest_path).await {
+        Ok(content) => {
+            content.and_then(|content| release::manifest_version(package_information, &content))
+        }
+        Err(err) => {
+            report_failure(
+                forge,
+                issue.number,
+                &format!("fetch `{}`", manifest_path),
+                &err,
+            )
+            .await;
+            return HttpResponse::Ok().finish();
+        }
+    };
+    let Some(current) = current else {
+        if let Err(err) = forge
+            .comment(
+                issue.number,
+                &format!(
+                    "I couldn't find the current version in `{}`, so I can't bump it.",
+                    manifest_path
+                ),
+            )
+            .await
+        {
+            error!(
+                "Failed to comment on issue #{} regarding a missing version. Error: {}",
+                issue.number, err
+            );
+        }
+        return HttpResponse::Ok().finish();
+    };
+
+    let version = bump.apply(&current);
+    if version == *requested {
+        return prepare_release(forge, app_username, issue).await;
+    }
+
+    if let Err(err) = close_release_pull(forge, config, issue).await {
+        report_failure(
+            forge,
+            issue.number,
+            &format!("cancel the release of {}", requested),
+            &err,
+        )
+        .await;
+        return HttpResponse::Ok().finish();
+    }
+
+    let title = release::tag_name(package, &version);
+    if let Err(err) = forge.update_issue_title(issue.number, &title).await {
+        report_failure(forge, issue.number, "rename the issue", &err).await;
+        return HttpResponse::Ok().finish();
+    }
+
+    let issue = ReleaseIssue {
+        title,
+        ..issue.clone()
+    };
+    prepare_release(forge, app_username, &issue).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::memory::InMemoryForge;
+
+    static CONFIG: &str = r#"
+        version = 1
+        default_branch = "main"
+
+        [issues_meta_data]
+        allowed_authors = ["jdoe"]
+
+        [packages.example]
+        changelog_file = "CHANGELOG.md"
+        package_manager = "cargo"
+    "#;
+
+    fn forge() -> InMemoryForge {
+        let forge = InMemoryForge::with_files(&[
+            (".github/release-butler.toml", CONFIG),
+            (
+                "Cargo.toml",
+                "[package]\nname = \"example\"\nversion = \"1.2.2\"\n",
+            ),
+        ]);
+        forge.open_issue(7, "v1.2.3", "- Fix", "jdoe", &[crate::RELEASE_ISSUE_LABEL]);
+        forge
+    }
+
+    fn release_issue(forge: &InMemoryForge) -> ReleaseIssue {
+        let repository = forge.repository();
+        let issue = &repository.issues[&7];
+        ReleaseIssue {
+            number: 7,
+            title: issue.title.clone(),
+            body: issue.body.clone(),
+            author: issue.author.clone(),
+            updated_at: "2025-03-12T10:00:00Z".parse().unwrap(),
+        }
+    }
+
+    fn comment(body: &str, author: &str) -> ReleaseComment {
+        ReleaseComment {
+            id: 42,
+            number: 7,
+            on_pull: false,
+            body: body.to_owned(),
+            author: author.to_owned(),
+            created_at: "2025-03-12T11:00:00Z".parse().unwrap(),
+        }
+    }
+
+    async fn run(forge: &InMemoryForge, body: &str, author: &str) {
+        handle_command(
+            forge,
+            "app/release-butler",
+            &release_issue(forge),
+            &comment(body, author),
+        )
+        .await;
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            Command::parse("/release-butler retry"),
+            Some(Ok(Command::Retry))
+        );
+        assert_eq!(
+            Command::parse("Looks wrong\n  /release-butler   bump minor  \nthanks"),
+            Some(Ok(Command::Bump(Bump::Minor)))
+        );
+        assert_eq!(
+            Command::parse("/release-butler bump 2.0.0"),
+            Some(Err(String::from("bump 2.0.0")))
+        );
+        assert_eq!(Command::parse("/release-butler"), Some(Err(String::new())));
+        assert_eq!(Command::parse("/release-butlers retry"), None);
+        assert_eq!(Command::parse("LGTM"), None);
+    }
+
+    #[test]
+    fn test_apply_bump() {
+        let version = |v: &str| Version::parse(v).unwrap();
+        assert_eq!(Bump::Major.apply(&version("1.2.3")), version("2.0.0"));
+        assert_eq!(Bump::Minor.apply(&version("1.2.3")), version("1.3.0"));
+        assert_eq!(Bump::Patch.apply(&version("1.2.3")), version("1.2.4"));
+        assert_eq!(Bump::Major.apply(&version("2.0.0-rc.1")), version("2.0.0"));
+        assert_eq!(Bump::Minor.apply(&version("1.3.0-rc.1")), version("1.3.0"));
+        assert_eq!(Bump::Minor.apply(&version("1.3.1-rc.1")), version("1.4.0"));
+        assert_eq!(Bump::Patch.apply(&version("1.3.1-rc.1")), version("1.3.1"));
+    }
+
+    #[actix_web::test]
+    async fn test_retry() {
+        let forge = forge();
+        run(&forge, "/release-butler retry", "jdoe").await;
+
+        let repository = forge.repository();
+        assert_eq!(repository.pulls.len(), 1);
+        assert_eq!(
+            repository
+                .file("release-butler/@1.2.3", "CHANGELOG.md")
+                .unwrap(),
+            "# Changelog\n\n## [1.2.3] - 2025-03-12\n- Fix"
+        );
+        assert_eq!(repository.reactions[&42], [Reaction::ThumbsUp]);
+    }
+
+    #[actix_web::test]
+    async fn test_unauthorized_author() {
+        let forge = forge();
+        run(&forge, "/release-butler cancel", "mallory").await;
+
+        let repository = forge.repository();
+        assert_eq!(repository.reactions[&42], [Reaction::ThumbsDown]);
+        assert!(repository.issues[&7]
+            .labels
+            .contains(&crate::RELEASE_ISSUE_LABEL.to_owned()));
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_command() {
+        let forge = forge();
+        run(&forge, "/release-butler ship it", "jdoe").await;
+
+        let repository = forge.repository();
+        assert_eq!(repository.reactions[&42], [Reaction::Confused]);
+        assert!(repository.comments[&7][0].contains("I don't know the command `ship it`"));
+    }
+
+    #[actix_web::test]
+    async fn test_cancel() {
+        let forge = forge();
+        run(&forge, "/release-butler retry", "jdoe").await;
+        run(&forge, "/release-butler cancel", "jdoe").await;
+
+        let repository = forge.repository();
+        assert!(repository.pulls[0].closed);
+        assert!(!repository.branches.contains_key("release-butler/@1.2.3"));
+        assert!(repository.issues[&7].labels.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_cancel_with_bumped_dependents() {
+        let forge = InMemoryForge::with_files(&[
+            (
+                ".github/release-butler.toml",
+                r#"
+                version = 1
+                default_branch = "main"
+                bump_dependents = true
+
+                [issues_meta_data]
+                allowed_authors = ["jdoe"]
+
+                [packages.core]
+                path = "core"
+                package_manager = "cargo"
+
+                [packages.cli]
+                path = "cli"
+                package_manager = "cargo"
+                "#,
+            ),
+            (
+                "core/Cargo.toml",
+                "[package]\nname = \"example-core\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                "cli/Cargo.toml",
+                "[package]\nname = \"example-cli\"\nversion = \"1.0.0\"\n\n\
+                [dependencies]\nexample-core = { path = \"../core\", version = \"0.1.0\" }\n",
+            ),
+        ]);
+        forge.open_issue(
+            7,
+            "core@0.2.0",
+            "- Fix",
+            "jdoe",
+            &[crate::RELEASE_ISSUE_LABEL],
+        );
+        run(&forge, "/release-butler retry", "jdoe").await;
+        assert_eq!(
+            forge.repository().pulls[0].head,
+            "release-butler/core@0.2.0+cli@1.0.1"
+        );
+
+        run(&forge, "/release-butler cancel", "jdoe").await;
+
+        let repository = forge.repository();
+        assert!(repository.pulls[0].closed);
+        assert!(!repository
+            .branches
+            .contains_key("release-butler/core@0.2.0+cli@1.0.1"));
+    }
+
+    #[actix_web::test]
+    async fn test_bump() {
+        let forge = forge();
+        run(&forge, "/release-butler retry", "jdoe").await;
+        run(&forge, "/release-butler bump minor", "jdoe").await;
+
+        let repository = forge.repository();
+        assert_eq!(repository.issues[&7].title, "v1.3.0");
+        assert!(repository.pulls[0].closed);
+        assert!(!repository.branches.contains_key("release-butler/@1.2.3"));
+        assert_eq!(repository.pulls[1].title, "RELEASE @v1.3.0");
+        assert!(!repository.pulls[1].closed);
+        assert_eq!(
+            repository
+                .file("release-butler/@1.3.0", "Cargo.toml")
+                .unwrap(),
+            "[package]\nname = \"example\"\nversion = \"1.3.0\"\n"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_preview() {
+        let forge = forge();
+        run(&forge, "/release-butler preview", "jdoe").await;
+
+        let repository = forge.repository();
+        assert!(repository.pulls.is_empty());
+        let comment = &repository.comments[&7][0];
+        assert!(comment.starts_with("**Preview**"));
+        assert!(comment.contains("+version = \"1.2.3\"\n"));
+    }
+}
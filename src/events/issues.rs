@@ -1,16 +1,20 @@
-use super::*;
+use super::{comments::Bump, *};
 use crate::{
-    common::{File, ReferenceExt, UpdateFiles},
-    config::PackageManager,
+    client::ClientError,
+    common::File,
+    config::{Config, Package},
+    dependents::{self, DependencyGraph},
+    forge::{Forge, GitHubForge},
+    issue_form::IssueForm,
+    release::{self, FileEdit},
+    status::{Status, StatusComment, Step},
     webhook::{generate_gh_from_event, get_config},
 };
-use octocrab::{
-    models::webhook_events::{
-        payload::IssuesWebhookEventAction, WebhookEvent, WebhookEventPayload,
-    },
-    params::repos::Reference,
+use chrono::{DateTime, Utc};
+use octocrab::models::webhook_events::{
+    payload::IssuesWebhookEventAction, WebhookEvent, WebhookEventPayload,
 };
-use std::path::PathBuf;
+use std::{collections::BTreeMap, fmt::Display};
 use tracing::error;
 
 pub struct IssuesHandler<'a> {
@@ -19,6 +23,145 @@ pub struct IssuesHandler<'a> {
     repository: (&'a str, &'a str),
 }
 
+/// An issue requesting a release, independent of the forge it was opened on
+#[derive(Debug, Clone)]
+pub struct ReleaseIssue {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    pub author: String,
+    /// Used as the release date in the changelog
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ReleaseIssue {
+    /// Packages and versions requested by the title (i.e. `v1.2.3` or `foo@1.2.3, bar@0.4.0`).
+    /// If the title doesn't follow the format (i.e. `Release`), by the issue form or the
+    /// `foo@1.2.3` lines starting the body.
+    pub fn releases(&self) -> Result<Vec<(String, semver::Version)>, semver::Error> {
+        let err = match parse_releases(&self.title) {
+            Ok(releases) => {
+                return Ok(releases
+                    .into_iter()
+                    .map(|(package, version)| (package.to_owned(), version))
+                    .collect())
+            }
+            Err(err) => err,
+        };
+
+        let body = base_line(&self.body).1;
+        if let Some(form) = IssueForm::parse(body) {
+            let version = form.version.strip_prefix('v').unwrap_or(&form.version);
+            return Ok(vec![(
+                form.package.clone(),
+                semver::Version::parse(version)?,
+            )]);
+        }
+
+        let (releases, _) = release_lines(body);
+        if releases.is_empty() {
+            return Err(err);
+        }
+        Ok(releases
+            .into_iter()
+            .map(|(package, version)| (package.to_owned(), version))
+            .collect())
+    }
+
+    /// Changes of the release, the changelog field if the issue was opened with the issue form
+    pub fn notes(&self) -> String {
+        release_notes(&self.body)
+    }
+
+    /// Branch to base the release on, from a `base: release/1.x` line starting the body
+    pub fn base(&self) -> Option<&str> {
+        base_line(&self.body).0
+    }
+}
+
+/// Changes of the release in the body of the release issue, without the `base:` and
+/// `foo@1.2.3` lines starting it. See [`ReleaseIssue::notes`].
+pub fn release_notes(body: &str) -> String {
+    let body = base_line(body).1;
+    match IssueForm::parse(body) {
+        Some(form) => form.changelog,
+        None => release_lines(body).1.to_owned(),
+    }
+}
+
+/// The branch of the `base: release/1.x` line starting `body`, and the rest of the body
+fn base_line(body: &str) -> (Option<&str>, &str) {
+    let trimmed = body.trim_start();
+    let (line, rest) = trimmed.split_once('\n').unwrap_or((trimmed, ""));
+    match line.trim().strip_prefix("base:").map(str::trim) {
+        Some(branch) if !branch.is_empty() => (Some(branch), rest.trim_start()),
+        _ => (None, body),
+    }
+}
+
+/// The `foo@1.2.3` (or `- foo@1.2.3`) lines starting `body`, and the rest of the body
+fn release_lines(body: &str) -> (Vec<(&str, semver::Version)>, &str) {
+    let mut releases = Vec::new();
+    let mut rest = body;
+
+    for line in body.split_inclusive('\n') {
+        let release = line.trim();
+        let release = release
+            .strip_prefix("- ")
+            .or_else(|| release.strip_prefix("* "))
+            .unwrap_or(release);
+        if !release.is_empty() {
+            match parse_issue_title(release) {
+                Ok((package, version)) if !package.is_empty() => releases.push((package, version)),
+                _ => break,
+            }
+        }
+        rest = &rest[line.len()..];
+    }
+
+    if releases.is_empty() {
+        (releases, body)
+    } else {
+        (releases, rest.trim_start())
+    }
+}
+
+/// Comments on the release issue about an API failure, so that failures don't go unnoticed
+pub(super) async fn report_failure(
+    forge: &dyn Forge,
+    issue_number: u64,
+    step: &str,
+    err: &impl Display,
+) {
+    error!(
+        "Failed to {} for issue #{}. Error: {}",
+        step, issue_number, err
+    );
+
+    if let Err(comment_err) = forge
+        .comment(
+            issue_number,
+            &format!(
+                "I failed to {} even after retrying, so the release has been stopped.\n\n\
+                <details>\n\
+                <summary>Error</summary>\n\n\
+                ```\n{}\n```\n\
+                </details>\n\n\
+                Edit the issue or re-add the `{}` label to try again.",
+                step,
+                err,
+                crate::RELEASE_ISSUE_LABEL
+            ),
+        )
+        .await
+    {
+        error!(
+            "Failed to report failure on issue #{}. Error: {}",
+            issue_number, comment_err
+        );
+    }
+}
+
 #[async_trait::async_trait]
 impl<'a> Handler<'a> for IssuesHandler<'a> {
     fn new(repository: (&'a str, &'a str), payload: &'a WebhookEvent, state: &'a State) -> Self {
@@ -49,404 +192,752 @@ impl<'a> Handler<'a> for IssuesHandler<'a> {
                     return Ok(HttpResponse::Ok().finish());
                 }
 
-                let gh = generate_gh_from_event(self.payload, &self.state.gh)?;
-                let issues_handler = gh.issues(self.repository.0, self.repository.1);
-
-                let Ok((package, version)) = parse_issue_title(&issues.issue.title) else {
-                    if let Err(err) = issues_handler
-                        .create_comment(
-                            issues.issue.number,
-                            format!("\
-                                The title `{}` doesn't follow the required format. The title must follow one of the \
-                                following patterns:\n\
-                                - `v1.2.3`\n\
-                                - `1.2.3`\n\
-                                - `package@v1.2.3`\n\
-                                - `package@1.2.3`\n\n\
-                                Prerelease and build metadata are supported: (e.g., `v1.2.3-beta.1+1234`)\n\n\
-                                **The `release-butler` label is reserved for automated release management.**\n\n\
-                                <details>\n\
-                                <summary>Action taken</summary>\n\
-                                The `release-butler` label has been removed. You can add it back once the title format is correct.\n\
-                                </details>",
-                                &issues.issue.title
-                            ),
-                        )
-                        .await
-                    {
-                        error!(
-                            "Failed to create comment on issue with wrong title format in issue #{} in {}/{}. Error: {}",
-                            issues.issue.number,
-                            self.repository.0,
-                            self.repository.1,
-                            err
-                        );
-                    };
-                    if let Err(err) = issues_handler
-                        .remove_label(issues.issue.number, crate::RELEASE_ISSUE_LABEL)
-                        .await
-                    {
-                        error!(
-                            "Failed to remove the label {} from issue #{} in {}/{}. Error: {}",
-                            crate::RELEASE_ISSUE_LABEL,
-                            issues.issue.number,
-                            self.repository.0,
-                            self.repository.1,
-                            err
-                        );
-                    };
-
+                // Renamed by `/release-butler bump`, which prepares the release itself
+                if issues.action == IssuesWebhookEventAction::Edited
+                    && self
+                        .payload
+                        .sender
+                        .as_ref()
+                        .is_some_and(|sender| sender.r#type == "Bot")
+                {
                     return Ok(HttpResponse::Ok().finish());
-                };
+                }
 
-                let Some(config) =
-                    get_config(self.repository.0, self.repository.1, self.state, &gh).await
-                else {
-                    return Ok(HttpResponse::InternalServerError().finish());
+                let gh = generate_gh_from_event(self.payload, self.state)?;
+                let forge = GitHubForge::new(gh, self.repository.0, self.repository.1);
+
+                let issue = ReleaseIssue {
+                    number: issues.issue.number,
+                    title: issues.issue.title.clone(),
+                    body: issues.issue.body.clone().unwrap_or_default(),
+                    author: issues.issue.user.login.clone(),
+                    updated_at: issues.issue.updated_at,
                 };
 
-                // Check if issue is created by a valid user
-                if !config
-                    .issues_meta_data
-                    .allowed_authors
-                    .iter()
-                    .any(|author| author.to_lowercase() == issues.issue.user.login.to_lowercase())
+                Ok(prepare_release(&forge, &self.state.app_username, &issue).await)
+            }
+            _ => Err(WebhookError::UnsupportedEvent),
+        }
+    }
+}
+
+/// Bumps the version and updates the changelog of the package requested by `issue` on a release
+/// branch, and opens a pull request for it. Problems are reported as comments on the issue.
+pub async fn prepare_release(
+    forge: &dyn Forge,
+    app_username: &str,
+    issue: &ReleaseIssue,
+) -> HttpResponse {
+    prepare(forge, app_username, issue, false).await
+}
+
+/// Comments the changes [`prepare_release`] would commit, as in dry run mode, whether or not
+/// `dry_run` is enabled
+pub async fn preview_release(
+    forge: &dyn Forge,
+    app_username: &str,
+    issue: &ReleaseIssue,
+) -> HttpResponse {
+    prepare(forge, app_username, issue, true).await
+}
+
+async fn prepare(
+    forge: &dyn Forge,
+    app_username: &str,
+    issue: &ReleaseIssue,
+    preview: bool,
+) -> HttpResponse {
+    let Ok(releases) = issue.releases() else {
+        if let Err(err) = forge
+            .comment(
+                issue.number,
+                &format!("\
+                    The title `{}` doesn't follow the required format. The title must follow one of the \
+                    following patterns:\n\
+                    - `v1.2.3`\n\
+                    - `1.2.3`\n\
+                    - `package@v1.2.3`\n\
+                    - `package@1.2.3`\n\n\
+                    Prerelease and build metadata are supported: (e.g., `v1.2.3-beta.1+1234`)\n\n\
+                    The release issue form can be used instead, see `release-butler issue-form`.\n\n\
+                    **The `release-butler` label is reserved for automated release management.**\n\n\
+                    <details>\n\
+                    <summary>Action taken</summary>\n\
+                    The `release-butler` label has been removed. You can add it back once the title format is correct.\n\
+                    </details>",
+                    &issue.title
+                ),
+            )
+            .await
+        {
+            error!(
+                "Failed to create comment on issue with wrong title format in issue #{}. Error: {}",
+                issue.number, err
+            );
+        };
+        if let Err(err) = forge
+            .remove_label(issue.number, crate::RELEASE_ISSUE_LABEL)
+            .await
+        {
+            error!(
+                "Failed to remove the label {} from issue #{}. Error: {}",
+                crate::RELEASE_ISSUE_LABEL,
+                issue.number,
+                err
+            );
+        };
+
+        return HttpResponse::Ok().finish();
+    };
+
+    let config = match get_config(forge, app_username).await {
+        Ok(Some(config)) => config,
+        Ok(None) => return HttpResponse::InternalServerError().finish(),
+        Err(err) => {
+            report_failure(forge, issue.number, "fetch the configuration", &err).await;
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    // Check if issue is created by a valid user
+    if !config
+        .issues_meta_data
+        .allowed_authors
+        .iter()
+        .any(|author| author.to_lowercase() == issue.author.to_lowercase())
+    {
+        if let Err(err) = forge
+            .comment(
+                issue.number,
+                &config.issues_meta_data.unauthorized_author_comment,
+            )
+            .await
+        {
+            error!(
+                "Failed to create comment on issue #{} regarding unauthorized issue author. Error: {}",
+                issue.number, err
+            );
+        }
+
+        if let Err(err) = forge
+            .remove_label(issue.number, crate::RELEASE_ISSUE_LABEL)
+            .await
+        {
+            error!(
+                "Failed to remove the label `{}` on issue #{}. Error: {}",
+                crate::RELEASE_ISSUE_LABEL,
+                issue.number,
+                err
+            );
+        }
+
+        return HttpResponse::Ok().finish();
+    }
+
+    let releases = release::expand_groups(&config, &releases);
+
+    // Check every package before touching any file
+    let mut problems = Vec::new();
+    for (index, (package, _)) in releases.iter().enumerate() {
+        if releases[..index]
+            .iter()
+            .any(|(previous, _)| previous == package)
+        {
+            problems.push(format!(
+                "The package `{}` is listed more than once.",
+                package
+            ));
+            continue;
+        }
+
+        // check if package name is requried
+        if package.is_empty() && config.packages.len() > 1 {
+            problems.push(String::from(
+                "The `.github/release-butler.toml` contains information of multiple packages while no package \
+                name was specified in the issue title.\n\nPlease prefix the title with `<PACKAGE_NAME>@`.",
+            ));
+            continue;
+        }
+
+        if package_information(&config, package).is_none() {
+            problems.push(format!(
+                "The package `{}` specified in the issue title was not found in the `.github/release-butler.toml` \
+                configuration file.\n\nPlease check the package name and try again.",
+                if package.is_empty() { "default" } else { package }
+            ));
+        }
+    }
+
+    if !problems.is_empty() {
+        if let Err(err) = forge.comment(issue.number, &problems.join("\n\n")).await {
+            error!(
+                "Failed to create comment on issue #{} regarding invalid packages. Error: {}",
+                issue.number, err
+            );
+        }
+
+        return HttpResponse::Ok().finish();
+    }
+
+    let notes = issue.notes();
+    let base = release::base_branch(&config, issue.base(), &releases);
+
+    // Packages of the repository depending on the released ones, released along with them if
+    // `bump_dependents` is enabled
+    let dependents = match find_dependents(forge, &config, base, &releases).await {
+        Ok(dependents) => dependents,
+        Err(err) => {
+            report_failure(forge, issue.number, "fetch the manifests", &err).await;
+            return HttpResponse::Ok().finish();
+        }
+    };
+    let Dependents {
+        graph,
+        bumped,
+        comment: dependents_comment,
+        notes: dependent_notes,
+    } = dependents;
+    let mut releases = releases;
+    releases.extend(bumped);
+
+    let packages: Vec<_> = releases
+        .iter()
+        .filter_map(|(package, version)| {
+            Some((
+                package.as_str(),
+                version,
+                package_information(&config, package)?,
+            ))
+        })
+        .collect();
+    // Requirements on the released packages are updated in the manifests of the others
+    let versions: BTreeMap<_, _> = packages
+        .iter()
+        .map(|(package, version, _)| (graph.crate_name(package).to_owned(), (*version).clone()))
+        .collect();
+
+    let tags: Vec<_> = packages
+        .iter()
+        .map(|(package, version, _)| release::tag_name(package, version))
+        .collect();
+    let create_gh_release = packages
+        .iter()
+        .any(|(_, _, package_information)| package_information.create_gh_release);
+
+    let mut status = (config.status_comment && !config.dry_run && !preview).then(|| {
+        StatusComment::new(
+            forge,
+            issue.number,
+            Status::new(tags.join(", "), create_gh_release),
+        )
+    });
+    if let Some(status) = &mut status {
+        status.complete(Step::LoadConfig, "").await;
+    }
+
+    // Modify the files and create a commit
+    let mut edits = Vec::new();
+    let mut shared_changelogs = Vec::new();
+    for (package, version, package_information) in &packages {
+        let manifest_path = release::manifest_path(package_information);
+        let manifest_path = manifest_path.as_str();
+
+        let manifest_content = match read_edited(forge, base, &edits, manifest_path).await {
+            Ok(Some(content)) => content,
+            Ok(None) => {
+                error!("`{}` doesn't exists", manifest_path);
+                if let Err(err) = forge
+                    .comment(
+                        issue.number,
+                        &format!(
+                            "Failed to find file with path `{}`. Please make sure the file `Cargo.toml` exists.\n\n\
+                            If you believe this is a mistake please open a issue at [release-butler](https://github.com/rs-workspace/release-butler)",
+                            manifest_path
+                        ),
+                    )
+                    .await
                 {
-                    if let Err(err) = issues_handler
-                        .create_comment(
-                            issues.issue.number,
-                            config.issues_meta_data.unauthorized_author_comment,
-                        )
-                        .await
-                    {
-                        error!(
-                            "Failed to create comment on issue #{} in {}/{} regarding unauthorized issue author. Error: {}", 
-                            issues.issue.number,
-                            self.repository.0,
-                            self.repository.1, err
-                        );
-                    }
+                    error!(
+                        "Failed to create a comment in issue #{} regarding non-existing `Cargo.toml`. Error: {}",
+                        issue.number, err
+                    );
+                };
 
-                    if let Err(err) = issues_handler
-                        .remove_label(issues.issue.number, crate::RELEASE_ISSUE_LABEL)
-                        .await
-                    {
-                        error!(
-                            "Failed to remove the label `{}` on issue #{} in {}/{}. Error: {}",
-                            crate::RELEASE_ISSUE_LABEL,
-                            issues.issue.number,
-                            self.repository.0,
-                            self.repository.1,
-                            err
-                        );
+                return HttpResponse::Ok().finish();
+            }
+            Err(err) => {
+                report(
+                    &mut status,
+                    forge,
+                    issue.number,
+                    Step::UpdateFiles,
+                    &format!("fetch `{}`", manifest_path),
+                    &err,
+                )
+                .await;
+                return HttpResponse::Ok().finish();
+            }
+        };
+
+        let new_manifest_content =
+            release::bump_manifest(package_information, &manifest_content, version).and_then(
+                |content| {
+                    if packages.len() > 1 {
+                        dependents::update_requirements(&content, &versions)
+                    } else {
+                        Ok(content)
                     }
+                },
+            );
+        let Ok(new_manifest_content) = new_manifest_content else {
+            error!(
+                "Failed to parse `{}` of package `{}`",
+                manifest_path, package
+            );
+            return HttpResponse::Ok().finish();
+        };
+        push_edit(
+            &mut edits,
+            FileEdit {
+                path: manifest_path.to_owned(),
+                old: Some(manifest_content),
+                new: new_manifest_content,
+            },
+        );
 
-                    return Ok(HttpResponse::Ok().finish());
+        // The changelog shared by a group is written once, for its first member
+        let changelog_path = match release::find_group(&config, package) {
+            Some((name, group)) if release::has_shared_changelog(group) => {
+                if shared_changelogs.contains(&name) {
+                    None
+                } else {
+                    shared_changelogs.push(name);
+                    release::group_changelog_path(group, version)
                 }
-
-                // check if package name is requried
-                if package.is_empty() && config.packages.len() > 1 {
-                    if let Err(err) = issues_handler
-                        .create_comment(
-                            issues.issue.number,
-                            "The `.github/release-butler.toml` contains information of multiple packages while no package \
-                            name was specified in the issue title.\n\nPlease prefix the title with `<PACKAGE_NAME>@`."
-                        )
-                        .await {
-                            error!(
-                                "Failed to create comment on issue #{} in {}/{} regarding package name not specified. Error: {}", 
-                                issues.issue.number,
-                                self.repository.0,
-                                self.repository.1, err
-                            );
-
-                            return Ok(HttpResponse::Ok().finish());
-                        }
+            }
+            _ => release::changelog_path(package_information, version),
+        };
+        if let Some(changelog_path) = changelog_path {
+            let changelog_content = match read_edited(forge, base, &edits, changelog_path).await {
+                Ok(file) => file,
+                Err(err) => {
+                    report(
+                        &mut status,
+                        forge,
+                        issue.number,
+                        Step::UpdateFiles,
+                        &format!("fetch `{}`", changelog_path),
+                        &err,
+                    )
+                    .await;
+                    return HttpResponse::Ok().finish();
                 }
+            };
 
-                let package_information = if package.is_empty() {
-                    config.packages.values().next()
-                } else {
-                    config.packages.get(package)
-                };
+            push_edit(
+                &mut edits,
+                FileEdit {
+                    path: changelog_path.to_owned(),
+                    new: release::update_changelog(
+                        changelog_content.as_deref(),
+                        version,
+                        issue.updated_at.date_naive(),
+                        dependent_notes.get(*package).unwrap_or(&notes),
+                    ),
+                    old: changelog_content,
+                },
+            );
+        }
+    }
+
+    let branch = release::batch_branch(&releases);
+
+    if config.dry_run || preview {
+        let summary = if preview {
+            String::from("**Preview**: nothing was committed.")
+        } else {
+            format!(
+                "**Dry run**: `dry_run` is enabled in `{}`, so nothing was committed and no PR was opened.",
+                crate::DEFAULT_CONFIG_FILE_PATH
+            )
+        };
+        let mut comment = dry_run_comment(
+            &summary,
+            &branch,
+            &edits,
+            &tags,
+            create_gh_release.then_some(
+                packages
+                    .iter()
+                    .any(|(_, version, _)| !version.pre.is_empty()),
+            ),
+        );
+        if let Some(dependents_comment) = &dependents_comment {
+            comment.push_str(&format!("\n\n{}", dependents_comment));
+        }
+        if let Err(err) = forge.comment(issue.number, &comment).await {
+            error!(
+                "Failed to comment the dry run on issue #{}. Error: {}",
+                issue.number, err
+            );
+        }
+        return HttpResponse::Ok().finish();
+    }
+    let updated_files = edits
+        .into_iter()
+        .map(|edit| File::text(edit.path, edit.new))
+        .collect();
+
+    // Get the latest commit in the base branch
+    let latest_commit_sha = match forge.branch_head(base).await {
+        Ok(Some(sha)) => sha,
+        Ok(None) => {
+            error!("Base branch `{}` doesn't exists", base);
+            if let Err(err) = forge
+                .comment(
+                    issue.number,
+                    &format!(
+                        "The branch `{}` to base the release on doesn't exist.",
+                        base
+                    ),
+                )
+                .await
+            {
+                error!(
+                    "Failed to create a comment in issue #{} regarding a missing base branch. Error: {}",
+                    issue.number, err
+                );
+            }
+            return HttpResponse::Ok().finish();
+        }
+        Err(err) => {
+            report(
+                &mut status,
+                forge,
+                issue.number,
+                Step::UpdateFiles,
+                &format!("get the latest commit on `{}`", base),
+                &err,
+            )
+            .await;
+            return HttpResponse::Ok().finish();
+        }
+    };
+
+    let commit_message = match releases.as_slice() {
+        [(_, version)] => format!("chore: RELEASE {}", version),
+        _ => format!("chore: {}", release::pull_title(&releases)),
+    };
+
+    // Don't open a PR with a partial release commit
+    let commit_sha = match forge
+        .commit_files(
+            &branch,
+            &latest_commit_sha,
+            &commit_message,
+            updated_files,
+            config.commit_method,
+        )
+        .await
+    {
+        Ok(sha) => sha,
+        Err(err) => {
+            report(
+                &mut status,
+                forge,
+                issue.number,
+                Step::UpdateFiles,
+                "commit the release changes",
+                &err,
+            )
+            .await;
+            return HttpResponse::Ok().finish();
+        }
+    };
+    if let Some(status) = &mut status {
+        status
+            .complete(Step::UpdateFiles, format!("in {}", commit_sha))
+            .await;
+    }
+
+    // Check if PR is already opened
+    let open_pull = match forge.find_open_pull(&branch, base).await {
+        Ok(pull) => pull,
+        Err(err) => {
+            report(
+                &mut status,
+                forge,
+                issue.number,
+                Step::OpenPull,
+                "check for an existing release pull request",
+                &err,
+            )
+            .await;
+            return HttpResponse::Ok().finish();
+        }
+    };
 
-                let Some(package_information) = package_information else {
-                    if let Err(err) = issues_handler
-                        .create_comment(
-                            issues.issue.number,
-                            format!(
-                                "The package `{}` specified in the issue title was not found in the `.github/release-butler.toml` \
-                                configuration file.\n\nPlease check the package name and try again.",
-                                if package.is_empty() { "default" } else { package }
-                            ),
-                        )
-                        .await
-                    {
+    let pull = match open_pull {
+        Some(pull) => pull,
+        None => match forge
+            .create_pull(
+                &release::pull_title(&releases),
+                &branch,
+                base,
+                &format!("Fixes #{}\n\nThis is an automatically generated PR by [release-butler](https://github.com/rs-workspace/release-butler)", issue.number),
+            )
+            .await
+        {
+            Ok(pull) => {
+                if let Some(dependents_comment) = &dependents_comment {
+                    if let Err(err) = forge.comment(issue.number, dependents_comment).await {
                         error!(
-                            "Failed to create comment on issue #{} in {}/{} regarding invalid package name. Error: {}",
-                            issues.issue.number,
-                            self.repository.0,
-                            self.repository.1,
-                            err
+                            "Failed to comment the dependents on issue #{}. Error: {}",
+                            issue.number, err
                         );
                     }
-
-                    return Ok(HttpResponse::Ok().finish());
-                };
-
-                // Modify the files and create a commit
-                let repos = gh.repos(self.repository.0, self.repository.1);
-                match package_information.package_manager {
-                    PackageManager::Cargo | PackageManager::CargoWorkspace => {
-                        let path = PathBuf::from(&package_information.path).join("Cargo.toml");
-                        let Some(path_str) = path.to_str() else {
-                            error!("Failed to convert path to absolute path of `Cargo.toml`");
-                            return Ok(HttpResponse::Ok().finish());
-                        };
-
-                        let mut cargo_toml_content_items = match repos
-                            .get_content()
-                            .path(path_str)
-                            .send()
-                            .await
-                        {
-                            Ok(contents) => contents,
-                            Err(err) => {
-                                if let octocrab::Error::GitHub { source, .. } = err {
-                                    if source.status_code.as_u16() == 404 {
-                                        error!(
-                                            "`{}` doesn't exists in {}/{}",
-                                            path_str, self.repository.0, self.repository.1
-                                        );
-                                        if let Err(err) = issues_handler
-                                            .create_comment(
-                                                issues.issue.number,
-                                                format!(
-                                                    "Failed to find file with path `{}`. Please make sure the file `Cargo.toml` exists.\n\n\
-                                                    If you believe this is a mistake please open a issue at [release-butler](https://github.com/rs-workspace/release-butler)",
-                                                    path_str
-                                                ),
-                                            )
-                                            .await
-                                        {
-                                            error!(
-                                                "Failed to create a comment in issue #{} in {}/{} regarding non-existing `Cargo.toml`. Error: {}",
-                                                issues.issue.number,
-                                                self.repository.0,
-                                                self.repository.1,
-                                                err
-                                            );
-                                        };
-                                    }
-                                };
-
-                                return Ok(HttpResponse::Ok().finish());
-                            }
-                        };
-
-                        let mut updated_files = Vec::new();
-
-                        let cargo_toml_files = cargo_toml_content_items.take_items();
-                        for file_ in cargo_toml_files {
-                            if PathBuf::from(&file_.path) != path {
-                                continue;
-                            }
-
-                            let Some(cargo_toml_content) = file_.decoded_content() else {
-                                error!("Failed to decode of `Cargo.toml`");
-                                return Ok(HttpResponse::Ok().finish());
-                            };
-                            let Ok(mut doc) = cargo_toml_content.parse::<toml_edit::DocumentMut>()
-                            else {
-                                error!("Failed to parse `Cargo.toml`");
-                                return Ok(HttpResponse::Ok().finish());
-                            };
-
-                            let version_key = match package_information.package_manager {
-                                PackageManager::CargoWorkspace => "workspace.package",
-                                _ => "package",
-                            };
-                            doc[version_key]["version"] = toml_edit::value(version.to_string());
-
-                            updated_files.push(File {
-                                name: file_.name,
-                                new_content: doc.to_string(),
-                            });
-
-                            break;
-                        }
-
-                        let changelog_path_str = if version.pre.is_empty() {
-                            &package_information.changelog_file
-                        } else {
-                            &package_information.pre_release_changelog_file
-                        };
-
-                        if !changelog_path_str.is_empty() {
-                            let changelog_content_items =
-                                match repos.get_content().path(changelog_path_str).send().await {
-                                    Ok(mut contents) => contents.take_items(),
-                                    Err(err) => {
-                                        if let octocrab::Error::GitHub { source, .. } = err {
-                                            if source.status_code.as_u16() == 404 {
-                                                Vec::new()
-                                            } else {
-                                                return Ok(HttpResponse::Ok().finish());
-                                            }
-                                        } else {
-                                            return Ok(HttpResponse::Ok().finish());
-                                        }
-                                    }
-                                };
-                            let changelog_file_path = PathBuf::from(changelog_path_str);
-
-                            // Create the file if doesn't exists
-                            if changelog_content_items.is_empty() {
-                                let new_content = format!(
-                                    "# Changelog\n\n## [{}] - {}\n{}",
-                                    version,
-                                    issues.issue.updated_at.format("%Y-%m-%d"),
-                                    issues.issue.body.as_ref().unwrap_or(&String::new())
-                                );
-
-                                updated_files.push(File {
-                                    name: changelog_path_str.to_owned(),
-                                    new_content,
-                                });
-                            }
-
-                            for content in changelog_content_items {
-                                if PathBuf::from(&content.path) != changelog_file_path {
-                                    continue;
-                                }
-
-                                let Some(changelog_content) = content.decoded_content() else {
-                                    error!("Failed to decode changelog content");
-                                    continue;
-                                };
-
-                                let changelog_lines: Vec<&str> =
-                                    changelog_content.lines().collect();
-                                let mut new_content = String::new();
-                                let mut added_version = false;
-
-                                // Find the first "## [" line to insert the new version before it
-                                for line in changelog_lines {
-                                    if !added_version && line.starts_with("## [") {
-                                        // Add new version section
-                                        new_content.push_str(&format!(
-                                            "## [{}] - {}\n",
-                                            version,
-                                            issues.issue.updated_at.format("%Y-%m-%d")
-                                        ));
-                                        new_content.push_str(
-                                            issues.issue.body.as_ref().unwrap_or(&String::new()),
-                                        );
-                                        new_content.push_str("\n\n");
-                                        added_version = true;
-                                    }
-                                    new_content.push_str(line);
-                                    new_content.push('\n');
-                                }
-
-                                // If no version headers found, append to the end
-                                if !added_version {
-                                    new_content.push_str(&format!(
-                                        "## [{}] - {}\n",
-                                        version,
-                                        issues.issue.updated_at.format("%Y-%m-%d")
-                                    ));
-                                    new_content.push_str(
-                                        issues.issue.body.as_ref().unwrap_or(&String::new()),
-                                    );
-                                    new_content.push('\n');
-                                }
-
-                                updated_files.push(File {
-                                    name: content.name,
-                                    new_content,
-                                });
-                                break;
-                            }
-                        }
-
-                        // Push changes to branch
-                        if !updated_files.is_empty() {
-                            // Get the latest commit in default branch
-                            let Ok(commits) = repos
-                                .list_commits()
-                                .branch(&config.default_branch)
-                                .send()
-                                .await
-                            else {
-                                error!("Failed to get commit history on default branch");
-                                return Ok(HttpResponse::Ok().finish());
-                            };
-                            let latest_commit_sha = &commits.items[0].sha;
-
-                            let branch = Reference::Branch(format!(
-                                "release-butler/{}@{}",
-                                package, version
-                            ));
-
-                            let updated_files = UpdateFiles::new(
-                                &gh,
-                                updated_files,
-                                &branch,
-                                format!("chore: RELEASE {}", version),
-                            );
-
-                            updated_files
-                                .execute(self.repository.0, self.repository.1, latest_commit_sha)
-                                .await;
-
-                            // Check if PR is already opened
-                            let pulls = gh.pulls(self.repository.0, self.repository.1);
-                            let is_pull_already_there = match pulls
-                                .list()
-                                .base(&config.default_branch)
-                                .head(branch.branch_name())
-                                .state(octocrab::params::State::Open)
-                                .send()
-                                .await
-                            {
-                                Ok(mut res) => {
-                                    let items = res.take_items();
-                                    !items.is_empty()
-                                }
-                                Err(_) => true,
-                            };
-
-                            if !is_pull_already_there {
-                                if let Err(err) = pulls
-                                    .create(
-                                        format!("RELEASE {}@v{}", package, version),
-                                        branch.branch_name(),
-                                        &config.default_branch,
-                                    )
-                                    .maintainer_can_modify(true)
-                                    .body(format!("Fixes #{}\n\nThis is an automatically generated PR by [release-butler](https://github.com/rs-workspace/release-butler)", issues.issue.number))
-                                    .send()
-                                    .await
-                                {
-                                    error!("Failed to create a pull request. Error: {}", err);
-                                }
-                            }
-                        }
-
-                        // TODO: Open a PR, if not opened already
-                    } // TODO: More Package Managers
                 }
+                pull
             }
-            _ => {
-                return Err(WebhookError::UnsupportedEvent);
+            Err(err) => {
+                report(
+                    &mut status,
+                    forge,
+                    issue.number,
+                    Step::OpenPull,
+                    "create the release pull request",
+                    &err,
+                )
+                .await;
+                return HttpResponse::Ok().finish();
             }
+        },
+    };
+    if let Some(status) = &mut status {
+        status.complete(Step::OpenPull, &pull.html_url).await;
+    }
+
+    HttpResponse::Ok().finish()
+}
+
+/// The package of the configuration named `package`, the only one if `package` is empty
+fn package_information<'a>(config: &'a Config, package: &str) -> Option<&'a Package> {
+    if package.is_empty() {
+        config.packages.values().next()
+    } else {
+        config.packages.get(package)
+    }
+}
+
+/// Packages of the repository depending on released packages
+#[derive(Default)]
+pub(super) struct Dependents {
+    pub graph: DependencyGraph,
+    /// Patch releases of the dependents, released along with the packages if `bump_dependents`
+    /// is enabled
+    pub bumped: Vec<(String, semver::Version)>,
+    /// Lists the dependents, `None` if there are none
+    pub comment: Option<String>,
+    /// Changelog of the bumped dependents, keyed by package
+    pub notes: BTreeMap<String, String>,
+}
+
+/// Dependents of `releases` in the manifests on `base`. The release branch is named after
+/// `releases` followed by [`Dependents::bumped`].
+pub(super) async fn find_dependents(
+    forge: &dyn Forge,
+    config: &Config,
+    base: &str,
+    releases: &[(String, semver::Version)],
+) -> Result<Dependents, ClientError> {
+    if config.packages.len() <= 1 {
+        return Ok(Dependents::default());
+    }
+
+    let mut manifests = BTreeMap::new();
+    for (name, package_information) in &config.packages {
+        let manifest_path = release::manifest_path(package_information);
+        if let Some(content) = forge.read_branch_file(base, &manifest_path).await? {
+            manifests.insert(name.clone(), content);
         }
+    }
+    let graph = DependencyGraph::new(&manifests);
+
+    let released: Vec<_> = releases
+        .iter()
+        .map(|(package, _)| package.as_str())
+        .collect();
+    let dependents = graph.dependents(&released, config.bump_dependents);
+    let mut bumped = Vec::new();
+    if config.bump_dependents {
+        for dependent in &dependents {
+            let current = manifests.get(dependent).and_then(|content| {
+                release::manifest_version(&config.packages[dependent], content)
+            });
+            if let Some(current) = current {
+                bumped.push((dependent.clone(), Bump::Patch.apply(&current)));
+            }
+        }
+    }
+    let comment = (!dependents.is_empty())
+        .then(|| dependents_summary(&graph, &released, &dependents, &bumped));
+
+    let mut notes = BTreeMap::new();
+    for (dependent, _) in &bumped {
+        let updated: Vec<_> = graph
+            .dependencies
+            .iter()
+            .filter(|dependency| dependency.package == *dependent)
+            .filter_map(|dependency| {
+                let (_, version) = releases
+                    .iter()
+                    .chain(&bumped)
+                    .find(|(package, _)| *package == dependency.dependency)?;
+                Some(format!(
+                    "- Updated `{}` to {}",
+                    graph.crate_name(&dependency.dependency),
+                    version
+                ))
+            })
+            .collect();
+        notes.insert(
+            dependent.clone(),
+            format!("### Changed\n{}", updated.join("\n")),
+        );
+    }
+
+    Ok(Dependents {
+        graph,
+        bumped,
+        comment,
+        notes,
+    })
+}
 
-        Ok(HttpResponse::Ok().finish())
+/// Lists the packages depending on `released`, and whether they are released along with them
+fn dependents_summary(
+    graph: &DependencyGraph,
+    released: &[&str],
+    dependents: &[String],
+    bumped: &[(String, semver::Version)],
+) -> String {
+    let mut comment = String::from("The following packages depend on the released packages:\n");
+    for dependency in graph.dependencies.iter().filter(|dependency| {
+        dependents.contains(&dependency.package)
+            && (released.contains(&dependency.dependency.as_str())
+                || dependents.contains(&dependency.dependency))
+    }) {
+        comment.push_str(&format!(
+            "- `{}` requires `{}` {}\n",
+            dependency.package, dependency.dependency, dependency.requirement
+        ));
+    }
+
+    if bumped.is_empty() {
+        comment.push_str(&format!(
+            "\nTheir requirements weren't updated. Set `bump_dependents = true` in `{}` to update \
+            them and release them with a patch bump in the release pull request.",
+            crate::DEFAULT_CONFIG_FILE_PATH
+        ));
+    } else {
+        let bumped: Vec<_> = bumped
+            .iter()
+            .map(|(package, version)| format!("`{}@{}`", package, version))
+            .collect();
+        comment.push_str(&format!(
+            "\nTheir requirements are updated and they are released along with them: {}.",
+            bumped.join(", ")
+        ));
+    }
+
+    comment
+}
+
+/// Content of `path` on `branch` with the edits made so far, so that packages sharing a file (i.e. the
+/// changelog) don't overwrite each other's changes
+async fn read_edited(
+    forge: &dyn Forge,
+    branch: &str,
+    edits: &[FileEdit],
+    path: &str,
+) -> Result<Option<String>, ClientError> {
+    match edits.iter().find(|edit| edit.path == path) {
+        Some(edit) => Ok(Some(edit.new.clone())),
+        None => forge.read_branch_file(branch, path).await,
+    }
+}
+
+/// Adds `edit`, replacing the new content of an earlier edit of the same file
+fn push_edit(edits: &mut Vec<FileEdit>, edit: FileEdit) {
+    match edits.iter_mut().find(|existing| existing.path == edit.path) {
+        Some(existing) => existing.new = edit.new,
+        None => edits.push(edit),
+    }
+}
+
+/// Reports a failure in the status comment if enabled, as a new comment otherwise
+async fn report(
+    status: &mut Option<StatusComment<'_>>,
+    forge: &dyn Forge,
+    issue_number: u64,
+    step: Step,
+    action: &str,
+    err: &impl Display,
+) {
+    match status {
+        Some(status) => status.fail(step, action, err).await,
+        None => report_failure(forge, issue_number, action, err).await,
     }
 }
 
+/// Describes what a release would do, posted instead of committing in dry run mode.
+/// `prerelease` is `None` if no release would be created.
+fn dry_run_comment(
+    summary: &str,
+    branch: &str,
+    edits: &[FileEdit],
+    tags: &[String],
+    prerelease: Option<bool>,
+) -> String {
+    let mut comment = format!(
+        "{}\n\nThe following changes would be committed on `{}`:\n",
+        summary, branch
+    );
+
+    for edit in edits {
+        comment.push_str(&format!("\n```diff\n{}```\n", edit.unified_diff()));
+    }
+
+    let tags: Vec<_> = tags.iter().map(|tag| format!("`{}`", tag)).collect();
+    comment.push_str(&format!(
+        "\nOnce the PR is merged, the {} {} would be created on the merge commit",
+        if tags.len() == 1 { "tag" } else { "tags" },
+        tags.join(", ")
+    ));
+    match prerelease {
+        Some(true) => comment.push_str(" along with a prerelease."),
+        Some(false) if tags.len() == 1 => comment.push_str(" along with a release."),
+        Some(false) => comment.push_str(" along with releases."),
+        None => comment.push('.'),
+    }
+
+    comment
+}
+
+/// Parses one or more releases separated by commas or spaces, i.e. `foo@1.2.3, bar@v0.4.0`
+pub fn parse_releases(list: &str) -> Result<Vec<(&str, semver::Version)>, semver::Error> {
+    let releases = list
+        .split([',', ' '])
+        .filter(|release| !release.is_empty())
+        .map(parse_issue_title)
+        .collect::<Result<Vec<_>, _>>()?;
+    if releases.is_empty() {
+        // Same error as an empty version
+        parse_issue_title(list)?;
+    }
+    Ok(releases)
+}
+
 pub fn parse_issue_title(title: &str) -> Result<(&str, semver::Version), semver::Error> {
     let (package, ver_str) = match title.split_once('@') {
         Some((pkg, ver)) => (pkg, ver),
@@ -464,6 +955,486 @@ mod tests {
     use semver::{BuildMetadata, Prerelease};
 
     use super::*;
+    use crate::forge::memory::InMemoryForge;
+
+    static CONFIG: &str = r#"
+        version = 1
+        default_branch = "main"
+
+        [issues_meta_data]
+        allowed_authors = ["jdoe"]
+
+        [packages.example]
+        changelog_file = "CHANGELOG.md"
+        package_manager = "cargo"
+    "#;
+
+    fn release_issue(title: &str, author: &str) -> ReleaseIssue {
+        ReleaseIssue {
+            number: 7,
+            title: title.to_owned(),
+            body: String::from("### Fixed\n- Bug Y"),
+            author: author.to_owned(),
+            updated_at: "2025-03-12T10:00:00Z".parse().unwrap(),
+        }
+    }
+
+    fn forge() -> InMemoryForge {
+        let forge = InMemoryForge::with_files(&[
+            (".github/release-butler.toml", CONFIG),
+            (
+                "Cargo.toml",
+                "[package]\nname = \"example\"\nversion = \"1.2.2\"\n",
+            ),
+            (
+                "CHANGELOG.md",
+                "# Changelog\n\n## [1.2.2] - 2025-01-01\n- Fix\n",
+            ),
+        ]);
+        forge.open_issue(7, "v1.2.3", "", "jdoe", &[crate::RELEASE_ISSUE_LABEL]);
+        forge
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release() {
+        let forge = forge();
+        let resp = prepare_release(
+            &forge,
+            "app/release-butler",
+            &release_issue("v1.2.3", "jdoe"),
+        )
+        .await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+
+        let repository = forge.repository();
+        let branch = "release-butler/@1.2.3";
+        assert_eq!(
+            repository.file(branch, "Cargo.toml").unwrap(),
+            "[package]\nname = \"example\"\nversion = \"1.2.3\"\n"
+        );
+        assert_eq!(
+            repository.file(branch, "CHANGELOG.md").unwrap(),
+            "# Changelog\n\n## [1.2.3] - 2025-03-12\n### Fixed\n- Bug Y\n\n## [1.2.2] - 2025-01-01\n- Fix\n"
+        );
+        assert_eq!(repository.pulls.len(), 1);
+        assert_eq!(repository.pulls[0].title, "RELEASE @v1.2.3");
+        assert_eq!(repository.pulls[0].head, branch);
+        assert_eq!(repository.pulls[0].base, "main");
+        assert!(repository.comments.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release_from_issue_form() {
+        let forge = forge();
+        let issue = ReleaseIssue {
+            title: String::from("Release"),
+            body: String::from("### Version\n\nv1.2.3\n\n### Changelog\n\n### Fixed\n- Bug Y"),
+            ..release_issue("", "jdoe")
+        };
+        prepare_release(&forge, "app/release-butler", &issue).await;
+
+        let repository = forge.repository();
+        assert_eq!(
+            repository.file("release-butler/@1.2.3", "CHANGELOG.md").unwrap(),
+            "# Changelog\n\n## [1.2.3] - 2025-03-12\n### Fixed\n- Bug Y\n\n## [1.2.2] - 2025-01-01\n- Fix\n"
+        );
+        assert_eq!(repository.pulls[0].title, "RELEASE @v1.2.3");
+        assert!(repository.comments.is_empty());
+    }
+
+    #[test]
+    fn test_release_issue_releases() {
+        let issue = ReleaseIssue {
+            body: String::from("### Package\n\nfoo\n\n### Version\n\n1.2.3"),
+            ..release_issue("Release", "jdoe")
+        };
+        assert_eq!(
+            issue.releases().unwrap(),
+            [(String::from("foo"), semver::Version::new(1, 2, 3))]
+        );
+
+        // The title takes precedence, i.e. once bumped with a command
+        let issue = ReleaseIssue {
+            title: String::from("foo@v1.3.0"),
+            ..issue
+        };
+        assert_eq!(
+            issue.releases().unwrap()[0].1,
+            semver::Version::new(1, 3, 0)
+        );
+
+        let issue = release_issue("foo@1.2.3, bar@v0.4.0", "jdoe");
+        assert_eq!(
+            issue.releases().unwrap(),
+            [
+                (String::from("foo"), semver::Version::new(1, 2, 3)),
+                (String::from("bar"), semver::Version::new(0, 4, 0))
+            ]
+        );
+
+        let issue = ReleaseIssue {
+            body: String::from("- foo@1.2.3\n- bar@0.4.0\n\n### Fixed\n- Bug Y"),
+            ..release_issue("Release", "jdoe")
+        };
+        assert_eq!(issue.releases().unwrap().len(), 2);
+        assert_eq!(issue.notes(), "### Fixed\n- Bug Y");
+        assert_eq!(issue.base(), None);
+
+        let issue = ReleaseIssue {
+            body: String::from("base: release/1.x\n- foo@1.2.3\n\n### Fixed\n- Bug Y"),
+            ..release_issue("Release", "jdoe")
+        };
+        assert_eq!(issue.base(), Some("release/1.x"));
+        assert_eq!(issue.releases().unwrap().len(), 1);
+        assert_eq!(issue.notes(), "### Fixed\n- Bug Y");
+
+        assert!(release_issue("Release", "jdoe").releases().is_err());
+        assert!(release_issue("foo@1.2.3, bar", "jdoe").releases().is_err());
+        assert!(release_issue("", "jdoe").releases().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_batch_release_with_shared_changelog() {
+        let forge = InMemoryForge::with_files(&[
+            (
+                ".github/release-butler.toml",
+                r#"
+                version = 1
+                default_branch = "main"
+
+                [issues_meta_data]
+                allowed_authors = ["jdoe"]
+
+                [packages.foo]
+                path = "foo"
+                changelog_file = "CHANGELOG.md"
+                package_manager = "cargo"
+
+                [packages.bar]
+                path = "bar"
+                changelog_file = "CHANGELOG.md"
+                package_manager = "cargo"
+                "#,
+            ),
+            (
+                "foo/Cargo.toml",
+                "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                "bar/Cargo.toml",
+                "[package]\nname = \"bar\"\nversion = \"0.3.0\"\n",
+            ),
+            ("CHANGELOG.md", "# Changelog\n"),
+        ]);
+        prepare_release(
+            &forge,
+            "app/release-butler",
+            &release_issue("foo@0.2.0 bar@0.4.0", "jdoe"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        let branch = "release-butler/foo@0.2.0+bar@0.4.0";
+        assert_eq!(
+            repository.file(branch, "CHANGELOG.md").unwrap(),
+            "# Changelog\n## [0.4.0] - 2025-03-12\n### Fixed\n- Bug Y\n\n## [0.2.0] - 2025-03-12\n### Fixed\n- Bug Y\n"
+        );
+        assert!(repository
+            .file(branch, "bar/Cargo.toml")
+            .unwrap()
+            .contains("0.4.0"));
+        assert_eq!(repository.pulls[0].title, "RELEASE foo@v0.2.0, bar@v0.4.0");
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release_bumps_dependents() {
+        let forge = InMemoryForge::with_files(&[
+            (
+                ".github/release-butler.toml",
+                r#"
+                version = 1
+                default_branch = "main"
+                bump_dependents = true
+
+                [issues_meta_data]
+                allowed_authors = ["jdoe"]
+
+                [packages.core]
+                path = "core"
+                changelog_file = "core/CHANGELOG.md"
+                package_manager = "cargo"
+
+                [packages.cli]
+                path = "cli"
+                changelog_file = "cli/CHANGELOG.md"
+                package_manager = "cargo"
+                "#,
+            ),
+            (
+                "core/Cargo.toml",
+                "[package]\nname = \"example-core\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                "cli/Cargo.toml",
+                "[package]\nname = \"example-cli\"\nversion = \"1.0.0\"\n\n\
+                [dependencies]\nexample-core = { path = \"../core\", version = \"0.1.0\" }\n",
+            ),
+        ]);
+        prepare_release(
+            &forge,
+            "app/release-butler",
+            &release_issue("core@0.2.0", "jdoe"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        let branch = "release-butler/core@0.2.0+cli@1.0.1";
+        assert_eq!(
+            repository.file(branch, "cli/Cargo.toml").unwrap(),
+            "[package]\nname = \"example-cli\"\nversion = \"1.0.1\"\n\n\
+            [dependencies]\nexample-core = { path = \"../core\", version = \"0.2.0\" }\n"
+        );
+        assert_eq!(
+            repository.file(branch, "cli/CHANGELOG.md").unwrap(),
+            "# Changelog\n\n## [1.0.1] - 2025-03-12\n### Changed\n- Updated `example-core` to 0.2.0"
+        );
+        assert_eq!(repository.pulls[0].title, "RELEASE core@v0.2.0, cli@v1.0.1");
+        assert_eq!(
+            repository.comments[&7],
+            ["The following packages depend on the released packages:\n\
+            - `cli` requires `core` 0.1.0\n\n\
+            Their requirements are updated and they are released along with them: `cli@1.0.1`."]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release_group() {
+        let forge = InMemoryForge::with_files(&[
+            (
+                ".github/release-butler.toml",
+                r#"
+                version = 1
+                default_branch = "main"
+
+                [issues_meta_data]
+                allowed_authors = ["jdoe"]
+
+                [packages.core]
+                path = "core"
+                changelog_file = "core/CHANGELOG.md"
+                package_manager = "cargo"
+
+                [packages.cli]
+                path = "cli"
+                changelog_file = "cli/CHANGELOG.md"
+                package_manager = "cargo"
+
+                [groups.example]
+                packages = ["core", "cli"]
+                changelog_file = "CHANGELOG.md"
+                "#,
+            ),
+            (
+                "core/Cargo.toml",
+                "[package]\nname = \"example-core\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                "cli/Cargo.toml",
+                "[package]\nname = \"example-cli\"\nversion = \"0.1.0\"\n\n\
+                [dependencies]\nexample-core = { path = \"../core\", version = \"0.1.0\" }\n",
+            ),
+        ]);
+        prepare_release(
+            &forge,
+            "app/release-butler",
+            &release_issue("example@0.2.0", "jdoe"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        let branch = "release-butler/core@0.2.0+cli@0.2.0";
+        assert_eq!(
+            repository.file(branch, "core/Cargo.toml").unwrap(),
+            "[package]\nname = \"example-core\"\nversion = \"0.2.0\"\n"
+        );
+        assert_eq!(
+            repository.file(branch, "cli/Cargo.toml").unwrap(),
+            "[package]\nname = \"example-cli\"\nversion = \"0.2.0\"\n\n\
+            [dependencies]\nexample-core = { path = \"../core\", version = \"0.2.0\" }\n"
+        );
+        assert_eq!(
+            repository.file(branch, "CHANGELOG.md").unwrap(),
+            "# Changelog\n\n## [0.2.0] - 2025-03-12\n### Fixed\n- Bug Y"
+        );
+        assert!(repository.file(branch, "core/CHANGELOG.md").is_none());
+        assert!(repository.file(branch, "cli/CHANGELOG.md").is_none());
+        assert_eq!(repository.pulls[0].title, "RELEASE core@v0.2.0, cli@v0.2.0");
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release_on_maintenance_branch() {
+        let forge = forge();
+        let main = forge.branch_head("main").await.unwrap().unwrap();
+        forge
+            .commit_files(
+                "release/1.0",
+                &main,
+                "chore: RELEASE 1.0.3",
+                vec![
+                    File::text(
+                        "Cargo.toml",
+                        "[package]\nname = \"example\"\nversion = \"1.0.3\"\n",
+                    ),
+                    File::text(
+                        "CHANGELOG.md",
+                        "# Changelog\n\n## [1.0.3] - 2025-01-01\n- Fix\n",
+                    ),
+                ],
+                crate::config::CommitMethod::Rest,
+            )
+            .await
+            .unwrap();
+        let issue = ReleaseIssue {
+            body: String::from("base: release/1.0\n\n### Fixed\n- Bug Y"),
+            ..release_issue("v1.0.4", "jdoe")
+        };
+        prepare_release(&forge, "app/release-butler", &issue).await;
+
+        let repository = forge.repository();
+        let branch = "release-butler/@1.0.4";
+        assert_eq!(
+            repository.file(branch, "Cargo.toml").unwrap(),
+            "[package]\nname = \"example\"\nversion = \"1.0.4\"\n"
+        );
+        assert_eq!(
+            repository.file(branch, "CHANGELOG.md").unwrap(),
+            "# Changelog\n\n## [1.0.4] - 2025-03-12\n### Fixed\n- Bug Y\n\n## [1.0.3] - 2025-01-01\n- Fix\n"
+        );
+        assert_eq!(repository.pulls[0].base, "release/1.0");
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release_twice_opens_one_pull() {
+        let forge = forge();
+        let issue = release_issue("v1.2.3", "jdoe");
+        prepare_release(&forge, "app/release-butler", &issue).await;
+        prepare_release(&forge, "app/release-butler", &issue).await;
+
+        assert_eq!(forge.repository().pulls.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release_unauthorized_author() {
+        let forge = forge();
+        prepare_release(
+            &forge,
+            "app/release-butler",
+            &release_issue("v1.2.3", "mallory"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        assert!(repository.pulls.is_empty());
+        assert!(repository.issues[&7].labels.is_empty());
+        assert_eq!(repository.comments[&7].len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release_invalid_title() {
+        let forge = forge();
+        prepare_release(
+            &forge,
+            "app/release-butler",
+            &release_issue("next release", "jdoe"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        assert!(repository.pulls.is_empty());
+        assert!(repository.issues[&7].labels.is_empty());
+        assert!(repository.comments[&7][0].contains("doesn't follow the required format"));
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release_dry_run() {
+        let forge = InMemoryForge::with_files(&[
+            (
+                ".github/release-butler.toml",
+                &format!("dry_run = true\n{}", CONFIG),
+            ),
+            (
+                "Cargo.toml",
+                "[package]\nname = \"example\"\nversion = \"1.2.2\"\n",
+            ),
+        ]);
+        forge.open_issue(7, "v1.2.3", "", "jdoe", &[crate::RELEASE_ISSUE_LABEL]);
+
+        prepare_release(
+            &forge,
+            "app/release-butler",
+            &release_issue("v1.2.3", "jdoe"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        assert!(repository.pulls.is_empty());
+        assert_eq!(repository.branches.len(), 1);
+
+        let comment = &repository.comments[&7][0];
+        assert!(comment.contains("committed on `release-butler/@1.2.3`"));
+        assert!(comment.contains("--- a/Cargo.toml\n+++ b/Cargo.toml\n"));
+        assert!(comment.contains("-version = \"1.2.2\"\n+version = \"1.2.3\"\n"));
+        assert!(comment.contains("--- /dev/null\n+++ b/CHANGELOG.md\n"));
+        assert!(comment.contains("+## [1.2.3] - 2025-03-12\n"));
+        assert!(comment.ends_with("the tag `v1.2.3` would be created on the merge commit."));
+    }
+
+    #[test]
+    fn test_dry_run_comment_release() {
+        let tags = [String::from("v1.2.3")];
+        // Whether the release is marked as latest is only known once it's published
+        assert!(
+            dry_run_comment("", "main", &[], &tags, Some(false)).ends_with(
+                "the tag `v1.2.3` would be created on the merge commit along with a release."
+            )
+        );
+        assert!(dry_run_comment("", "main", &[], &tags, Some(true))
+            .ends_with("along with a prerelease."));
+    }
+
+    #[actix_web::test]
+    async fn test_prepare_release_status_comment() {
+        let forge = InMemoryForge::with_files(&[
+            (
+                ".github/release-butler.toml",
+                &format!("status_comment = true\n{}", CONFIG),
+            ),
+            (
+                "Cargo.toml",
+                "[package]\nname = \"example\"\nversion = \"1.2.2\"\n",
+            ),
+        ]);
+        forge.open_issue(7, "v1.2.3", "", "jdoe", &[crate::RELEASE_ISSUE_LABEL]);
+        let issue = release_issue("v1.2.3", "jdoe");
+
+        prepare_release(&forge, "app/release-butler", &issue).await;
+        prepare_release(&forge, "app/release-butler", &issue).await;
+
+        let repository = forge.repository();
+        let branch_head = &repository.branches["release-butler/@1.2.3"];
+        assert_eq!(
+            repository.comments[&7],
+            [format!(
+                "<!-- release-butler-status -->\n### Release `v1.2.3`\n\n\
+                - [x] Configuration loaded\n\
+                - [x] Files updated in {}\n\
+                - [x] Pull request opened https://forge.test/pulls/101\n\
+                - [ ] Pull request merged\n\
+                - [ ] Tag created\n",
+                branch_head
+            )]
+        );
+    }
 
     #[test]
     fn test_valid_semver() {
@@ -2,6 +2,7 @@ use crate::{webhook::WebhookError, State};
 use actix_web::HttpResponse;
 use octocrab::models::webhook_events::WebhookEvent;
 
+pub mod comments;
 pub mod issues;
 pub mod pulls;
 
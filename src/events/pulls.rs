@@ -4,11 +4,17 @@ use octocrab::models::webhook_events::{
 use tracing::error;
 
 use crate::{
-    common::ReferenceExt,
+    client::ClientError,
+    forge::{Forge, GitHubForge},
+    release::{self, referenced_issues},
+    status::{StatusComment, Step},
     webhook::{generate_gh_from_event, get_config},
 };
 
-use super::{issues::parse_issue_title, *};
+use super::{
+    issues::{parse_releases, release_notes},
+    *,
+};
 
 pub struct PullsHandler<'a> {
     payload: &'a WebhookEvent,
@@ -16,6 +22,50 @@ pub struct PullsHandler<'a> {
     repository: (&'a str, &'a str),
 }
 
+/// A merged release pull request, independent of the forge it was opened on
+#[derive(Debug, Clone)]
+pub struct ReleasePull {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    pub merge_commit_sha: String,
+}
+
+/// Comments on the release issue (or the pull request, if the issue isn't known) about an API
+/// failure, so that failures don't go unnoticed
+async fn report_failure(
+    forge: &dyn Forge,
+    issue_number: Option<u64>,
+    pull_number: u64,
+    step: &str,
+    err: &ClientError,
+) {
+    error!(
+        "Failed to {} for release PR #{}. Error: {}",
+        step, pull_number, err
+    );
+
+    let body = format!(
+        "The release PR was merged, but I failed to {} even after retrying.\n\n\
+        <details>\n\
+        <summary>Error</summary>\n\n\
+        ```\n{}\n```\n\
+        </details>",
+        step, err
+    );
+    let result = match issue_number {
+        Some(issue_number) => forge.comment(issue_number, &body).await,
+        None => forge.comment_on_pull(pull_number, &body).await,
+    };
+
+    if let Err(comment_err) = result {
+        error!(
+            "Failed to report failure of release PR #{}. Error: {}",
+            pull_number, comment_err
+        );
+    }
+}
+
 #[async_trait::async_trait]
 impl<'a> Handler<'a> for PullsHandler<'a> {
     fn new(repository: (&'a str, &'a str), payload: &'a WebhookEvent, state: &'a State) -> Self {
@@ -41,157 +91,51 @@ impl<'a> Handler<'a> for PullsHandler<'a> {
                         "{}:release-butler/",
                         self.repository.0.to_lowercase()
                     )) {
-                        let Ok(gh) = generate_gh_from_event(self.payload, &self.state.gh) else {
+                        let Ok(gh) = generate_gh_from_event(self.payload, self.state) else {
                             error!("Failed to authenticate from github webhook installation id");
                             return Ok(HttpResponse::Ok().finish());
                         };
+                        let forge = GitHubForge::new(gh, self.repository.0, self.repository.1);
 
                         // Check if PR was merged
-                        if let Some(merged) = pull.pull_request.merged {
-                            if merged {
-                                // Create a tag
-                                let Some(commit_sha) = &pull.pull_request.merge_commit_sha else {
-                                    return Err(WebhookError::MalformatedBody {
-                                        msg: String::from(
-                                            "The payload must contain `merge_commit_sha`",
-                                        ),
-                                    });
-                                };
-
-                                let Some(pull_title) = &pull.pull_request.title else {
-                                    return Err(WebhookError::MalformatedBody {
-                                        msg: String::from("The payload must contain `title`"),
-                                    });
-                                };
-
-                                let Some(tag) = pull_title.strip_prefix("RELEASE ") else {
-                                    if let Err(err) = gh
-                                        .issues(self.repository.0, self.repository.1)
-                                        .create_comment(
-                                            pull.number,
-                                            "The release title is malformated. It should be in the following format:\n\n`RELEASE <PACKAGE_NAME>@v<VERSION>`"
-                                            )
-                                        .await
-                                    {
-                                        error!("Failed to create PR comment. Error: {}", err);
-                                    }
-                                    return Ok(HttpResponse::Ok().finish());
-                                };
-
-                                let Some(tag) = tag.strip_prefix("@") else {
-                                    error!("Failed to find the tag for release");
-                                    return Ok(HttpResponse::Ok().finish());
-                                };
-
-                                let Ok((package, version)) = parse_issue_title(tag) else {
-                                    error!("Failed to parse issue title");
-                                    return Ok(HttpResponse::Ok().finish());
-                                };
-
-                                let tag = octocrab::params::repos::Reference::Tag(tag.to_owned());
-
-                                if let Err(err) = gh
-                                    .repos(self.repository.0, self.repository.1)
-                                    .create_ref(&tag, commit_sha)
-                                    .await
-                                {
-                                    error!("Failed to create tag. Error: {}", err);
-                                }
-
-                                let Some(config) = get_config(
-                                    self.repository.0,
-                                    self.repository.1,
-                                    self.state,
-                                    &gh,
-                                )
-                                .await
-                                else {
-                                    error!("Failed to fetch config file");
-                                    return Ok(HttpResponse::Ok().finish());
-                                };
-
-                                let package_information = if config.packages.len() == 1 {
-                                    if let Some((_, package)) = config.packages.iter().next() {
-                                        package
-                                    } else {
-                                        return Ok(HttpResponse::Ok().finish());
-                                    }
-                                } else if let Some(package) = config.packages.get(package) {
-                                    package
-                                } else {
-                                    return Ok(HttpResponse::Ok().finish());
-                                };
-
-                                if package_information.create_gh_release {
-                                    // get the issue number from pull body (Fixes #{number} <OTHER STUFF>)
-                                    let body = pull.pull_request.body.as_ref().map_or("", |v| v);
-
-                                    let numbers: Vec<&str> = body
-                                        .split(|c: char| !c.is_numeric() && c != '#')
-                                        .filter(|n| n.starts_with("#"))
-                                        .collect();
-
-                                    let Some(issue_number) = numbers[0].strip_prefix("#") else {
-                                        error!("Failed to get the issue number");
-                                        return Ok(HttpResponse::Ok().finish());
-                                    };
-
-                                    let Ok(issue) = gh
-                                        .issues(self.repository.0, self.repository.1)
-                                        .get(issue_number.parse().unwrap_or_default())
-                                        .await
-                                    else {
-                                        error!("Failed to get issue with number {}", issue_number);
-                                        return Ok(HttpResponse::Ok().finish());
-                                    };
-
-                                    let issue_body = issue
-                                        .body
-                                        .as_ref()
-                                        .map_or("<!-- No CHANGELOG Provided -->", |v| v);
-
-                                    let repo_release =
-                                        gh.repos(self.repository.0, self.repository.1);
-                                    let repo_release = repo_release.releases();
-
-                                    let tag_name = tag.branch_name();
-
-                                    let mut release = repo_release
-                                        .create(&tag_name)
-                                        .body(issue_body)
-                                        .name(&tag_name);
-
-                                    if !version.pre.is_empty() {
-                                        release = release.prerelease(true);
-                                    } else {
-                                        release = release.make_latest(
-                                            octocrab::repos::releases::MakeLatest::True,
-                                        );
-                                    }
-
-                                    if let Err(err) = release.send().await {
-                                        error!("Failed to create release. Error: {}", err);
-                                    }
-                                }
-
-                                // Create the tag
-                                return Ok(HttpResponse::Ok().finish());
-                            }
+                        if pull.pull_request.merged == Some(true) {
+                            let Some(commit_sha) = &pull.pull_request.merge_commit_sha else {
+                                return Err(WebhookError::MalformatedBody {
+                                    msg: String::from(
+                                        "The payload must contain `merge_commit_sha`",
+                                    ),
+                                });
+                            };
+
+                            let Some(pull_title) = &pull.pull_request.title else {
+                                return Err(WebhookError::MalformatedBody {
+                                    msg: String::from("The payload must contain `title`"),
+                                });
+                            };
+
+                            let release_pull = ReleasePull {
+                                number: pull.number,
+                                title: pull_title.clone(),
+                                body: pull.pull_request.body.clone().unwrap_or_default(),
+                                merge_commit_sha: commit_sha.clone(),
+                            };
+
+                            return Ok(publish_release(
+                                &forge,
+                                &self.state.app_username,
+                                &release_pull,
+                            )
+                            .await);
                         }
 
-                        // PR is closed, notify user
-                        if let Err(err) = gh
-                            .issues(self.repository.0, self.repository.1)
-                            .create_comment(
-                                pull.number,
-                                "You should remove the label `release-butler` from the issue that this PR is addressing instead \
-                                of manually closing it as this PR will be created again, if there is any activity on the issue. \
-                                If this PR was something else, please don't use PR(s) head branch that starts with `release-butler/` \
-                                as they are reserved for me."
-                                )
-                            .await
+                        // PR is closed, notify user unless closed by `/release-butler cancel`
+                        if self
+                            .payload
+                            .sender
+                            .as_ref()
+                            .is_none_or(|sender| sender.r#type != "Bot")
                         {
-                            error!("Failed to create PR comment. Error: {}", err);
+                            notify_closed_without_merge(&forge, pull.number).await;
                         }
                     }
                 }
@@ -203,3 +147,468 @@ impl<'a> Handler<'a> for PullsHandler<'a> {
         Ok(HttpResponse::Ok().finish())
     }
 }
+
+/// Number of the release issue from the body of a release pull request
+/// (`Fixes #{number} <OTHER STUFF>`)
+pub fn fixed_issue(body: &str) -> Option<u64> {
+    body.split(|c: char| !c.is_numeric() && c != '#')
+        .filter_map(|n| n.strip_prefix("#"))
+        .find_map(|n| n.parse::<u64>().ok())
+}
+
+/// Asks to remove the label from the release issue instead of closing the release pull request,
+/// as it would be opened again on the next activity on the issue
+pub async fn notify_closed_without_merge(forge: &dyn Forge, number: u64) {
+    if let Err(err) = forge
+        .comment_on_pull(
+            number,
+            "You should remove the label `release-butler` from the issue that this PR is addressing instead \
+            of manually closing it as this PR will be created again, if there is any activity on the issue. \
+            If this PR was something else, please don't use PR(s) head branch that starts with `release-butler/` \
+            as they are reserved for me."
+            )
+        .await
+    {
+        error!("Failed to create PR comment. Error: {}", err);
+    }
+}
+
+/// Tags the merge commit of a release pull request and creates the releases, if enabled for the
+/// packages. A tag (and release) is created for every package released by the pull request.
+/// Problems are reported as comments on the release issue (or the pull request).
+pub async fn publish_release(
+    forge: &dyn Forge,
+    app_username: &str,
+    pull: &ReleasePull,
+) -> HttpResponse {
+    let Some(releases) = pull.title.strip_prefix("RELEASE ") else {
+        if let Err(err) = forge
+            .comment_on_pull(
+                pull.number,
+                "The release title is malformated. It should be in the following format:\n\n`RELEASE <PACKAGE_NAME>@v<VERSION>`"
+                )
+            .await
+        {
+            error!("Failed to create PR comment. Error: {}", err);
+        }
+        return HttpResponse::Ok().finish();
+    };
+
+    let Ok(releases) = parse_releases(releases) else {
+        error!("Failed to parse the releases of PR #{}", pull.number);
+        return HttpResponse::Ok().finish();
+    };
+    let tags: Vec<_> = releases
+        .iter()
+        .map(|(package, version)| release::tag_name(package, version))
+        .collect();
+
+    let issue_number = fixed_issue(&pull.body);
+
+    let mut status = match issue_number {
+        Some(issue_number) => StatusComment::find(forge, issue_number).await,
+        None => None,
+    };
+    if let Some(status) = &mut status {
+        status
+            .complete(Step::MergePull, format!("in {}", pull.merge_commit_sha))
+            .await;
+    }
+
+    for tag in &tags {
+        if let Err(err) = forge.create_tag(tag, &pull.merge_commit_sha).await {
+            report(
+                &mut status,
+                forge,
+                issue_number,
+                pull.number,
+                Step::CreateTag,
+                &format!("create the tag `{}`", tag),
+                &err,
+            )
+            .await;
+            return HttpResponse::Ok().finish();
+        }
+    }
+    if let Some(status) = &mut status {
+        let detail: Vec<_> = tags.iter().map(|tag| format!("`{}`", tag)).collect();
+        status.complete(Step::CreateTag, detail.join(", ")).await;
+    }
+
+    let config = match get_config(forge, app_username).await {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            error!("Failed to fetch config file");
+            return HttpResponse::Ok().finish();
+        }
+        Err(err) => {
+            report(
+                &mut status,
+                forge,
+                issue_number,
+                pull.number,
+                Step::PublishRelease,
+                "fetch the configuration",
+                &err,
+            )
+            .await;
+            return HttpResponse::Ok().finish();
+        }
+    };
+
+    let mut issue = None;
+    // Tags of the existing releases, listed once a release is created. `Some(None)` if they
+    // couldn't be listed.
+    let mut existing_tags = None;
+    // Link to the release of each tag, if created
+    let mut release_urls = vec![None; tags.len()];
+    for (index, (package, version)) in releases.iter().enumerate() {
+        let package_information = if config.packages.len() == 1 {
+            config.packages.values().next()
+        } else {
+            config.packages.get(*package)
+        };
+        let Some(package_information) = package_information else {
+            error!("The package `{}` was not found in the config", package);
+            continue;
+        };
+        if !package_information.create_gh_release {
+            continue;
+        }
+
+        let Some(issue_number) = issue_number else {
+            error!("Failed to get the issue number");
+            return HttpResponse::Ok().finish();
+        };
+
+        if issue.is_none() {
+            match forge.get_issue(issue_number).await {
+                Ok(fetched) => issue = Some(fetched),
+                Err(err) => {
+                    report(
+                        &mut status,
+                        forge,
+                        Some(issue_number),
+                        pull.number,
+                        Step::PublishRelease,
+                        "fetch the release issue",
+                        &err,
+                    )
+                    .await;
+                    return HttpResponse::Ok().finish();
+                }
+            }
+        }
+
+        let issue_body = issue
+            .as_ref()
+            .and_then(|issue| issue.body.as_deref())
+            .map_or_else(
+                || String::from("<!-- No CHANGELOG Provided -->"),
+                release_notes,
+            );
+
+        let prerelease = !version.pre.is_empty();
+        if existing_tags.is_none() {
+            existing_tags = Some(match forge.list_releases().await {
+                Ok(releases) => Some(
+                    releases
+                        .into_iter()
+                        .map(|release| release.tag_name)
+                        .collect::<Vec<_>>(),
+                ),
+                Err(err) => {
+                    error!("Failed to list the releases. Error: {}", err);
+                    None
+                }
+            });
+        }
+        let make_latest = release::make_latest(
+            package,
+            version,
+            existing_tags.as_ref().and_then(Option::as_deref),
+        );
+
+        match forge
+            .create_release(&tags[index], &issue_body, prerelease, make_latest)
+            .await
+        {
+            Ok(release) => release_urls[index] = Some(release.html_url),
+            Err(err) => {
+                report(
+                    &mut status,
+                    forge,
+                    Some(issue_number),
+                    pull.number,
+                    Step::PublishRelease,
+                    &format!("create the release `{}`", tags[index]),
+                    &err,
+                )
+                .await;
+                return HttpResponse::Ok().finish();
+            }
+        }
+    }
+    if let Some(status) = &mut status {
+        let urls: Vec<_> = release_urls.iter().flatten().cloned().collect();
+        if !urls.is_empty() {
+            status.complete(Step::PublishRelease, urls.join(", ")).await;
+        }
+    }
+
+    let Some(issue_number) = issue_number else {
+        return HttpResponse::Ok().finish();
+    };
+
+    let released: Vec<_> = tags
+        .iter()
+        .zip(&release_urls)
+        .map(|(tag, url)| match url {
+            Some(url) => format!("[`{}`]({})", tag, url),
+            None => format!("`{}`", tag),
+        })
+        .collect();
+    let released_in = format!("Released in {}", released.join(", "));
+
+    // `Fixes #N` doesn't close the issue when the pull request targets another branch than the
+    // default one
+    if status.is_none() {
+        if let Err(err) = forge.comment(issue_number, &released_in).await {
+            error!(
+                "Failed to comment on issue #{}. Error: {}",
+                issue_number, err
+            );
+        }
+    }
+    if let Err(err) = forge.close_issue(issue_number).await {
+        error!("Failed to close issue #{}. Error: {}", issue_number, err);
+    }
+
+    if config.notify_referenced_issues {
+        let issue = match issue {
+            Some(issue) => issue,
+            None => match forge.get_issue(issue_number).await {
+                Ok(issue) => issue,
+                Err(err) => {
+                    error!("Failed to fetch issue #{}. Error: {}", issue_number, err);
+                    return HttpResponse::Ok().finish();
+                }
+            },
+        };
+
+        for number in referenced_issues(&release_notes(issue.body.as_deref().unwrap_or_default()))
+            .into_iter()
+            .filter(|number| *number != issue_number)
+        {
+            if let Err(err) = forge.comment(number, &released_in).await {
+                error!("Failed to comment on #{}. Error: {}", number, err);
+            }
+        }
+    }
+
+    HttpResponse::Ok().finish()
+}
+
+/// Reports a failure in the status comment of the release issue if it has one, as a new comment
+/// otherwise
+async fn report(
+    status: &mut Option<StatusComment<'_>>,
+    forge: &dyn Forge,
+    issue_number: Option<u64>,
+    pull_number: u64,
+    step: Step,
+    action: &str,
+    err: &ClientError,
+) {
+    match status {
+        Some(status) => status.fail(step, action, err).await,
+        None => report_failure(forge, issue_number, pull_number, action, err).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::{memory::InMemoryForge, MakeLatest};
+
+    static CONFIG: &str = r#"
+        version = 1
+        default_branch = "main"
+
+        [issues_meta_data]
+        allowed_authors = ["jdoe"]
+
+        [packages.example]
+        create_gh_release = true
+        package_manager = "cargo"
+    "#;
+
+    fn release_pull(title: &str) -> ReleasePull {
+        ReleasePull {
+            number: 101,
+            title: title.to_owned(),
+            body: String::from("Fixes #7\n\nThis is an automatically generated PR"),
+            merge_commit_sha: String::from("abc123"),
+        }
+    }
+
+    fn forge() -> InMemoryForge {
+        let forge = InMemoryForge::with_files(&[(".github/release-butler.toml", CONFIG)]);
+        forge.open_issue(7, "v1.2.3", "### Fixed\n- Bug Y", "jdoe", &[]);
+        forge
+    }
+
+    #[actix_web::test]
+    async fn test_publish_release() {
+        let forge = forge();
+        publish_release(
+            &forge,
+            "app/release-butler",
+            &release_pull("RELEASE @v1.2.3"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        assert_eq!(repository.tags["v1.2.3"], "abc123");
+        assert_eq!(repository.releases.len(), 1);
+        assert_eq!(repository.releases[0].tag, "v1.2.3");
+        assert_eq!(repository.releases[0].body, "### Fixed\n- Bug Y");
+        assert!(!repository.releases[0].prerelease);
+        assert_eq!(repository.releases[0].make_latest, MakeLatest::True);
+        assert_eq!(
+            repository.comments[&7],
+            ["Released in [`v1.2.3`](https://forge.test/releases/v1.2.3)"]
+        );
+        assert!(repository.issues[&7].closed);
+    }
+
+    #[actix_web::test]
+    async fn test_publish_release_of_older_version() {
+        let forge = InMemoryForge::with_files(&[(".github/release-butler.toml", CONFIG)]);
+        forge
+            .create_release("v2.0.0", "", false, MakeLatest::True)
+            .await
+            .unwrap();
+        forge.open_issue(7, "v1.4.1", "### Fixed\n- Bug Y", "jdoe", &[]);
+        publish_release(
+            &forge,
+            "app/release-butler",
+            &release_pull("RELEASE @v1.4.1"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        assert_eq!(repository.releases[1].tag, "v1.4.1");
+        assert_eq!(repository.releases[1].make_latest, MakeLatest::False);
+    }
+
+    #[actix_web::test]
+    async fn test_publish_release_from_issue_form() {
+        let forge = InMemoryForge::with_files(&[(".github/release-butler.toml", CONFIG)]);
+        forge.open_issue(
+            7,
+            "Release",
+            "### Version\n\n1.2.3\n\n### Changelog\n\n### Fixed\n- Bug Y",
+            "jdoe",
+            &[],
+        );
+        publish_release(
+            &forge,
+            "app/release-butler",
+            &release_pull("RELEASE @v1.2.3"),
+        )
+        .await;
+
+        assert_eq!(forge.repository().releases[0].body, "### Fixed\n- Bug Y");
+    }
+
+    #[actix_web::test]
+    async fn test_notify_referenced_issues() {
+        let forge = InMemoryForge::with_files(&[(
+            ".github/release-butler.toml",
+            &format!("notify_referenced_issues = true\n{}", CONFIG),
+        )]);
+        forge.open_issue(
+            7,
+            "v1.2.3",
+            "### Fixed\n- Bug Y (#3)\n- Bug Z (#101, #7, #3)",
+            "jdoe",
+            &[],
+        );
+        publish_release(
+            &forge,
+            "app/release-butler",
+            &release_pull("RELEASE @v1.2.3"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        assert_eq!(
+            repository.comments[&3],
+            ["Released in [`v1.2.3`](https://forge.test/releases/v1.2.3)"]
+        );
+        assert_eq!(repository.comments[&7].len(), 1);
+        // Same number as the pull request, but issues and merge requests are numbered separately
+        // on GitLab
+        assert_eq!(repository.comments[&101].len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_publish_prerelease() {
+        let forge = forge();
+        publish_release(
+            &forge,
+            "app/release-butler",
+            &release_pull("RELEASE @v1.3.0-rc.1"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        assert_eq!(repository.tags["v1.3.0-rc.1"], "abc123");
+        assert!(repository.releases[0].prerelease);
+        assert_eq!(repository.releases[0].make_latest, MakeLatest::False);
+    }
+
+    #[actix_web::test]
+    async fn test_existing_tag_is_reported() {
+        let forge = forge();
+        let pull = release_pull("RELEASE @v1.2.3");
+        publish_release(&forge, "app/release-butler", &pull).await;
+        publish_release(&forge, "app/release-butler", &pull).await;
+
+        let repository = forge.repository();
+        assert_eq!(repository.releases.len(), 1);
+        assert!(repository.comments[&7][1].contains("failed to create the tag"));
+    }
+
+    #[actix_web::test]
+    async fn test_publish_release_updates_status_comment() {
+        let forge = forge();
+        forge
+            .comment(
+                7,
+                "<!-- release-butler-status -->\n### Release `v1.2.3`\n\n\
+                - [x] Configuration loaded\n\
+                - [x] Pull request opened https://forge.test/pulls/101\n\
+                - [ ] Release published\n",
+            )
+            .await
+            .unwrap();
+
+        publish_release(
+            &forge,
+            "app/release-butler",
+            &release_pull("RELEASE @v1.2.3"),
+        )
+        .await;
+
+        let repository = forge.repository();
+        assert_eq!(repository.comments[&7].len(), 1);
+        assert!(repository.issues[&7].closed);
+        assert!(repository.comments[&7][0].ends_with(
+            "- [x] Pull request merged in abc123\n\
+            - [x] Tag created `v1.2.3`\n\
+            - [x] Release published https://forge.test/releases/v1.2.3\n"
+        ));
+    }
+}
@@ -0,0 +1,567 @@
+//! [`Forge`] backed by Gitea REST API (v1), which Forgejo implements too. Authenticated with an
+//! access token of a (bot) user.
+
+use super::{rest::RestClient, Forge, MakeLatest, Reaction};
+use crate::{
+    client::{self, ClientError, Comment, Issue, PullRequest, Release},
+    common::{encode_path, File, FileChange, FileMode, UpdateFilesError},
+    config::CommitMethod,
+};
+use base64::Engine;
+use http::Method;
+use serde::Deserialize;
+use tracing::error;
+
+/// Color of the labels created by release-butler, Gitea requires one
+static LABEL_COLOR: &str = "#ededed";
+
+/// Encodes every segment of `path`, keeping the separators
+fn encode_file_path(path: &str) -> String {
+    path.split('/')
+        .map(encode_path)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct User {
+    login: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Label {
+    id: u64,
+    name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct GiteaIssue {
+    number: u64,
+    title: String,
+    body: Option<String>,
+    html_url: String,
+    user: User,
+    #[serde(default)]
+    labels: Vec<Label>,
+}
+
+impl From<GiteaIssue> for Issue {
+    fn from(issue: GiteaIssue) -> Self {
+        Self {
+            number: issue.number,
+            title: issue.title,
+            // Gitea sends an empty string instead of null
+            body: issue.body.filter(|body| !body.is_empty()),
+            html_url: issue.html_url,
+            user: client::User {
+                login: issue.user.login,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Branch {
+    commit: BranchCommit,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BranchCommit {
+    id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct PullBranch {
+    #[serde(rename = "ref")]
+    ref_: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct GiteaPull {
+    number: u64,
+    html_url: String,
+    head: PullBranch,
+    base: PullBranch,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ContentsResponse {
+    sha: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct FilesResponse {
+    commit: FilesCommit,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct FilesCommit {
+    sha: String,
+}
+
+/// A single Gitea or Forgejo repository
+pub struct GiteaForge {
+    client: RestClient,
+    owner: String,
+    repo: String,
+    default_branch: String,
+}
+
+impl GiteaForge {
+    pub fn new(
+        client: RestClient,
+        owner: impl Into<String>,
+        repo: impl Into<String>,
+        default_branch: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            owner: owner.into(),
+            repo: repo.into(),
+            default_branch: default_branch.into(),
+        }
+    }
+
+    fn route(&self, route: &str) -> String {
+        format!(
+            "/repos/{}/{}{}",
+            encode_path(&self.owner),
+            encode_path(&self.repo),
+            route
+        )
+    }
+
+    /// Sha of the blob at `path` on `ref_`, which is required to update or delete a file
+    async fn blob_sha(&self, path: &str, ref_: &str) -> Result<Option<String>, ClientError> {
+        match self
+            .client
+            .get::<ContentsResponse>(&self.route(&format!(
+                "/contents/{}?ref={}",
+                encode_file_path(path),
+                encode_path(ref_)
+            )))
+            .await
+        {
+            Ok(contents) => Ok(Some(contents.sha)),
+            Err(err) if err.is_not_found() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Labels are referred by id in the API. Missing labels are created if `create` is set.
+    async fn label_id(&self, name: &str, create: bool) -> Result<Option<u64>, ClientError> {
+        let labels: Vec<Label> = self.client.get(&self.route("/labels?limit=50")).await?;
+        if let Some(label) = labels.into_iter().find(|label| label.name == name) {
+            return Ok(Some(label.id));
+        }
+        if !create {
+            return Ok(None);
+        }
+
+        let label: Label = self
+            .client
+            .post(
+                &self.route("/labels"),
+                &serde_json::json!({ "name": name, "color": LABEL_COLOR }),
+            )
+            .await?;
+        Ok(Some(label.id))
+    }
+
+    /// The contents API can only commit on top of a branch, so `branch` is created at `base_sha`
+    /// if it doesn't exist. Gitea can't move an existing branch, and deleting it would close its
+    /// pull request, so the commit then goes on top of its head instead. Returns the parent of
+    /// the commit.
+    async fn prepare_branch(&self, branch: &str, base_sha: &str) -> Result<String, ClientError> {
+        if let Some(head) = self.branch_head(branch).await? {
+            return Ok(head);
+        }
+
+        self.client
+            .post::<_, serde_json::Value>(
+                &self.route("/branches"),
+                &serde_json::json!({ "new_branch_name": branch, "old_ref_name": base_sha }),
+            )
+            .await?;
+        Ok(base_sha.to_owned())
+    }
+}
+
+#[async_trait::async_trait]
+impl Forge for GiteaForge {
+    async fn read_file(&self, path: &str) -> Result<Option<String>, ClientError> {
+        self.read_branch_file(&self.default_branch, path).await
+    }
+
+    async fn read_branch_file(
+        &self,
+        branch: &str,
+        path: &str,
+    ) -> Result<Option<String>, ClientError> {
+        match self
+            .client
+            .send(
+                Method::GET,
+                &self.route(&format!(
+                    "/raw/{}?ref={}",
+                    encode_file_path(path),
+                    encode_path(branch)
+                )),
+                None::<&()>,
+            )
+            .await
+        {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.is_not_found() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn branch_head(&self, branch: &str) -> Result<Option<String>, ClientError> {
+        match self
+            .client
+            .get::<Branch>(&self.route(&format!("/branches/{}", encode_file_path(branch))))
+            .await
+        {
+            Ok(branch) => Ok(Some(branch.commit.id)),
+            Err(err) if err.is_not_found() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates the commit with the contents API, which changes multiple files in a single commit.
+    /// The API can't set file modes, so only regular files are supported.
+    async fn commit_files(
+        &self,
+        branch: &str,
+        base_sha: &str,
+        message: &str,
+        files: Vec<File>,
+        _method: CommitMethod,
+    ) -> Result<String, UpdateFilesError> {
+        if files.is_empty() {
+            return Err(UpdateFilesError::NoFiles);
+        }
+
+        let parent = self
+            .prepare_branch(branch, base_sha)
+            .await
+            .map_err(|source| UpdateFilesError::Ref {
+                ref_: format!("heads/{}", branch),
+                source,
+            })?;
+
+        let mut changes = Vec::with_capacity(files.len());
+        for file in files {
+            if matches!(file.mode, Some(FileMode::Executable | FileMode::Symlink)) {
+                return Err(UpdateFilesError::UnsupportedMode { path: file.name });
+            }
+
+            let sha = self.blob_sha(&file.name, &parent).await.map_err(|source| {
+                UpdateFilesError::Blob {
+                    path: file.name.clone(),
+                    source,
+                }
+            })?;
+
+            let change = match (file.change, sha) {
+                // Nothing to delete
+                (FileChange::Delete, None) => continue,
+                (FileChange::Delete, Some(sha)) => serde_json::json!({
+                    "operation": "delete",
+                    "path": file.name,
+                    "sha": sha
+                }),
+                (change, sha) => {
+                    let content = match change {
+                        FileChange::Text(content) => {
+                            base64::prelude::BASE64_STANDARD.encode(content)
+                        }
+                        FileChange::Binary(content) => {
+                            base64::prelude::BASE64_STANDARD.encode(content)
+                        }
+                        FileChange::Delete => unreachable!(),
+                    };
+                    match sha {
+                        Some(sha) => serde_json::json!({
+                            "operation": "update",
+                            "path": file.name,
+                            "content": content,
+                            "sha": sha
+                        }),
+                        None => serde_json::json!({
+                            "operation": "create",
+                            "path": file.name,
+                            "content": content
+                        }),
+                    }
+                }
+            };
+            changes.push(change);
+        }
+
+        if changes.is_empty() {
+            return Err(UpdateFilesError::NoFiles);
+        }
+
+        let response: FilesResponse = self
+            .client
+            .post(
+                &self.route("/contents"),
+                &serde_json::json!({
+                    "branch": branch,
+                    "message": message,
+                    "files": changes
+                }),
+            )
+            .await
+            .map_err(|source| {
+                error!("Failed to create commit with Gitea API! Error: {}", source);
+                UpdateFilesError::Commit { source }
+            })?;
+
+        Ok(response.commit.sha)
+    }
+
+    async fn find_open_pull(
+        &self,
+        head: &str,
+        base: &str,
+    ) -> Result<Option<PullRequest>, ClientError> {
+        let pulls: Vec<GiteaPull> = self
+            .client
+            .get(&self.route("/pulls?state=open&limit=50"))
+            .await?;
+        Ok(pulls
+            .into_iter()
+            .find(|pull| pull.head.ref_ == head && pull.base.ref_ == base)
+            .map(|pull| PullRequest {
+                number: pull.number,
+                html_url: pull.html_url,
+            }))
+    }
+
+    async fn create_pull(
+        &self,
+        title: &str,
+        head: &str,
+        base: &str,
+        body: &str,
+    ) -> Result<PullRequest, ClientError> {
+        self.client
+            .post(
+                &self.route("/pulls"),
+                &serde_json::json!({
+                    "title": title,
+                    "head": head,
+                    "base": base,
+                    "body": body
+                }),
+            )
+            .await
+    }
+
+    async fn close_pull(&self, number: u64) -> Result<(), ClientError> {
+        self.client
+            .patch::<_, serde_json::Value>(
+                &self.route(&format!("/pulls/{}", number)),
+                &serde_json::json!({ "state": "closed" }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_branch(&self, branch: &str) -> Result<(), ClientError> {
+        self.client
+            .delete(&self.route(&format!("/branches/{}", encode_file_path(branch))))
+            .await
+    }
+
+    async fn get_issue(&self, number: u64) -> Result<Issue, ClientError> {
+        let issue: GiteaIssue = self
+            .client
+            .get(&self.route(&format!("/issues/{}", number)))
+            .await?;
+        Ok(issue.into())
+    }
+
+    async fn update_issue_title(&self, number: u64, title: &str) -> Result<(), ClientError> {
+        self.client
+            .patch::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}", number)),
+                &serde_json::json!({ "title": title }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn close_issue(&self, number: u64) -> Result<(), ClientError> {
+        self.client
+            .patch::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}", number)),
+                &serde_json::json!({ "state": "closed" }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn find_issues(&self, author: &str, label: &str) -> Result<Vec<Issue>, ClientError> {
+        let issues: Vec<GiteaIssue> = self
+            .client
+            .get(&self.route(&format!(
+                "/issues?state=open&type=issues&created_by={}&labels={}",
+                encode_path(author),
+                encode_path(label)
+            )))
+            .await?;
+        // Older versions ignore `created_by`
+        Ok(issues
+            .into_iter()
+            .filter(|issue| {
+                issue.user.login == author && issue.labels.iter().any(|l| l.name == label)
+            })
+            .map(Issue::from)
+            .collect())
+    }
+
+    async fn create_issue(
+        &self,
+        title: &str,
+        body: &str,
+        labels: &[&str],
+    ) -> Result<Issue, ClientError> {
+        let mut label_ids = Vec::with_capacity(labels.len());
+        for label in labels {
+            label_ids.extend(self.label_id(label, true).await?);
+        }
+
+        let issue: GiteaIssue = self
+            .client
+            .post(
+                &self.route("/issues"),
+                &serde_json::json!({
+                    "title": title,
+                    "body": body,
+                    "labels": label_ids
+                }),
+            )
+            .await?;
+        Ok(issue.into())
+    }
+
+    async fn comment(&self, number: u64, body: &str) -> Result<(), ClientError> {
+        self.client
+            .post::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}/comments", number)),
+                &serde_json::json!({ "body": body }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn list_comments(&self, number: u64) -> Result<Vec<Comment>, ClientError> {
+        // Gitea caps `limit` at 50 by default
+        self.client
+            .get_pages(&self.route(&format!("/issues/{}/comments", number)), 50)
+            .await
+    }
+
+    async fn update_comment(
+        &self,
+        _number: u64,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<(), ClientError> {
+        self.client
+            .patch::<_, serde_json::Value>(
+                &self.route(&format!("/issues/comments/{}", comment_id)),
+                &serde_json::json!({ "body": body }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn react(
+        &self,
+        _number: u64,
+        comment_id: u64,
+        reaction: Reaction,
+    ) -> Result<(), ClientError> {
+        self.client
+            .post::<_, serde_json::Value>(
+                &self.route(&format!("/issues/comments/{}/reactions", comment_id)),
+                &serde_json::json!({ "content": reaction.content() }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn add_label(&self, number: u64, label: &str) -> Result<(), ClientError> {
+        let label_ids: Vec<u64> = self.label_id(label, true).await?.into_iter().collect();
+        self.client
+            .post::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}/labels", number)),
+                &serde_json::json!({ "labels": label_ids }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_label(&self, number: u64, label: &str) -> Result<(), ClientError> {
+        let Some(label_id) = self.label_id(label, false).await? else {
+            return Ok(());
+        };
+        self.client
+            .delete(&self.route(&format!("/issues/{}/labels/{}", number, label_id)))
+            .await
+    }
+
+    async fn create_tag(&self, name: &str, sha: &str) -> Result<(), ClientError> {
+        self.client
+            .post::<_, serde_json::Value>(
+                &self.route("/tags"),
+                &serde_json::json!({ "tag_name": name, "target": sha }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Gitea shows the newest stable release as latest, so `make_latest` is ignored
+    async fn create_release(
+        &self,
+        tag: &str,
+        body: &str,
+        prerelease: bool,
+        _make_latest: MakeLatest,
+    ) -> Result<Release, ClientError> {
+        self.client
+            .post(
+                &self.route("/releases"),
+                &serde_json::json!({
+                    "tag_name": tag,
+                    "name": tag,
+                    "body": body,
+                    "prerelease": prerelease
+                }),
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_file_path() {
+        assert_eq!(
+            encode_file_path("crates/foo/Cargo.toml"),
+            "crates/foo/Cargo.toml"
+        );
+        assert_eq!(
+            encode_file_path("release-butler/foo@1.2.3"),
+            "release-butler/foo%401.2.3"
+        );
+        assert_eq!(encode_file_path("a b/c#d"), "a%20b/c%23d");
+    }
+}
@@ -0,0 +1,224 @@
+use super::{Forge, MakeLatest, Reaction};
+use crate::{
+    client::{ClientError, Comment, FileContent, GitHubClient, Issue, PullRequest, Release},
+    common::{File, UpdateFiles, UpdateFilesError},
+    config::CommitMethod,
+};
+use octocrab::params::repos::Reference;
+
+/// [`Forge`] backed by GitHub REST and GraphQL API
+pub struct GitHubForge {
+    gh: GitHubClient,
+    owner: String,
+    repo: String,
+}
+
+impl GitHubForge {
+    pub fn new(gh: GitHubClient, owner: impl Into<String>, repo: impl Into<String>) -> Self {
+        Self {
+            gh,
+            owner: owner.into(),
+            repo: repo.into(),
+        }
+    }
+
+    pub fn client(&self) -> &GitHubClient {
+        &self.gh
+    }
+}
+
+#[async_trait::async_trait]
+impl Forge for GitHubForge {
+    async fn read_file(&self, path: &str) -> Result<Option<String>, ClientError> {
+        let file = self
+            .gh
+            .get_file(&self.owner, &self.repo, path, None)
+            .await?;
+        decode_file(path, file)
+    }
+
+    async fn read_branch_file(
+        &self,
+        branch: &str,
+        path: &str,
+    ) -> Result<Option<String>, ClientError> {
+        let file = self
+            .gh
+            .get_file(&self.owner, &self.repo, path, Some(branch))
+            .await?;
+        decode_file(path, file)
+    }
+
+    async fn branch_head(&self, branch: &str) -> Result<Option<String>, ClientError> {
+        Ok(self
+            .gh
+            .get_ref(&self.owner, &self.repo, &format!("heads/{}", branch))
+            .await?
+            .map(|ref_| ref_.object.sha))
+    }
+
+    async fn commit_files(
+        &self,
+        branch: &str,
+        base_sha: &str,
+        message: &str,
+        files: Vec<File>,
+        method: CommitMethod,
+    ) -> Result<String, UpdateFilesError> {
+        let branch = Reference::Branch(branch.to_owned());
+        UpdateFiles::new(&self.gh, files, &branch, message.to_owned(), method)
+            .execute(&self.owner, &self.repo, base_sha)
+            .await
+    }
+
+    async fn find_open_pull(
+        &self,
+        head: &str,
+        base: &str,
+    ) -> Result<Option<PullRequest>, ClientError> {
+        Ok(self
+            .gh
+            .list_open_pulls(&self.owner, &self.repo, base, head)
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    async fn create_pull(
+        &self,
+        title: &str,
+        head: &str,
+        base: &str,
+        body: &str,
+    ) -> Result<PullRequest, ClientError> {
+        self.gh
+            .create_pull(&self.owner, &self.repo, title, head, base, body)
+            .await
+    }
+
+    async fn close_pull(&self, number: u64) -> Result<(), ClientError> {
+        self.gh.close_pull(&self.owner, &self.repo, number).await
+    }
+
+    async fn delete_branch(&self, branch: &str) -> Result<(), ClientError> {
+        let branch = Reference::Branch(branch.to_owned());
+        self.gh
+            .delete_ref(&self.owner, &self.repo, &branch.ref_url())
+            .await
+    }
+
+    async fn get_issue(&self, number: u64) -> Result<Issue, ClientError> {
+        self.gh.get_issue(&self.owner, &self.repo, number).await
+    }
+
+    async fn update_issue_title(&self, number: u64, title: &str) -> Result<(), ClientError> {
+        self.gh
+            .update_issue_title(&self.owner, &self.repo, number, title)
+            .await
+    }
+
+    async fn close_issue(&self, number: u64) -> Result<(), ClientError> {
+        self.gh.close_issue(&self.owner, &self.repo, number).await
+    }
+
+    async fn find_issues(&self, author: &str, label: &str) -> Result<Vec<Issue>, ClientError> {
+        self.gh
+            .list_issues(&self.owner, &self.repo, author, label)
+            .await
+    }
+
+    async fn create_issue(
+        &self,
+        title: &str,
+        body: &str,
+        labels: &[&str],
+    ) -> Result<Issue, ClientError> {
+        self.gh
+            .create_issue(&self.owner, &self.repo, title, body, labels)
+            .await
+    }
+
+    async fn comment(&self, number: u64, body: &str) -> Result<(), ClientError> {
+        self.gh
+            .create_comment(&self.owner, &self.repo, number, body)
+            .await
+    }
+
+    async fn list_comments(&self, number: u64) -> Result<Vec<Comment>, ClientError> {
+        self.gh.list_comments(&self.owner, &self.repo, number).await
+    }
+
+    async fn update_comment(
+        &self,
+        _number: u64,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<(), ClientError> {
+        self.gh
+            .update_comment(&self.owner, &self.repo, comment_id, body)
+            .await
+    }
+
+    async fn react(
+        &self,
+        _number: u64,
+        comment_id: u64,
+        reaction: Reaction,
+    ) -> Result<(), ClientError> {
+        self.gh
+            .create_comment_reaction(&self.owner, &self.repo, comment_id, reaction.content())
+            .await
+    }
+
+    async fn add_label(&self, number: u64, label: &str) -> Result<(), ClientError> {
+        self.gh
+            .add_labels(&self.owner, &self.repo, number, &[label])
+            .await
+    }
+
+    async fn remove_label(&self, number: u64, label: &str) -> Result<(), ClientError> {
+        self.gh
+            .remove_label(&self.owner, &self.repo, number, label)
+            .await
+    }
+
+    async fn create_tag(&self, name: &str, sha: &str) -> Result<(), ClientError> {
+        let tag = Reference::Tag(name.to_owned());
+        self.gh
+            .create_ref(&self.owner, &self.repo, &tag.ref_url(), sha)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_release(
+        &self,
+        tag: &str,
+        body: &str,
+        prerelease: bool,
+        make_latest: MakeLatest,
+    ) -> Result<Release, ClientError> {
+        self.gh
+            .create_release(
+                &self.owner,
+                &self.repo,
+                tag,
+                body,
+                prerelease,
+                make_latest.as_str(),
+            )
+            .await
+    }
+
+    async fn list_releases(&self) -> Result<Vec<Release>, ClientError> {
+        self.gh.list_releases(&self.owner, &self.repo).await
+    }
+}
+
+fn decode_file(path: &str, file: Option<FileContent>) -> Result<Option<String>, ClientError> {
+    match file {
+        Some(file) => file.decoded_content().map(Some).ok_or(ClientError::Decode {
+            msg: format!("`{}` isn't valid base64 encoded UTF-8", path),
+        }),
+        None => Ok(None),
+    }
+}
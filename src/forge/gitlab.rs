@@ -0,0 +1,520 @@
+//! [`Forge`] backed by GitLab REST API (v4), authenticated with a personal, project or group
+//! access token.
+
+use super::{rest::RestClient, Forge, MakeLatest, Reaction};
+use crate::{
+    client::{ClientError, Comment, Issue, PullRequest, Release, User},
+    common::{encode_path, File, FileChange, FileMode, UpdateFilesError},
+    config::CommitMethod,
+};
+use base64::Engine;
+use http::Method;
+use serde::Deserialize;
+use tracing::error;
+
+/// Name of the award emoji for `reaction`
+fn award_emoji(reaction: Reaction) -> &'static str {
+    match reaction {
+        Reaction::ThumbsUp => "thumbsup",
+        Reaction::ThumbsDown => "thumbsdown",
+        Reaction::Confused => "confused",
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct GitLabUser {
+    username: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct GitLabIssue {
+    iid: u64,
+    title: String,
+    description: Option<String>,
+    web_url: String,
+    author: GitLabUser,
+}
+
+impl From<GitLabIssue> for Issue {
+    fn from(issue: GitLabIssue) -> Self {
+        Self {
+            number: issue.iid,
+            title: issue.title,
+            body: issue.description,
+            html_url: issue.web_url,
+            user: User {
+                login: issue.author.username,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct MergeRequest {
+    iid: u64,
+    web_url: String,
+}
+
+impl From<MergeRequest> for PullRequest {
+    fn from(merge_request: MergeRequest) -> Self {
+        Self {
+            number: merge_request.iid,
+            html_url: merge_request.web_url,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Commit {
+    id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Branch {
+    commit: Commit,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct GitLabRelease {
+    tag_name: String,
+    #[serde(rename = "_links")]
+    links: ReleaseLinks,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ReleaseLinks {
+    #[serde(rename = "self")]
+    self_: String,
+}
+
+/// A single GitLab project
+pub struct GitLabForge {
+    gl: RestClient,
+    /// Project path with namespace, i.e. `group/project`
+    project: String,
+    default_branch: String,
+}
+
+impl GitLabForge {
+    pub fn new(
+        gl: RestClient,
+        project: impl Into<String>,
+        default_branch: impl Into<String>,
+    ) -> Self {
+        Self {
+            gl,
+            project: project.into(),
+            default_branch: default_branch.into(),
+        }
+    }
+
+    fn route(&self, route: &str) -> String {
+        format!("/projects/{}{}", encode_path(&self.project), route)
+    }
+
+    /// Username of the author of the issue. Issue webhooks only contain the id of the author.
+    pub async fn issue_author(&self, number: u64) -> Result<String, ClientError> {
+        let issue: GitLabIssue = self
+            .gl
+            .get(&self.route(&format!("/issues/{}", number)))
+            .await?;
+        Ok(issue.author.username)
+    }
+
+    /// Whether the file exists on `ref_`, which decides between `create` and `update` actions
+    async fn file_exists(&self, path: &str, ref_: &str) -> Result<bool, ClientError> {
+        match self
+            .gl
+            .get::<serde_json::Value>(&self.route(&format!(
+                "/repository/files/{}?ref={}",
+                encode_path(path),
+                encode_path(ref_)
+            )))
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(err) if err.is_not_found() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Forge for GitLabForge {
+    async fn read_file(&self, path: &str) -> Result<Option<String>, ClientError> {
+        self.read_branch_file(&self.default_branch, path).await
+    }
+
+    async fn read_branch_file(
+        &self,
+        branch: &str,
+        path: &str,
+    ) -> Result<Option<String>, ClientError> {
+        match self
+            .gl
+            .send(
+                Method::GET,
+                &self.route(&format!(
+                    "/repository/files/{}/raw?ref={}",
+                    encode_path(path),
+                    encode_path(branch)
+                )),
+                None::<&()>,
+            )
+            .await
+        {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.is_not_found() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn branch_head(&self, branch: &str) -> Result<Option<String>, ClientError> {
+        match self
+            .gl
+            .get::<Branch>(&self.route(&format!("/repository/branches/{}", encode_path(branch))))
+            .await
+        {
+            Ok(branch) => Ok(Some(branch.commit.id)),
+            Err(err) if err.is_not_found() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates the commit with the commits API, which is a single atomic request. `force`
+    /// overwrites the branch with `base_sha` as parent, same as the GitHub implementation.
+    async fn commit_files(
+        &self,
+        branch: &str,
+        base_sha: &str,
+        message: &str,
+        files: Vec<File>,
+        _method: CommitMethod,
+    ) -> Result<String, UpdateFilesError> {
+        if files.is_empty() {
+            return Err(UpdateFilesError::NoFiles);
+        }
+
+        let mut actions = Vec::with_capacity(files.len());
+        for file in files {
+            if file.mode == Some(FileMode::Symlink) {
+                return Err(UpdateFilesError::UnsupportedMode { path: file.name });
+            }
+
+            let (action, content, encoding) = match file.change {
+                FileChange::Delete => ("delete", None, "text"),
+                change => {
+                    let exists =
+                        self.file_exists(&file.name, base_sha)
+                            .await
+                            .map_err(|source| UpdateFilesError::Blob {
+                                path: file.name.clone(),
+                                source,
+                            })?;
+                    let action = if exists { "update" } else { "create" };
+
+                    match change {
+                        FileChange::Text(content) => (action, Some(content), "text"),
+                        FileChange::Binary(content) => (
+                            action,
+                            Some(base64::prelude::BASE64_STANDARD.encode(content)),
+                            "base64",
+                        ),
+                        FileChange::Delete => unreachable!(),
+                    }
+                }
+            };
+
+            let mut action = serde_json::json!({
+                "action": action,
+                "file_path": file.name,
+            });
+            if let Some(content) = content {
+                action["content"] = content.into();
+                action["encoding"] = encoding.into();
+            }
+            if let Some(mode) = file.mode {
+                action["execute_filemode"] = (mode == FileMode::Executable).into();
+            }
+            actions.push(action);
+        }
+
+        let commit: Commit = self
+            .gl
+            .post(
+                &self.route("/repository/commits"),
+                &serde_json::json!({
+                    "branch": branch,
+                    "start_sha": base_sha,
+                    "commit_message": message,
+                    "actions": actions,
+                    "force": true
+                }),
+            )
+            .await
+            .map_err(|source| {
+                error!("Failed to create commit with GitLab API! Error: {}", source);
+                UpdateFilesError::Commit { source }
+            })?;
+
+        Ok(commit.id)
+    }
+
+    async fn find_open_pull(
+        &self,
+        head: &str,
+        base: &str,
+    ) -> Result<Option<PullRequest>, ClientError> {
+        let merge_requests: Vec<MergeRequest> = self
+            .gl
+            .get(&self.route(&format!(
+                "/merge_requests?state=opened&source_branch={}&target_branch={}",
+                encode_path(head),
+                encode_path(base)
+            )))
+            .await?;
+        Ok(merge_requests.into_iter().next().map(PullRequest::from))
+    }
+
+    async fn create_pull(
+        &self,
+        title: &str,
+        head: &str,
+        base: &str,
+        body: &str,
+    ) -> Result<PullRequest, ClientError> {
+        let merge_request: MergeRequest = self
+            .gl
+            .post(
+                &self.route("/merge_requests"),
+                &serde_json::json!({
+                    "title": title,
+                    "source_branch": head,
+                    "target_branch": base,
+                    "description": body,
+                    "remove_source_branch": true
+                }),
+            )
+            .await?;
+        Ok(merge_request.into())
+    }
+
+    async fn close_pull(&self, number: u64) -> Result<(), ClientError> {
+        self.gl
+            .put::<_, serde_json::Value>(
+                &self.route(&format!("/merge_requests/{}", number)),
+                &serde_json::json!({ "state_event": "close" }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_branch(&self, branch: &str) -> Result<(), ClientError> {
+        self.gl
+            .delete(&self.route(&format!("/repository/branches/{}", encode_path(branch))))
+            .await
+    }
+
+    async fn get_issue(&self, number: u64) -> Result<Issue, ClientError> {
+        let issue: GitLabIssue = self
+            .gl
+            .get(&self.route(&format!("/issues/{}", number)))
+            .await?;
+        Ok(issue.into())
+    }
+
+    async fn update_issue_title(&self, number: u64, title: &str) -> Result<(), ClientError> {
+        self.gl
+            .put::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}", number)),
+                &serde_json::json!({ "title": title }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn close_issue(&self, number: u64) -> Result<(), ClientError> {
+        self.gl
+            .put::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}", number)),
+                &serde_json::json!({ "state_event": "close" }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn find_issues(&self, author: &str, label: &str) -> Result<Vec<Issue>, ClientError> {
+        let issues: Vec<GitLabIssue> = self
+            .gl
+            .get(&self.route(&format!(
+                "/issues?state=opened&author_username={}&labels={}",
+                encode_path(author),
+                encode_path(label)
+            )))
+            .await?;
+        Ok(issues.into_iter().map(Issue::from).collect())
+    }
+
+    async fn create_issue(
+        &self,
+        title: &str,
+        body: &str,
+        labels: &[&str],
+    ) -> Result<Issue, ClientError> {
+        let issue: GitLabIssue = self
+            .gl
+            .post(
+                &self.route("/issues"),
+                &serde_json::json!({
+                    "title": title,
+                    "description": body,
+                    "labels": labels.join(",")
+                }),
+            )
+            .await?;
+        Ok(issue.into())
+    }
+
+    async fn comment(&self, number: u64, body: &str) -> Result<(), ClientError> {
+        self.gl
+            .post::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}/notes", number)),
+                &serde_json::json!({ "body": body }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Issues and merge requests are numbered independently on GitLab
+    async fn comment_on_pull(&self, number: u64, body: &str) -> Result<(), ClientError> {
+        self.gl
+            .post::<_, serde_json::Value>(
+                &self.route(&format!("/merge_requests/{}/notes", number)),
+                &serde_json::json!({ "body": body }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Includes system notes, i.e. label changes
+    async fn list_comments(&self, number: u64) -> Result<Vec<Comment>, ClientError> {
+        self.gl
+            .get_pages(
+                &self.route(&format!(
+                    "/issues/{}/notes?sort=asc&order_by=created_at",
+                    number
+                )),
+                100,
+            )
+            .await
+    }
+
+    async fn update_comment(
+        &self,
+        number: u64,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<(), ClientError> {
+        self.gl
+            .put::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}/notes/{}", number, comment_id)),
+                &serde_json::json!({ "body": body }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn react(
+        &self,
+        number: u64,
+        comment_id: u64,
+        reaction: Reaction,
+    ) -> Result<(), ClientError> {
+        self.gl
+            .post::<_, serde_json::Value>(
+                &self.route(&format!(
+                    "/issues/{}/notes/{}/award_emoji",
+                    number, comment_id
+                )),
+                &serde_json::json!({ "name": award_emoji(reaction) }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn react_on_pull(
+        &self,
+        number: u64,
+        comment_id: u64,
+        reaction: Reaction,
+    ) -> Result<(), ClientError> {
+        self.gl
+            .post::<_, serde_json::Value>(
+                &self.route(&format!(
+                    "/merge_requests/{}/notes/{}/award_emoji",
+                    number, comment_id
+                )),
+                &serde_json::json!({ "name": award_emoji(reaction) }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn add_label(&self, number: u64, label: &str) -> Result<(), ClientError> {
+        self.gl
+            .put::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}", number)),
+                &serde_json::json!({ "add_labels": label }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_label(&self, number: u64, label: &str) -> Result<(), ClientError> {
+        self.gl
+            .put::<_, serde_json::Value>(
+                &self.route(&format!("/issues/{}", number)),
+                &serde_json::json!({ "remove_labels": label }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn create_tag(&self, name: &str, sha: &str) -> Result<(), ClientError> {
+        self.gl
+            .post::<_, serde_json::Value>(
+                &self.route("/repository/tags"),
+                &serde_json::json!({ "tag_name": name, "ref": sha }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// GitLab has no notion of prereleases or latest release (it is decided by release date),
+    /// so `prerelease` and `make_latest` are ignored
+    async fn create_release(
+        &self,
+        tag: &str,
+        body: &str,
+        _prerelease: bool,
+        _make_latest: MakeLatest,
+    ) -> Result<Release, ClientError> {
+        let release: GitLabRelease = self
+            .gl
+            .post(
+                &self.route("/releases"),
+                &serde_json::json!({
+                    "tag_name": tag,
+                    "name": tag,
+                    "description": body
+                }),
+            )
+            .await?;
+        // GitLab releases are identified by their tag
+        Ok(Release {
+            id: 0,
+            tag_name: release.tag_name,
+            html_url: release.links.self_,
+        })
+    }
+}
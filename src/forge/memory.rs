@@ -0,0 +1,458 @@
+//! A [`Forge`] keeping a single repository in memory, for testing the release logic without
+//! any HTTP server.
+
+use super::{Forge, MakeLatest, Reaction};
+use crate::{
+    client::{ClientError, Comment, Issue, PullRequest, Release, User},
+    common::{File, FileChange, UpdateFilesError},
+    config::CommitMethod,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+};
+
+pub type Files = BTreeMap<String, Vec<u8>>;
+
+#[derive(Debug, Clone)]
+pub struct StoredIssue {
+    pub title: String,
+    pub body: String,
+    pub author: String,
+    pub labels: Vec<String>,
+    pub closed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredPull {
+    pub number: u64,
+    pub title: String,
+    pub head: String,
+    pub base: String,
+    pub body: String,
+    pub closed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredRelease {
+    pub tag: String,
+    pub body: String,
+    pub prerelease: bool,
+    pub make_latest: MakeLatest,
+}
+
+#[derive(Debug, Clone)]
+pub struct Repository {
+    pub default_branch: String,
+    /// Snapshot of every file, keyed by commit sha
+    pub commits: BTreeMap<String, Files>,
+    /// Branch name to commit sha
+    pub branches: BTreeMap<String, String>,
+    /// Tag name to commit sha
+    pub tags: BTreeMap<String, String>,
+    pub issues: BTreeMap<u64, StoredIssue>,
+    pub pulls: Vec<StoredPull>,
+    pub releases: Vec<StoredRelease>,
+    /// Comments on issues and pull requests
+    pub comments: BTreeMap<u64, Vec<String>>,
+    /// Reactions to comments, keyed by comment id
+    pub reactions: BTreeMap<u64, Vec<Reaction>>,
+    next_number: u64,
+}
+
+impl Default for Repository {
+    fn default() -> Self {
+        Self {
+            default_branch: String::from("main"),
+            commits: BTreeMap::new(),
+            branches: BTreeMap::new(),
+            tags: BTreeMap::new(),
+            issues: BTreeMap::new(),
+            pulls: Vec::new(),
+            releases: Vec::new(),
+            comments: BTreeMap::new(),
+            reactions: BTreeMap::new(),
+            next_number: 100,
+        }
+    }
+}
+
+impl Repository {
+    fn next_number(&mut self) -> u64 {
+        self.next_number += 1;
+        self.next_number
+    }
+
+    /// Content of the file at `path` on `branch`
+    pub fn file(&self, branch: &str, path: &str) -> Option<String> {
+        let sha = self.branches.get(branch)?;
+        let content = self.commits.get(sha)?.get(path)?;
+        Some(String::from_utf8_lossy(content).into_owned())
+    }
+
+    fn commit(&mut self, files: Files) -> String {
+        let sha = format!("{:040x}", self.commits.len() + 1);
+        self.commits.insert(sha.clone(), files);
+        sha
+    }
+
+    fn issue(&self, number: u64) -> Result<Issue, ClientError> {
+        let issue = self.issues.get(&number).ok_or_else(not_found)?;
+        Ok(Issue {
+            number,
+            title: issue.title.clone(),
+            body: Some(issue.body.clone()),
+            html_url: format!("https://forge.test/issues/{}", number),
+            user: User {
+                login: issue.author.clone(),
+            },
+        })
+    }
+}
+
+fn not_found() -> ClientError {
+    ClientError::Api {
+        status: 404,
+        message: String::from("Not Found"),
+    }
+}
+
+fn unprocessable(message: &str) -> ClientError {
+    ClientError::Api {
+        status: 422,
+        message: message.to_owned(),
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryForge {
+    repository: Mutex<Repository>,
+}
+
+impl InMemoryForge {
+    /// A repository with a single commit on the default branch containing `files`
+    pub fn with_files(files: &[(&str, &str)]) -> Self {
+        let forge = Self::default();
+        {
+            let mut repository = forge.repository();
+            let sha = repository.commit(
+                files
+                    .iter()
+                    .map(|(path, content)| (path.to_string(), content.as_bytes().to_vec()))
+                    .collect(),
+            );
+            let default_branch = repository.default_branch.clone();
+            repository.branches.insert(default_branch, sha);
+        }
+        forge
+    }
+
+    pub fn repository(&self) -> MutexGuard<'_, Repository> {
+        self.repository.lock().unwrap()
+    }
+
+    pub fn open_issue(&self, number: u64, title: &str, body: &str, author: &str, labels: &[&str]) {
+        self.repository().issues.insert(
+            number,
+            StoredIssue {
+                title: title.to_owned(),
+                body: body.to_owned(),
+                author: author.to_owned(),
+                labels: labels.iter().map(|label| label.to_string()).collect(),
+                closed: false,
+            },
+        );
+    }
+
+    pub fn comments(&self, number: u64) -> Vec<String> {
+        self.repository()
+            .comments
+            .get(&number)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait::async_trait]
+impl Forge for InMemoryForge {
+    async fn read_file(&self, path: &str) -> Result<Option<String>, ClientError> {
+        let repository = self.repository();
+        Ok(repository.file(&repository.default_branch, path))
+    }
+
+    async fn read_branch_file(
+        &self,
+        branch: &str,
+        path: &str,
+    ) -> Result<Option<String>, ClientError> {
+        Ok(self.repository().file(branch, path))
+    }
+
+    async fn branch_head(&self, branch: &str) -> Result<Option<String>, ClientError> {
+        Ok(self.repository().branches.get(branch).cloned())
+    }
+
+    async fn commit_files(
+        &self,
+        branch: &str,
+        base_sha: &str,
+        _message: &str,
+        files: Vec<File>,
+        _method: CommitMethod,
+    ) -> Result<String, UpdateFilesError> {
+        if files.is_empty() {
+            return Err(UpdateFilesError::NoFiles);
+        }
+
+        let mut repository = self.repository();
+        let Some(mut snapshot) = repository.commits.get(base_sha).cloned() else {
+            return Err(UpdateFilesError::Tree {
+                source: unprocessable("Base commit does not exist"),
+            });
+        };
+
+        for file in files {
+            match file.change {
+                FileChange::Text(content) => {
+                    snapshot.insert(file.name, content.into_bytes());
+                }
+                FileChange::Binary(content) => {
+                    snapshot.insert(file.name, content);
+                }
+                FileChange::Delete => {
+                    snapshot.remove(&file.name);
+                }
+            }
+        }
+
+        let sha = repository.commit(snapshot);
+        repository.branches.insert(branch.to_owned(), sha.clone());
+        Ok(sha)
+    }
+
+    async fn find_open_pull(
+        &self,
+        head: &str,
+        base: &str,
+    ) -> Result<Option<PullRequest>, ClientError> {
+        Ok(self
+            .repository()
+            .pulls
+            .iter()
+            .find(|pull| !pull.closed && pull.head == head && pull.base == base)
+            .map(|pull| PullRequest {
+                number: pull.number,
+                html_url: format!("https://forge.test/pulls/{}", pull.number),
+            }))
+    }
+
+    async fn create_pull(
+        &self,
+        title: &str,
+        head: &str,
+        base: &str,
+        body: &str,
+    ) -> Result<PullRequest, ClientError> {
+        let mut repository = self.repository();
+        if !repository.branches.contains_key(head) {
+            return Err(unprocessable("head does not exist"));
+        }
+
+        let number = repository.next_number();
+        repository.pulls.push(StoredPull {
+            number,
+            title: title.to_owned(),
+            head: head.to_owned(),
+            base: base.to_owned(),
+            body: body.to_owned(),
+            closed: false,
+        });
+        Ok(PullRequest {
+            number,
+            html_url: format!("https://forge.test/pulls/{}", number),
+        })
+    }
+
+    async fn close_pull(&self, number: u64) -> Result<(), ClientError> {
+        let mut repository = self.repository();
+        let pull = repository
+            .pulls
+            .iter_mut()
+            .find(|pull| pull.number == number)
+            .ok_or_else(not_found)?;
+        pull.closed = true;
+        Ok(())
+    }
+
+    async fn delete_branch(&self, branch: &str) -> Result<(), ClientError> {
+        self.repository()
+            .branches
+            .remove(branch)
+            .map(|_| ())
+            .ok_or_else(not_found)
+    }
+
+    async fn get_issue(&self, number: u64) -> Result<Issue, ClientError> {
+        self.repository().issue(number)
+    }
+
+    async fn update_issue_title(&self, number: u64, title: &str) -> Result<(), ClientError> {
+        let mut repository = self.repository();
+        let issue = repository.issues.get_mut(&number).ok_or_else(not_found)?;
+        issue.title = title.to_owned();
+        Ok(())
+    }
+
+    async fn close_issue(&self, number: u64) -> Result<(), ClientError> {
+        let mut repository = self.repository();
+        let issue = repository.issues.get_mut(&number).ok_or_else(not_found)?;
+        issue.closed = true;
+        Ok(())
+    }
+
+    async fn find_issues(&self, author: &str, label: &str) -> Result<Vec<Issue>, ClientError> {
+        let repository = self.repository();
+        repository
+            .issues
+            .iter()
+            .filter(|(_, issue)| {
+                !issue.closed && issue.author == author && issue.labels.iter().any(|l| l == label)
+            })
+            .map(|(number, _)| repository.issue(*number))
+            .collect()
+    }
+
+    async fn create_issue(
+        &self,
+        title: &str,
+        body: &str,
+        labels: &[&str],
+    ) -> Result<Issue, ClientError> {
+        let mut repository = self.repository();
+        let number = repository.next_number();
+        repository.issues.insert(
+            number,
+            StoredIssue {
+                title: title.to_owned(),
+                body: body.to_owned(),
+                author: String::new(),
+                labels: labels.iter().map(|label| label.to_string()).collect(),
+                closed: false,
+            },
+        );
+        repository.issue(number)
+    }
+
+    async fn comment(&self, number: u64, body: &str) -> Result<(), ClientError> {
+        self.repository()
+            .comments
+            .entry(number)
+            .or_default()
+            .push(body.to_owned());
+        Ok(())
+    }
+
+    /// Comment ids are 1-based positions in the comments of the issue
+    async fn list_comments(&self, number: u64) -> Result<Vec<Comment>, ClientError> {
+        Ok(self
+            .comments(number)
+            .into_iter()
+            .enumerate()
+            .map(|(index, body)| Comment {
+                id: index as u64 + 1,
+                body: Some(body),
+            })
+            .collect())
+    }
+
+    async fn update_comment(
+        &self,
+        number: u64,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<(), ClientError> {
+        let mut repository = self.repository();
+        let comment = repository
+            .comments
+            .get_mut(&number)
+            .and_then(|comments| comments.get_mut((comment_id as usize).checked_sub(1)?))
+            .ok_or_else(not_found)?;
+        *comment = body.to_owned();
+        Ok(())
+    }
+
+    async fn react(
+        &self,
+        _number: u64,
+        comment_id: u64,
+        reaction: Reaction,
+    ) -> Result<(), ClientError> {
+        self.repository()
+            .reactions
+            .entry(comment_id)
+            .or_default()
+            .push(reaction);
+        Ok(())
+    }
+
+    async fn add_label(&self, number: u64, label: &str) -> Result<(), ClientError> {
+        let mut repository = self.repository();
+        let issue = repository.issues.get_mut(&number).ok_or_else(not_found)?;
+        if !issue.labels.iter().any(|l| l == label) {
+            issue.labels.push(label.to_owned());
+        }
+        Ok(())
+    }
+
+    async fn remove_label(&self, number: u64, label: &str) -> Result<(), ClientError> {
+        let mut repository = self.repository();
+        let issue = repository.issues.get_mut(&number).ok_or_else(not_found)?;
+        issue.labels.retain(|l| l != label);
+        Ok(())
+    }
+
+    async fn create_tag(&self, name: &str, sha: &str) -> Result<(), ClientError> {
+        let mut repository = self.repository();
+        if repository.tags.contains_key(name) {
+            return Err(unprocessable("Reference already exists"));
+        }
+        repository.tags.insert(name.to_owned(), sha.to_owned());
+        Ok(())
+    }
+
+    async fn create_release(
+        &self,
+        tag: &str,
+        body: &str,
+        prerelease: bool,
+        make_latest: MakeLatest,
+    ) -> Result<Release, ClientError> {
+        let mut repository = self.repository();
+        repository.releases.push(StoredRelease {
+            tag: tag.to_owned(),
+            body: body.to_owned(),
+            prerelease,
+            make_latest,
+        });
+        Ok(Release {
+            id: repository.releases.len() as u64,
+            tag_name: tag.to_owned(),
+            html_url: format!("https://forge.test/releases/{}", tag),
+        })
+    }
+
+    async fn list_releases(&self) -> Result<Vec<Release>, ClientError> {
+        Ok(self
+            .repository()
+            .releases
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, release)| Release {
+                id: index as u64 + 1,
+                tag_name: release.tag.clone(),
+                html_url: format!("https://forge.test/releases/{}", release.tag),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,189 @@
+//! The operations release-butler needs from the service hosting a repository.
+//!
+//! The release logic in [`crate::events`] only talks to a [`Forge`], so it can be exercised
+//! against [`memory::InMemoryForge`] in tests and reused for hosts other than GitHub.
+
+use crate::{
+    client::{ClientError, Comment, Issue, PullRequest, Release},
+    common::{File, UpdateFilesError},
+    config::CommitMethod,
+};
+
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+#[cfg(any(test, feature = "tests"))]
+pub mod memory;
+pub mod rest;
+
+pub use gitea::GiteaForge;
+pub use github::GitHubForge;
+pub use gitlab::GitLabForge;
+
+/// Reaction acknowledging a comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reaction {
+    ThumbsUp,
+    ThumbsDown,
+    Confused,
+}
+
+impl Reaction {
+    /// Name used by GitHub, Gitea and Forgejo
+    pub fn content(self) -> &'static str {
+        match self {
+            Reaction::ThumbsUp => "+1",
+            Reaction::ThumbsDown => "-1",
+            Reaction::Confused => "confused",
+        }
+    }
+}
+
+/// Whether a new release is marked as the latest one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MakeLatest {
+    True,
+    False,
+    /// Left to the forge, i.e. GitHub picks the latest by creation date and version
+    Legacy,
+}
+
+impl MakeLatest {
+    /// Value of the `make_latest` parameter of GitHub
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MakeLatest::True => "true",
+            MakeLatest::False => "false",
+            MakeLatest::Legacy => "legacy",
+        }
+    }
+}
+
+/// A single repository on a code hosting service
+#[async_trait::async_trait]
+pub trait Forge: Send + Sync {
+    /// Content of the file at `path` on the default branch, `None` if it doesn't exist
+    async fn read_file(&self, path: &str) -> Result<Option<String>, ClientError>;
+
+    /// Content of the file at `path` on `branch`, `None` if it doesn't exist
+    async fn read_branch_file(
+        &self,
+        branch: &str,
+        path: &str,
+    ) -> Result<Option<String>, ClientError>;
+
+    /// Sha of the latest commit on `branch`, `None` if the branch doesn't exist
+    async fn branch_head(&self, branch: &str) -> Result<Option<String>, ClientError>;
+
+    /// Commits `files` on top of `base_sha` and points `branch` to the new commit, creating the
+    /// branch if needed. Returns the sha of the commit. Forges with a single way of committing
+    /// ignore `method`.
+    ///
+    /// Gitea can't move an existing branch, so there the commit goes on top of the head of
+    /// `branch` instead: changes of the base branch made since `branch` was created aren't
+    /// picked up, and previous release commits stay in its history.
+    async fn commit_files(
+        &self,
+        branch: &str,
+        base_sha: &str,
+        message: &str,
+        files: Vec<File>,
+        method: CommitMethod,
+    ) -> Result<String, UpdateFilesError>;
+
+    /// Open pull request from `head` into `base`, if any
+    async fn find_open_pull(
+        &self,
+        head: &str,
+        base: &str,
+    ) -> Result<Option<PullRequest>, ClientError>;
+
+    async fn create_pull(
+        &self,
+        title: &str,
+        head: &str,
+        base: &str,
+        body: &str,
+    ) -> Result<PullRequest, ClientError>;
+
+    /// Closes a pull request without merging it
+    async fn close_pull(&self, number: u64) -> Result<(), ClientError>;
+
+    async fn delete_branch(&self, branch: &str) -> Result<(), ClientError>;
+
+    async fn get_issue(&self, number: u64) -> Result<Issue, ClientError>;
+
+    async fn update_issue_title(&self, number: u64, title: &str) -> Result<(), ClientError>;
+
+    async fn close_issue(&self, number: u64) -> Result<(), ClientError>;
+
+    /// Open issues created by `author` with `label`
+    async fn find_issues(&self, author: &str, label: &str) -> Result<Vec<Issue>, ClientError>;
+
+    async fn create_issue(
+        &self,
+        title: &str,
+        body: &str,
+        labels: &[&str],
+    ) -> Result<Issue, ClientError>;
+
+    /// Comments on an issue
+    async fn comment(&self, number: u64, body: &str) -> Result<(), ClientError>;
+
+    /// All comments on an issue, oldest first
+    async fn list_comments(&self, number: u64) -> Result<Vec<Comment>, ClientError>;
+
+    /// Replaces the body of the comment `comment_id` on issue `number`
+    async fn update_comment(
+        &self,
+        number: u64,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<(), ClientError>;
+
+    /// Comments on a pull request. Forges sharing the numbering between issues and pull requests
+    /// don't need to override it.
+    async fn comment_on_pull(&self, number: u64, body: &str) -> Result<(), ClientError> {
+        self.comment(number, body).await
+    }
+
+    /// Reacts to the comment `comment_id` on issue `number`
+    async fn react(
+        &self,
+        number: u64,
+        comment_id: u64,
+        reaction: Reaction,
+    ) -> Result<(), ClientError>;
+
+    /// Reacts to a comment on a pull request, same as [`Forge::comment_on_pull`]
+    async fn react_on_pull(
+        &self,
+        number: u64,
+        comment_id: u64,
+        reaction: Reaction,
+    ) -> Result<(), ClientError> {
+        self.react(number, comment_id, reaction).await
+    }
+
+    async fn add_label(&self, number: u64, label: &str) -> Result<(), ClientError>;
+
+    async fn remove_label(&self, number: u64, label: &str) -> Result<(), ClientError>;
+
+    /// Creates a lightweight tag `name` pointing to the commit `sha`
+    async fn create_tag(&self, name: &str, sha: &str) -> Result<(), ClientError>;
+
+    /// Creates a release named after `tag`
+    async fn create_release(
+        &self,
+        tag: &str,
+        body: &str,
+        prerelease: bool,
+        make_latest: MakeLatest,
+    ) -> Result<Release, ClientError>;
+
+    /// All existing releases, newest first. Forges ignoring
+    /// `make_latest` don't need to override it.
+    async fn list_releases(&self) -> Result<Vec<Release>, ClientError> {
+        Ok(Vec::new())
+    }
+}
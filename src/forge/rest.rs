@@ -0,0 +1,211 @@
+//! Minimal JSON client shared by the forges talking to a plain REST API.
+
+use crate::client::{is_idempotent, ClientError, RetryPolicy};
+use http::Method;
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{error, warn};
+
+/// JSON REST client for forges authenticated with a token header, i.e. GitLab and Gitea
+#[derive(Clone)]
+pub struct RestClient {
+    http: reqwest::Client,
+    /// i.e. `https://gitlab.example.com/api/v4`
+    api_url: String,
+    auth_header: &'static str,
+    auth_value: String,
+    /// Query parameter setting the number of items per page
+    page_size_param: &'static str,
+    policy: RetryPolicy,
+}
+
+impl RestClient {
+    /// `base_url` is the URL of the GitLab instance, i.e. `https://gitlab.com`
+    pub fn gitlab(base_url: &str, token: impl Into<String>, policy: RetryPolicy) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_url: format!("{}/api/v4", base_url.trim_end_matches('/')),
+            auth_header: "PRIVATE-TOKEN",
+            auth_value: token.into(),
+            page_size_param: "per_page",
+            policy,
+        }
+    }
+
+    /// `base_url` is the URL of the Gitea or Forgejo instance, i.e. `https://codeberg.org`
+    pub fn gitea(base_url: &str, token: impl AsRef<str>, policy: RetryPolicy) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_url: format!("{}/api/v1", base_url.trim_end_matches('/')),
+            auth_header: "Authorization",
+            auth_value: format!("token {}", token.as_ref()),
+            page_size_param: "limit",
+            policy,
+        }
+    }
+
+    /// Sends the request, retrying on transient failures as described by the [`RetryPolicy`].
+    /// Returns the raw body of the successful response.
+    pub async fn send<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        route: &str,
+        body: Option<&B>,
+    ) -> Result<String, ClientError> {
+        let mut attempt = 0;
+
+        loop {
+            let mut request = self
+                .http
+                .request(method.clone(), format!("{}{}", self.api_url, route))
+                .header(self.auth_header, &self.auth_value);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(err) => {
+                    let retryable = err.is_connect() || is_idempotent(&method);
+                    if retryable && attempt < self.policy.max_retries {
+                        let delay = self.policy.backoff(attempt);
+                        warn!(
+                            "{} {} failed, retrying in {:?}. Error: {}",
+                            method, route, delay, err
+                        );
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                        continue;
+                    }
+
+                    error!("{} {} failed. Error: {}", method, route, err);
+                    return Err(ClientError::Transport {
+                        msg: err.to_string(),
+                    });
+                }
+            };
+
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response
+                .text()
+                .await
+                .map_err(|err| ClientError::Transport {
+                    msg: err.to_string(),
+                })?;
+
+            if status.is_success() {
+                return Ok(body);
+            }
+
+            // Errors are reported as `message` (sometimes an object of field errors) or `error`
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| match (&body["message"], &body["error"]) {
+                    (serde_json::Value::String(message), _) => Some(message.clone()),
+                    (serde_json::Value::Null, serde_json::Value::String(error)) => {
+                        Some(error.clone())
+                    }
+                    (serde_json::Value::Null, _) => None,
+                    (message, _) => Some(message.to_string()),
+                })
+                .unwrap_or(body);
+
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|now| now.as_secs())
+                .unwrap_or_default();
+
+            match self
+                .policy
+                .retry_delay(attempt, &method, status, &headers, &message, now)
+            {
+                Some(delay) => {
+                    warn!(
+                        "{} {} responded with {}, retrying in {:?}. Message: {}",
+                        method, route, status, delay, message
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => {
+                    return Err(ClientError::Api {
+                        status: status.as_u16(),
+                        message,
+                    })
+                }
+            }
+        }
+    }
+
+    async fn send_json<B: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        route: &str,
+        body: Option<&B>,
+    ) -> Result<R, ClientError> {
+        let response = self.send(method, route, body).await?;
+
+        serde_json::from_str(&response).map_err(|err| ClientError::Decode {
+            msg: err.to_string(),
+        })
+    }
+
+    pub async fn get<R: DeserializeOwned>(&self, route: &str) -> Result<R, ClientError> {
+        self.send_json(Method::GET, route, None::<&()>).await
+    }
+
+    /// Lists every page of `route`, requesting `per_page` items at a time
+    pub async fn get_pages<R: DeserializeOwned>(
+        &self,
+        route: &str,
+        per_page: usize,
+    ) -> Result<Vec<R>, ClientError> {
+        let separator = if route.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        for page in 1.. {
+            let batch: Vec<R> = self
+                .get(&format!(
+                    "{}{}{}={}&page={}",
+                    route, separator, self.page_size_param, per_page, page
+                ))
+                .await?;
+            let last_page = batch.len() < per_page;
+            items.extend(batch);
+            if last_page {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    pub async fn post<B: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        route: &str,
+        body: &B,
+    ) -> Result<R, ClientError> {
+        self.send_json(Method::POST, route, Some(body)).await
+    }
+
+    pub async fn put<B: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        route: &str,
+        body: &B,
+    ) -> Result<R, ClientError> {
+        self.send_json(Method::PUT, route, Some(body)).await
+    }
+
+    pub async fn patch<B: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        route: &str,
+        body: &B,
+    ) -> Result<R, ClientError> {
+        self.send_json(Method::PATCH, route, Some(body)).await
+    }
+
+    /// Responses of `DELETE` are usually empty, so the body is discarded
+    pub async fn delete(&self, route: &str) -> Result<(), ClientError> {
+        self.send(Method::DELETE, route, None::<&()>).await?;
+        Ok(())
+    }
+}
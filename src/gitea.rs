@@ -0,0 +1,252 @@
+//! Webhook endpoint for repositories hosted on Gitea or Forgejo. Issue and pull request events go
+//! through the same release flow as GitHub, using [`GiteaForge`].
+
+use crate::{
+    client::RetryPolicy,
+    common::verify_hmac_sha256_hex,
+    events::{
+        issues::{prepare_release, ReleaseIssue},
+        pulls::{notify_closed_without_merge, publish_release, ReleasePull},
+    },
+    forge::{rest::RestClient, GiteaForge},
+    webhook::{WebhookError, WEBHOOK_SIZE_LIMIT},
+    State,
+};
+use actix_web::{
+    post,
+    web::{self, Bytes},
+    HttpRequest, HttpResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::{error, info};
+
+#[derive(Clone)]
+pub struct GiteaState {
+    pub client: RestClient,
+    /// Secret of the webhook, used to sign the payload in `X-Gitea-Signature`
+    pub webhook_secret: String,
+    /// Username of the user owning the access token, used to find the issues opened by us
+    pub username: String,
+}
+
+impl GiteaState {
+    /// `base_url` is the URL of the Gitea or Forgejo instance, i.e. `https://codeberg.org`
+    pub fn new(base_url: &str, token: String, webhook_secret: String, username: String) -> Self {
+        Self {
+            client: RestClient::gitea(base_url, token, RetryPolicy::default()),
+            webhook_secret,
+            username,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Owner {
+    login: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Repository {
+    id: u64,
+    name: String,
+    owner: Owner,
+    default_branch: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Label {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Issue {
+    number: u64,
+    title: String,
+    body: Option<String>,
+    user: Owner,
+    #[serde(default)]
+    labels: Vec<Label>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug)]
+struct IssueEvent {
+    action: String,
+    issue: Issue,
+    repository: Repository,
+}
+
+#[derive(Deserialize, Debug)]
+struct PullBranch {
+    #[serde(rename = "ref")]
+    ref_: String,
+    repo_id: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Pull {
+    number: u64,
+    title: String,
+    body: Option<String>,
+    merged: bool,
+    merge_commit_sha: Option<String>,
+    head: PullBranch,
+    base: PullBranch,
+}
+
+#[derive(Deserialize, Debug)]
+struct PullEvent {
+    action: String,
+    pull_request: Pull,
+    repository: Repository,
+    sender: Option<Owner>,
+}
+
+#[post("/gitea/webhook/")]
+pub async fn parse_gitea_event(
+    req: HttpRequest,
+    body: web::Payload,
+    state: web::Data<State>,
+) -> Result<HttpResponse, WebhookError> {
+    let Some(gitea) = &state.gitea else {
+        info!("Got a Gitea webhook, but Gitea isn't configured");
+        return Err(WebhookError::UnsupportedEvent);
+    };
+
+    let headers = req.headers();
+    let signature = headers
+        .get("X-Gitea-Signature")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    let gitea_event = headers
+        .get("X-Gitea-Event")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+
+    if signature.is_empty() || gitea_event.is_empty() {
+        error!("Either the header `X-Gitea-Signature` or `X-Gitea-Event` was empty or one of them failed to parse");
+        return Err(WebhookError::RequiredHeadersNotAvailable);
+    }
+
+    let Ok(body) = body.to_bytes_limited(WEBHOOK_SIZE_LIMIT).await else {
+        error!("Body size is greater than 25MB.");
+        return Err(WebhookError::LargeBodySize);
+    };
+    let body = body.unwrap_or_else(|err| {
+        error!("Failed to convert body payload to bytes. Error: {:?}", err);
+        Bytes::new()
+    });
+
+    if !verify_hmac_sha256_hex(&body, gitea.webhook_secret.as_bytes(), signature) {
+        error!("Invalid Signature. This is not a valid webhook event send by Gitea.");
+        return Err(WebhookError::InvalidGiteaSignature);
+    }
+
+    match gitea_event {
+        "issues" => {
+            let event: IssueEvent = parse_payload(&body)?;
+            handle_issue_event(gitea, event).await
+        }
+        "pull_request" => {
+            let event: PullEvent = parse_payload(&body)?;
+            handle_pull_event(gitea, event).await
+        }
+        _ => {
+            info!("Got an unsupported Gitea event: {}", gitea_event);
+            Err(WebhookError::UnsupportedEvent)
+        }
+    }
+}
+
+fn parse_payload<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, WebhookError> {
+    serde_json::from_slice(body).map_err(|err| {
+        error!("Failed to serialize Gitea webhook payload. Error: {}", err);
+        WebhookError::SerializationFailed
+    })
+}
+
+fn forge(gitea: &GiteaState, repository: Repository) -> GiteaForge {
+    GiteaForge::new(
+        gitea.client.clone(),
+        repository.owner.login,
+        repository.name,
+        repository.default_branch,
+    )
+}
+
+async fn handle_issue_event(
+    gitea: &GiteaState,
+    event: IssueEvent,
+) -> Result<HttpResponse, WebhookError> {
+    let issue = event.issue;
+
+    // Label changes are sent as `label_updated`, in the `issues` event
+    let relevant = matches!(
+        event.action.as_str(),
+        "opened" | "reopened" | "edited" | "label_updated"
+    );
+    if !relevant
+        || !issue
+            .labels
+            .iter()
+            .any(|label| label.name == crate::RELEASE_ISSUE_LABEL)
+    {
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let forge = forge(gitea, event.repository);
+    let release_issue = ReleaseIssue {
+        number: issue.number,
+        title: issue.title,
+        body: issue.body.unwrap_or_default(),
+        author: issue.user.login,
+        updated_at: issue.updated_at,
+    };
+
+    Ok(prepare_release(&forge, &gitea.username, &release_issue).await)
+}
+
+async fn handle_pull_event(
+    gitea: &GiteaState,
+    event: PullEvent,
+) -> Result<HttpResponse, WebhookError> {
+    let pull = event.pull_request;
+
+    // Pull requests from forks can't be release pull requests
+    if event.action != "closed"
+        || !pull.head.ref_.starts_with("release-butler/")
+        || pull.head.repo_id != pull.base.repo_id
+        || pull.head.repo_id != event.repository.id
+    {
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let forge = forge(gitea, event.repository);
+
+    if !pull.merged {
+        // Skipped for any close by the app account, which only closes release pulls on purpose
+        if event
+            .sender
+            .is_none_or(|sender| sender.login != gitea.username)
+        {
+            notify_closed_without_merge(&forge, pull.number).await;
+        }
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let Some(merge_commit_sha) = pull.merge_commit_sha else {
+        return Err(WebhookError::MalformatedBody {
+            msg: String::from("The payload must contain `merge_commit_sha`"),
+        });
+    };
+
+    let release_pull = ReleasePull {
+        number: pull.number,
+        title: pull.title,
+        body: pull.body.unwrap_or_default(),
+        merge_commit_sha,
+    };
+
+    Ok(publish_release(&forge, &gitea.username, &release_pull).await)
+}
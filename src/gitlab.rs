@@ -0,0 +1,317 @@
+//! Webhook endpoint for projects hosted on GitLab. Issue and merge request events go through the
+//! same release flow as GitHub, using [`GitLabForge`].
+
+use crate::{
+    client::RetryPolicy,
+    events::{
+        issues::{prepare_release, ReleaseIssue},
+        pulls::{notify_closed_without_merge, publish_release, ReleasePull},
+    },
+    forge::{rest::RestClient, GitLabForge},
+    webhook::{WebhookError, WEBHOOK_SIZE_LIMIT},
+    State,
+};
+use actix_web::{
+    post,
+    web::{self, Bytes},
+    HttpRequest, HttpResponse,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserialize;
+use tracing::{error, info};
+
+#[derive(Clone)]
+pub struct GitLabState {
+    pub gl: RestClient,
+    /// Secret token configured on the webhook, sent back in `X-Gitlab-Token`
+    pub webhook_token: String,
+    /// Username of the user owning the access token, used to find the issues opened by us
+    pub username: String,
+}
+
+impl GitLabState {
+    /// `base_url` is the URL of the GitLab instance, i.e. `https://gitlab.com`
+    pub fn new(base_url: &str, token: String, webhook_token: String, username: String) -> Self {
+        Self {
+            gl: RestClient::gitlab(base_url, token, RetryPolicy::default()),
+            webhook_token,
+            username,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Project {
+    path_with_namespace: String,
+    default_branch: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct User {
+    username: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Label {
+    title: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct IssueAttributes {
+    iid: u64,
+    title: String,
+    description: Option<String>,
+    action: Option<String>,
+    updated_at: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct IssueEvent {
+    project: Project,
+    object_attributes: IssueAttributes,
+    #[serde(default)]
+    labels: Vec<Label>,
+    #[serde(default)]
+    changes: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LastCommit {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct MergeRequestAttributes {
+    iid: u64,
+    title: String,
+    description: Option<String>,
+    action: Option<String>,
+    source_branch: String,
+    source_project_id: u64,
+    target_project_id: u64,
+    merge_commit_sha: Option<String>,
+    squash_commit_sha: Option<String>,
+    last_commit: Option<LastCommit>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MergeRequestEvent {
+    user: Option<User>,
+    project: Project,
+    object_attributes: MergeRequestAttributes,
+}
+
+/// Compares without short-circuiting, so the token can't be guessed from response times
+fn tokens_match(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .bytes()
+            .zip(actual.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// GitLab sends `2025-03-12 10:00:00 UTC` in webhooks and RFC 3339 in the API
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S UTC")
+                .map(|timestamp| timestamp.and_utc())
+                .ok()
+        })
+}
+
+#[post("/gitlab/webhook/")]
+pub async fn parse_gitlab_event(
+    req: HttpRequest,
+    body: web::Payload,
+    state: web::Data<State>,
+) -> Result<HttpResponse, WebhookError> {
+    let Some(gitlab) = &state.gitlab else {
+        info!("Got a GitLab webhook, but GitLab isn't configured");
+        return Err(WebhookError::UnsupportedEvent);
+    };
+
+    let headers = req.headers();
+    let token = headers
+        .get("X-Gitlab-Token")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    let gitlab_event = headers
+        .get("X-Gitlab-Event")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+
+    if token.is_empty() || gitlab_event.is_empty() {
+        error!("Either the header `X-Gitlab-Token` or `X-Gitlab-Event` was empty or one of them failed to parse");
+        return Err(WebhookError::RequiredHeadersNotAvailable);
+    }
+
+    if !tokens_match(&gitlab.webhook_token, token) {
+        error!("Invalid Token. This is not a valid webhook event send by GitLab.");
+        return Err(WebhookError::InvalidToken);
+    }
+
+    let Ok(body) = body.to_bytes_limited(WEBHOOK_SIZE_LIMIT).await else {
+        error!("Body size is greater than 25MB.");
+        return Err(WebhookError::LargeBodySize);
+    };
+    let body = body.unwrap_or_else(|err| {
+        error!("Failed to convert body payload to bytes. Error: {:?}", err);
+        Bytes::new()
+    });
+
+    match gitlab_event {
+        "Issue Hook" => {
+            let event: IssueEvent = parse_payload(&body)?;
+            handle_issue_event(gitlab, event).await
+        }
+        "Merge Request Hook" => {
+            let event: MergeRequestEvent = parse_payload(&body)?;
+            handle_merge_request_event(gitlab, event).await
+        }
+        _ => {
+            info!("Got an unsupported GitLab event: {}", gitlab_event);
+            Err(WebhookError::UnsupportedEvent)
+        }
+    }
+}
+
+fn parse_payload<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, WebhookError> {
+    serde_json::from_slice(body).map_err(|err| {
+        error!("Failed to serialize GitLab webhook payload. Error: {}", err);
+        WebhookError::SerializationFailed
+    })
+}
+
+async fn handle_issue_event(
+    gitlab: &GitLabState,
+    event: IssueEvent,
+) -> Result<HttpResponse, WebhookError> {
+    let issue = event.object_attributes;
+
+    // Same as `labeled` and `edited` actions on GitHub
+    let relevant = match issue.action.as_deref() {
+        Some("open") | Some("reopen") => true,
+        Some("update") => ["labels", "title", "description"]
+            .iter()
+            .any(|field| event.changes.contains_key(*field)),
+        _ => false,
+    };
+    if !relevant
+        || !event
+            .labels
+            .iter()
+            .any(|label| label.title == crate::RELEASE_ISSUE_LABEL)
+    {
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let forge = GitLabForge::new(
+        gitlab.gl.clone(),
+        event.project.path_with_namespace,
+        event.project.default_branch,
+    );
+
+    let author = match forge.issue_author(issue.iid).await {
+        Ok(author) => author,
+        Err(err) => {
+            error!(
+                "Failed to get the author of issue #{}. Error: {}",
+                issue.iid, err
+            );
+            return Ok(HttpResponse::InternalServerError().finish());
+        }
+    };
+
+    let release_issue = ReleaseIssue {
+        number: issue.iid,
+        title: issue.title,
+        body: issue.description.unwrap_or_default(),
+        author,
+        updated_at: parse_timestamp(&issue.updated_at).unwrap_or_else(Utc::now),
+    };
+
+    Ok(prepare_release(&forge, &gitlab.username, &release_issue).await)
+}
+
+async fn handle_merge_request_event(
+    gitlab: &GitLabState,
+    event: MergeRequestEvent,
+) -> Result<HttpResponse, WebhookError> {
+    let merge_request = event.object_attributes;
+
+    // Merge requests from forks can't be release merge requests
+    if !merge_request.source_branch.starts_with("release-butler/")
+        || merge_request.source_project_id != merge_request.target_project_id
+    {
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let forge = GitLabForge::new(
+        gitlab.gl.clone(),
+        event.project.path_with_namespace,
+        event.project.default_branch,
+    );
+
+    match merge_request.action.as_deref() {
+        Some("merge") => {
+            // Squashed fast-forward merges only set `squash_commit_sha`, and other fast-forward
+            // merges don't create a commit
+            let Some(merge_commit_sha) = merge_request
+                .merge_commit_sha
+                .or(merge_request.squash_commit_sha)
+                .or(merge_request.last_commit.map(|commit| commit.id))
+            else {
+                return Err(WebhookError::MalformatedBody {
+                    msg: String::from("The payload must contain `merge_commit_sha`"),
+                });
+            };
+
+            let release_pull = ReleasePull {
+                number: merge_request.iid,
+                title: merge_request.title,
+                body: merge_request.description.unwrap_or_default(),
+                merge_commit_sha,
+            };
+
+            Ok(publish_release(&forge, &gitlab.username, &release_pull).await)
+        }
+        Some("close") => {
+            // Skipped for any close by the app account, which only closes release merge
+            // requests on purpose
+            if event
+                .user
+                .is_none_or(|user| user.username != gitlab.username)
+            {
+                notify_closed_without_merge(&forge, merge_request.iid).await;
+            }
+            Ok(HttpResponse::Ok().finish())
+        }
+        _ => Ok(HttpResponse::Ok().finish()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "secreT"));
+        assert!(!tokens_match("secret", "secret2"));
+        assert!(!tokens_match("secret", ""));
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        let expected: DateTime<Utc> = "2025-03-12T10:00:00Z".parse().unwrap();
+        assert_eq!(parse_timestamp("2025-03-12 10:00:00 UTC"), Some(expected));
+        assert_eq!(parse_timestamp("2025-03-12T10:00:00Z"), Some(expected));
+        assert_eq!(parse_timestamp("2025-03-12T11:00:00+01:00"), Some(expected));
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+}
@@ -0,0 +1,166 @@
+//! The GitHub issue form requesting a release, an alternative to typing the version in the
+//! issue title.
+
+use crate::config::Config;
+
+/// Where GitHub looks for the issue form
+pub static ISSUE_FORM_PATH: &str = ".github/ISSUE_TEMPLATE/release.yml";
+
+/// Shown by GitHub for optional fields left empty
+static NO_RESPONSE: &str = "_No response_";
+
+/// A release issue opened with the issue form, as rendered by GitHub
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueForm {
+    /// Empty if the form has no package dropdown, i.e. for a single package
+    pub package: String,
+    pub version: String,
+    pub changelog: String,
+}
+
+impl IssueForm {
+    /// Parses the body of an issue opened with the form rendered by [`render`], `None` if it
+    /// wasn't
+    pub fn parse(body: &str) -> Option<Self> {
+        let body = body.replace("\r\n", "\n");
+        let mut rest = body.trim_start();
+
+        let package = section(&mut rest, "### Package").unwrap_or_default();
+        let version = section(&mut rest, "### Version")?;
+        // Last section, the changelog can contain `###` headings of its own
+        let changelog = rest
+            .strip_prefix("### Changelog")
+            .map(|changelog| response(changelog.trim()))
+            .unwrap_or_default();
+
+        Some(Self {
+            package: response(package).to_owned(),
+            version: response(version).to_owned(),
+            changelog: changelog.to_owned(),
+        })
+    }
+}
+
+/// Value of the section starting `rest` with `heading`, moving `rest` to the next section
+fn section<'a>(rest: &mut &'a str, heading: &str) -> Option<&'a str> {
+    let value = rest.strip_prefix(heading)?.strip_prefix('\n')?;
+    let (value, next) = match value.find("\n### ") {
+        Some(index) => (&value[..index], &value[index + 1..]),
+        None => (value, ""),
+    };
+    *rest = next;
+    Some(value.trim())
+}
+
+fn response(value: &str) -> &str {
+    if value == NO_RESPONSE {
+        ""
+    } else {
+        value
+    }
+}
+
+static PACKAGE_FIELD: &str = r#"  - type: dropdown
+    id: package
+    attributes:
+      label: Package
+      options:
+"#;
+
+static VERSION_AND_CHANGELOG_FIELDS: &str = r####"  - type: input
+    id: version
+    attributes:
+      label: Version
+      description: Version to release, i.e. `1.2.3` or `1.3.0-rc.1`
+      placeholder: 1.2.3
+    validations:
+      required: true
+  - type: textarea
+    id: changelog
+    attributes:
+      label: Changelog
+      description: Added to the changelog and used as the release notes
+      placeholder: "### Fixed\n- Bug Y"
+"####;
+
+/// The issue form, with a package dropdown if the configuration has more than one package
+pub fn render(config: &Config) -> String {
+    let mut form = format!(
+        "# Generated by `release-butler issue-form` from `{}`, run it again after adding a package\n\
+        name: Release\n\
+        description: Request a release, opening a pull request bumping the version\n\
+        title: Release\n\
+        labels: [\"{}\"]\n\
+        body:\n",
+        crate::DEFAULT_CONFIG_FILE_PATH,
+        crate::RELEASE_ISSUE_LABEL
+    );
+
+    if config.packages.len() > 1 {
+        form.push_str(PACKAGE_FIELD);
+        for name in config.packages.keys() {
+            form.push_str(&format!("        - {}\n", quote(name)));
+        }
+        form.push_str("    validations:\n      required: true\n");
+    }
+    form.push_str(VERSION_AND_CHANGELOG_FIELDS);
+
+    form
+}
+
+/// Double-quoted YAML string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(packages: &str) -> Config {
+        toml::from_str(&format!(
+            "version = 1\ndefault_branch = \"main\"\n[issues_meta_data]\nallowed_authors = []\n{}",
+            packages
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let form = render(&config(
+            "[packages.foo]\npackage_manager = \"cargo\"\n[packages.bar]\npackage_manager = \"cargo\"\n",
+        ));
+        assert!(form.contains("labels: [\"release-butler\"]\n"));
+        assert!(form.contains(
+            "      label: Package\n      options:\n        - \"bar\"\n        - \"foo\"\n"
+        ));
+        assert!(form.contains("      label: Version\n"));
+        assert!(form.contains("      label: Changelog\n"));
+
+        let form = render(&config("[packages.foo]\npackage_manager = \"cargo\"\n"));
+        assert!(!form.contains("label: Package"));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            IssueForm::parse(
+                "### Package\r\n\r\nfoo\r\n\r\n### Version\r\n\r\nv1.2.3\r\n\r\n### Changelog\r\n\r\n### Fixed\r\n- Bug Y"
+            ),
+            Some(IssueForm {
+                package: String::from("foo"),
+                version: String::from("v1.2.3"),
+                changelog: String::from("### Fixed\n- Bug Y"),
+            })
+        );
+        assert_eq!(
+            IssueForm::parse("### Version\n\n1.2.3\n\n### Changelog\n\n_No response_"),
+            Some(IssueForm {
+                package: String::new(),
+                version: String::from("1.2.3"),
+                changelog: String::new(),
+            })
+        );
+        assert_eq!(IssueForm::parse("### Fixed\n- Bug Y"), None);
+    }
+}
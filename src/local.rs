@@ -0,0 +1,553 @@
+//! The release flow run against a local git checkout instead of a forge, used by the CLI.
+
+use crate::{
+    config::{Config, Package, CONFIG_VERSION},
+    events::issues::parse_issue_title,
+    issue_form::{self, ISSUE_FORM_PATH},
+    release::{self, FileEdit},
+    validate::{self, Diagnostic},
+    DEFAULT_CONFIG_FILE_PATH,
+};
+use chrono::NaiveDate;
+use derive_more::{Display, Error};
+use git2::{ErrorCode, Repository, Status};
+use semver::Version;
+use std::{fs, path::Path};
+
+#[derive(Debug, Display, Error)]
+pub enum LocalError {
+    #[display("Failed to open the git repository. {source}")]
+    Open { source: git2::Error },
+    #[display("The repository has no working tree")]
+    Bare,
+    #[display("Failed to read `{path}`. {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[display("Failed to write `{path}`. {source}")]
+    Write {
+        path: String,
+        source: std::io::Error,
+    },
+    #[display("`{path}` is malformatted. {msg}")]
+    MalformedConfig { path: String, msg: String },
+    #[display("`{path}` has version {version}, but only version {CONFIG_VERSION} is supported")]
+    UnsupportedConfigVersion { path: String, version: u8 },
+    #[display("`{title}` doesn't follow the `[<PACKAGE_NAME>@]v<VERSION>` format")]
+    InvalidTitle { title: String },
+    #[display("`{DEFAULT_CONFIG_FILE_PATH}` contains multiple packages, prefix the version with `<PACKAGE_NAME>@`")]
+    PackageRequired,
+    #[display("The package `{name}` was not found in `{DEFAULT_CONFIG_FILE_PATH}`")]
+    UnknownPackage { name: String },
+    #[display("`{path}` doesn't exist")]
+    MissingManifest { path: String },
+    #[display("Failed to parse `{path}`. {source}")]
+    Manifest {
+        path: String,
+        source: toml_edit::TomlError,
+    },
+    #[display("`{path}` has uncommitted changes, commit or stash them first")]
+    Dirty { path: String },
+    #[display("`{path}` has staged changes, commit or unstage them first")]
+    Staged { path: String },
+    #[display("The branch `{branch}` already exists")]
+    BranchExists { branch: String },
+    #[display("The tag `{tag}` already exists")]
+    TagExists { tag: String },
+    #[display("Found {count} problem(s)")]
+    Invalid { count: usize },
+    #[display("{source}")]
+    Git { source: git2::Error },
+}
+
+impl From<git2::Error> for LocalError {
+    fn from(source: git2::Error) -> Self {
+        LocalError::Git { source }
+    }
+}
+
+/// Changes of a release, see [`LocalRepository::plan`]
+#[derive(Debug, Clone)]
+pub struct ReleasePlan {
+    pub version: Version,
+    /// Branch the changes are committed to
+    pub branch: String,
+    /// Tag to create once the release branch is merged
+    pub tag: String,
+    pub edits: Vec<FileEdit>,
+    /// Whether `dry_run` is enabled in the configuration
+    pub dry_run: bool,
+}
+
+/// Outcome of [`LocalRepository::prepare`]
+#[derive(Debug, Clone)]
+pub struct PreparedRelease {
+    pub branch: String,
+    pub commit: String,
+}
+
+/// A git checkout with `.github/release-butler.toml` in its working tree
+pub struct LocalRepository {
+    repo: Repository,
+}
+
+impl LocalRepository {
+    /// Opens the repository containing `path`
+    pub fn discover(path: impl AsRef<Path>) -> Result<Self, LocalError> {
+        let repo = Repository::discover(path).map_err(|source| LocalError::Open { source })?;
+        if repo.is_bare() {
+            return Err(LocalError::Bare);
+        }
+        Ok(Self { repo })
+    }
+
+    fn workdir(&self) -> &Path {
+        // Checked in `discover`
+        self.repo.workdir().expect("Repository has a working tree")
+    }
+
+    /// Content of the file at `path` (relative to repository root) in the working tree
+    pub fn read_file(&self, path: &str) -> Result<Option<String>, LocalError> {
+        match fs::read_to_string(self.workdir().join(path)) {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(LocalError::Read {
+                path: path.to_owned(),
+                source,
+            }),
+        }
+    }
+
+    pub fn config(&self) -> Result<Config, LocalError> {
+        let content = self.read_file(DEFAULT_CONFIG_FILE_PATH)?.ok_or_else(|| {
+            LocalError::MalformedConfig {
+                path: DEFAULT_CONFIG_FILE_PATH.to_owned(),
+                msg: String::from("The file doesn't exist."),
+            }
+        })?;
+
+        let config =
+            toml::from_str::<Config>(&content).map_err(|err| LocalError::MalformedConfig {
+                path: DEFAULT_CONFIG_FILE_PATH.to_owned(),
+                msg: err.message().to_owned(),
+            })?;
+
+        if config.version != CONFIG_VERSION {
+            return Err(LocalError::UnsupportedConfigVersion {
+                path: DEFAULT_CONFIG_FILE_PATH.to_owned(),
+                version: config.version,
+            });
+        }
+
+        Ok(config)
+    }
+
+    /// Checks the configuration, manifests and changelogs in the working tree, see
+    /// [`validate::validate`]
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        validate::validate(|path| self.read_file(path).map_err(|err| err.to_string()))
+    }
+
+    /// Computes the version bump and changelog edits of the package requested by `title` (same
+    /// format as the release issue title), without touching the working tree
+    pub fn plan(
+        &self,
+        title: &str,
+        notes: &str,
+        date: NaiveDate,
+    ) -> Result<ReleasePlan, LocalError> {
+        let (package, version) = parse_title(title)?;
+        let config = self.config()?;
+        let package_information = find_package(&config, package)?;
+
+        let manifest_path = release::manifest_path(package_information);
+        let manifest_content =
+            self.read_file(&manifest_path)?
+                .ok_or_else(|| LocalError::MissingManifest {
+                    path: manifest_path.clone(),
+                })?;
+        let new_manifest_content =
+            release::bump_manifest(package_information, &manifest_content, &version).map_err(
+                |source| LocalError::Manifest {
+                    path: manifest_path.clone(),
+                    source,
+                },
+            )?;
+
+        let mut edits = vec![FileEdit {
+            path: manifest_path,
+            old: Some(manifest_content),
+            new: new_manifest_content,
+        }];
+        if let Some(changelog_path) = release::changelog_path(package_information, &version) {
+            let changelog_content = self.read_file(changelog_path)?;
+            edits.push(FileEdit {
+                path: changelog_path.to_owned(),
+                new: release::update_changelog(changelog_content.as_deref(), &version, date, notes),
+                old: changelog_content,
+            });
+        }
+
+        Ok(ReleasePlan {
+            branch: release::release_branch(package, &version),
+            tag: release::tag_name(package, &version),
+            version,
+            edits,
+            dry_run: config.dry_run,
+        })
+    }
+
+    /// Applies `plan` to the working tree, commits the changes on top of `HEAD` on the release
+    /// branch and checks it out
+    pub fn prepare(&self, plan: &ReleasePlan) -> Result<PreparedRelease, LocalError> {
+        match self.repo.find_branch(&plan.branch, git2::BranchType::Local) {
+            Ok(_) => {
+                return Err(LocalError::BranchExists {
+                    branch: plan.branch.clone(),
+                })
+            }
+            Err(err) if err.code() == ErrorCode::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        // Local changes would end up in the release commit
+        for edit in &plan.edits {
+            match self.repo.status_file(Path::new(&edit.path)) {
+                Ok(status) if status != Status::CURRENT => {
+                    return Err(LocalError::Dirty {
+                        path: edit.path.clone(),
+                    })
+                }
+                Ok(_) => {}
+                Err(err) if err.code() == ErrorCode::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        // The release commit is written from the index, so it must match `HEAD`
+        let staged = Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE;
+        if let Some(entry) = self
+            .repo
+            .statuses(None)?
+            .iter()
+            .find(|entry| entry.status().intersects(staged))
+        {
+            return Err(LocalError::Staged {
+                path: entry.path().unwrap_or_default().to_owned(),
+            });
+        }
+
+        for edit in &plan.edits {
+            let full_path = self.workdir().join(&edit.path);
+            if let Some(parent) = full_path.parent() {
+                fs::create_dir_all(parent).map_err(|source| LocalError::Write {
+                    path: edit.path.clone(),
+                    source,
+                })?;
+            }
+            fs::write(&full_path, &edit.new).map_err(|source| LocalError::Write {
+                path: edit.path.clone(),
+                source,
+            })?;
+        }
+
+        let mut index = self.repo.index()?;
+        for edit in &plan.edits {
+            index.add_path(Path::new(&edit.path))?;
+        }
+        index.write()?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+
+        let head = self.repo.head()?.peel_to_commit()?;
+        let signature = self.repo.signature()?;
+        let ref_name = format!("refs/heads/{}", plan.branch);
+        let commit = self.repo.commit(
+            Some(&ref_name),
+            &signature,
+            &signature,
+            &format!("chore: RELEASE {}", plan.version),
+            &tree,
+            &[&head],
+        )?;
+        // The working tree and index already match the new commit
+        self.repo.set_head(&ref_name)?;
+
+        Ok(PreparedRelease {
+            branch: plan.branch.clone(),
+            commit: commit.to_string(),
+        })
+    }
+
+    /// Writes the release issue form generated from the configuration to
+    /// [`ISSUE_FORM_PATH`], returning its content
+    pub fn write_issue_form(&self) -> Result<String, LocalError> {
+        let form = issue_form::render(&self.config()?);
+
+        let full_path = self.workdir().join(ISSUE_FORM_PATH);
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent).map_err(|source| LocalError::Write {
+                path: ISSUE_FORM_PATH.to_owned(),
+                source,
+            })?;
+        }
+        fs::write(&full_path, &form).map_err(|source| LocalError::Write {
+            path: ISSUE_FORM_PATH.to_owned(),
+            source,
+        })?;
+
+        Ok(form)
+    }
+
+    /// Creates the release tag of the package requested by `title` on `rev`, i.e. the merge commit
+    /// of the release branch. Returns the name of the tag.
+    pub fn tag(&self, title: &str, rev: &str) -> Result<String, LocalError> {
+        let (package, version) = parse_title(title)?;
+        let tag = release::tag_name(package, &version);
+
+        let commit = self.repo.revparse_single(rev)?.peel_to_commit()?;
+        match self.repo.tag_lightweight(&tag, commit.as_object(), false) {
+            Ok(_) => Ok(tag),
+            Err(err) if err.code() == ErrorCode::Exists => Err(LocalError::TagExists { tag }),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+fn parse_title(title: &str) -> Result<(&str, Version), LocalError> {
+    parse_issue_title(title).map_err(|_| LocalError::InvalidTitle {
+        title: title.to_owned(),
+    })
+}
+
+/// Same rules as the release issue: the package name can only be omitted if there is one package
+fn find_package<'a>(config: &'a Config, name: &str) -> Result<&'a Package, LocalError> {
+    if name.is_empty() {
+        if config.packages.len() > 1 {
+            return Err(LocalError::PackageRequired);
+        }
+        return config
+            .packages
+            .values()
+            .next()
+            .ok_or_else(|| LocalError::UnknownPackage {
+                name: String::from("default"),
+            });
+    }
+
+    config
+        .packages
+        .get(name)
+        .ok_or_else(|| LocalError::UnknownPackage {
+            name: name.to_owned(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CONFIG: &str = r#"
+        version = 1
+        default_branch = "main"
+
+        [issues_meta_data]
+        allowed_authors = ["jdoe"]
+
+        [packages.foo]
+        path = "crates/foo"
+        changelog_file = "crates/foo/CHANGELOG.md"
+        package_manager = "cargo"
+
+        [packages.bar]
+        path = "crates/bar"
+        package_manager = "cargo"
+    "#;
+
+    fn checkout() -> (tempfile::TempDir, LocalRepository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "John Doe").unwrap();
+        config.set_str("user.email", "jdoe@example.com").unwrap();
+
+        for (path, content) in [
+            (DEFAULT_CONFIG_FILE_PATH, CONFIG),
+            (
+                "crates/foo/Cargo.toml",
+                "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                "crates/bar/Cargo.toml",
+                "[package]\nname = \"bar\"\nversion = \"0.3.0\"\n",
+            ),
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = repo.signature().unwrap();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "Initial commit",
+            &tree,
+            &[],
+        )
+        .unwrap();
+
+        let local = LocalRepository::discover(dir.path()).unwrap();
+        (dir, local)
+    }
+
+    #[test]
+    fn test_prepare() {
+        let (dir, local) = checkout();
+        let date = NaiveDate::from_ymd_opt(2025, 3, 12).unwrap();
+
+        let plan = local.plan("foo@v0.2.0", "### Added\n- X", date).unwrap();
+        assert_eq!(plan.tag, "foo@v0.2.0");
+        assert!(!plan.dry_run);
+        let paths: Vec<_> = plan.edits.iter().map(|edit| edit.path.as_str()).collect();
+        assert_eq!(paths, ["crates/foo/Cargo.toml", "crates/foo/CHANGELOG.md"]);
+        assert_eq!(plan.edits[1].old, None);
+
+        let prepared = local.prepare(&plan).unwrap();
+        assert_eq!(prepared.branch, "release-butler/foo@0.2.0");
+
+        let head = local.repo.head().unwrap();
+        assert_eq!(head.shorthand(), Some("release-butler/foo@0.2.0"));
+        let commit = head.peel_to_commit().unwrap();
+        assert_eq!(commit.id().to_string(), prepared.commit);
+        assert_eq!(commit.message(), Some("chore: RELEASE 0.2.0"));
+        assert!(local
+            .repo
+            .statuses(None)
+            .unwrap()
+            .iter()
+            .all(|entry| entry.status() == Status::CURRENT));
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("crates/foo/Cargo.toml")).unwrap(),
+            "[package]\nname = \"foo\"\nversion = \"0.2.0\"\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("crates/foo/CHANGELOG.md")).unwrap(),
+            "# Changelog\n\n## [0.2.0] - 2025-03-12\n### Added\n- X"
+        );
+
+        assert!(matches!(
+            local.prepare(&plan),
+            Err(LocalError::BranchExists { .. })
+        ));
+    }
+
+    #[test]
+    fn test_prepare_errors() {
+        let (dir, local) = checkout();
+        let date = NaiveDate::from_ymd_opt(2025, 3, 12).unwrap();
+
+        assert!(matches!(
+            local.plan("v0.2.0", "", date),
+            Err(LocalError::PackageRequired)
+        ));
+        assert!(matches!(
+            local.plan("baz@v0.2.0", "", date),
+            Err(LocalError::UnknownPackage { .. })
+        ));
+        assert!(matches!(
+            local.plan("foo@0.2", "", date),
+            Err(LocalError::InvalidTitle { .. })
+        ));
+
+        fs::write(dir.path().join("crates/bar/Cargo.toml"), "[package]\n").unwrap();
+        let plan = local.plan("bar@v0.4.0", "", date).unwrap();
+        assert!(matches!(
+            local.prepare(&plan),
+            Err(LocalError::Dirty { .. })
+        ));
+        // Nothing was committed
+        assert!(local
+            .repo
+            .find_branch("release-butler/bar@0.4.0", git2::BranchType::Local)
+            .is_err());
+    }
+
+    #[test]
+    fn test_prepare_with_staged_changes() {
+        let (dir, local) = checkout();
+        let date = NaiveDate::from_ymd_opt(2025, 3, 12).unwrap();
+
+        fs::write(dir.path().join("NOTES.md"), "WIP\n").unwrap();
+        let mut index = local.repo.index().unwrap();
+        index.add_path(Path::new("NOTES.md")).unwrap();
+        index.write().unwrap();
+
+        let plan = local.plan("foo@v0.2.0", "", date).unwrap();
+        assert!(matches!(
+            local.prepare(&plan),
+            Err(LocalError::Staged { path }) if path == "NOTES.md"
+        ));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("crates/foo/Cargo.toml")).unwrap(),
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n"
+        );
+    }
+
+    #[test]
+    fn test_tag() {
+        let (_dir, local) = checkout();
+        let head = local.repo.head().unwrap().peel_to_commit().unwrap().id();
+
+        assert_eq!(local.tag("foo@0.2.0", "HEAD").unwrap(), "foo@v0.2.0");
+        let tag = local.repo.find_reference("refs/tags/foo@v0.2.0").unwrap();
+        assert_eq!(tag.target(), Some(head));
+
+        assert!(matches!(
+            local.tag("foo@0.2.0", "HEAD"),
+            Err(LocalError::TagExists { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let (dir, local) = checkout();
+        assert_eq!(local.validate(), []);
+
+        fs::write(
+            dir.path().join("crates/bar/Cargo.toml"),
+            "[package]\nname = \"bar\"\n",
+        )
+        .unwrap();
+        let diagnostics = local.validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "crates/bar/Cargo.toml: `package.version` is missing"
+        );
+    }
+
+    #[test]
+    fn test_write_issue_form() {
+        let (dir, local) = checkout();
+        let form = local.write_issue_form().unwrap();
+
+        assert!(form.contains("        - \"bar\"\n        - \"foo\"\n"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join(ISSUE_FORM_PATH)).unwrap(),
+            form
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use actix_web::web::{Data, ServiceConfig};
-use release_butler::{webhook, State};
+use release_butler::{gitea, gitlab, webhook, State};
 use shuttle_actix_web::ShuttleActixWeb;
 use shuttle_runtime::SecretStore;
 
@@ -23,14 +23,71 @@ async fn main(
         .get("APP-USERNAME")
         .expect("Please provide secret `APP-USERNAME` which contains username of the application");
 
+    let mut state = State::new(webhook_secret, app_username, app_id, private_key);
+
+    // While rotating `RELEASE-BUTLER-SECRET`, deliveries signed with the previous secret(s) are
+    // still accepted
+    if let Some(previous_secrets) = secrets.get("RELEASE-BUTLER-PREVIOUS-SECRETS") {
+        for secret in previous_secrets.split(',').map(str::trim) {
+            if !secret.is_empty() {
+                state = state.with_previous_webhook_secret(secret.to_owned());
+            }
+        }
+    }
+
+    if secrets
+        .get("ALLOW-SHA1-SIGNATURE")
+        .is_some_and(|value| value == "true")
+    {
+        state = state.with_legacy_sha1_signature();
+    }
+
+    // GitLab is optional, it is enabled when its URL is provided
+    if let Some(gitlab_url) = secrets.get("GITLAB-URL") {
+        let token = secrets.get("GITLAB-TOKEN").expect(
+            "Please provide secret `GITLAB-TOKEN` which contains an access token with `api` scope",
+        );
+        let webhook_token = secrets
+            .get("GITLAB-WEBHOOK-TOKEN")
+            .expect("Please provide secret `GITLAB-WEBHOOK-TOKEN` which contains the secret token of the GitLab webhook");
+        let username = secrets
+            .get("GITLAB-USERNAME")
+            .expect("Please provide secret `GITLAB-USERNAME` which contains username of the access token's user");
+
+        state = state.with_gitlab(gitlab::GitLabState::new(
+            &gitlab_url,
+            token,
+            webhook_token,
+            username,
+        ));
+    }
+
+    // Same for Gitea and Forgejo
+    if let Some(gitea_url) = secrets.get("GITEA-URL") {
+        let token = secrets.get("GITEA-TOKEN").expect(
+            "Please provide secret `GITEA-TOKEN` which contains an access token with `write:repository` and `write:issue` scopes",
+        );
+        let webhook_secret = secrets
+            .get("GITEA-WEBHOOK-SECRET")
+            .expect("Please provide secret `GITEA-WEBHOOK-SECRET` which contains the secret of the Gitea webhook");
+        let username = secrets
+            .get("GITEA-USERNAME")
+            .expect("Please provide secret `GITEA-USERNAME` which contains username of the access token's user");
+
+        state = state.with_gitea(gitea::GiteaState::new(
+            &gitea_url,
+            token,
+            webhook_secret,
+            username,
+        ));
+    }
+
+    let state = Data::new(state);
     let config = move |cfg: &mut ServiceConfig| {
         cfg.service(webhook::parse_event)
-            .app_data(Data::new(State::new(
-                webhook_secret,
-                app_username,
-                app_id,
-                private_key,
-            )));
+            .service(gitlab::parse_gitlab_event)
+            .service(gitea::parse_gitea_event)
+            .app_data(state);
     };
 
     Ok(config.into())
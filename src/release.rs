@@ -0,0 +1,551 @@
+//! Edits made to a repository to release a package, shared by the webhook flow and the CLI.
+
+use crate::{
+    common::repository_path,
+    config::{Config, Group, Package, PackageManager},
+    forge::MakeLatest,
+};
+use chrono::NaiveDate;
+use semver::Version;
+
+/// A file changed by the release
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEdit {
+    /// Path of the file, relative to repository root
+    pub path: String,
+    /// Content before the release, `None` if the file is created
+    pub old: Option<String>,
+    pub new: String,
+}
+
+impl FileEdit {
+    /// Unified diff of the change, with `a/` and `b/` prefixed paths like `git diff`
+    pub fn unified_diff(&self) -> String {
+        let old_header = match self.old {
+            Some(_) => format!("a/{}", self.path),
+            None => String::from("/dev/null"),
+        };
+
+        similar::TextDiff::from_lines(self.old.as_deref().unwrap_or_default(), &self.new)
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_header, &format!("b/{}", self.path))
+            .missing_newline_hint(false)
+            .to_string()
+    }
+}
+
+/// Path of the manifest holding the version of `package`, relative to repository root
+pub fn manifest_path(package: &Package) -> String {
+    match package.package_manager {
+        PackageManager::Cargo | PackageManager::CargoWorkspace => {
+            repository_path(&package.path, "Cargo.toml")
+        }
+    }
+}
+
+/// Path of the changelog to update for `version`, `None` if the package doesn't keep one
+pub fn changelog_path<'a>(package: &'a Package, version: &Version) -> Option<&'a str> {
+    let path = if version.pre.is_empty() {
+        &package.changelog_file
+    } else {
+        &package.pre_release_changelog_file
+    };
+
+    (!path.is_empty()).then_some(path.as_str())
+}
+
+/// Path of the changelog shared by the members of `group` to update for `version`
+pub fn group_changelog_path<'a>(group: &'a Group, version: &Version) -> Option<&'a str> {
+    let path = if version.pre.is_empty() {
+        &group.changelog_file
+    } else {
+        &group.pre_release_changelog_file
+    };
+
+    (!path.is_empty()).then_some(path.as_str())
+}
+
+/// Whether the members of `group` share a changelog instead of keeping their own
+pub fn has_shared_changelog(group: &Group) -> bool {
+    !group.changelog_file.is_empty() || !group.pre_release_changelog_file.is_empty()
+}
+
+/// The group named `name`, or the group `name` is a member of
+pub fn find_group<'a>(config: &'a Config, name: &str) -> Option<(&'a str, &'a Group)> {
+    if let Some((name, group)) = config.groups.get_key_value(name) {
+        return Some((name, group));
+    }
+    config
+        .groups
+        .iter()
+        .find(|(_, group)| group.packages.iter().any(|member| member == name))
+        .map(|(name, group)| (name.as_str(), group))
+}
+
+/// Replaces a group, or a member of a group, with every member of the group at the same version
+pub fn expand_groups(config: &Config, releases: &[(String, Version)]) -> Vec<(String, Version)> {
+    let mut expanded: Vec<(String, Version)> = Vec::new();
+    for (name, version) in releases {
+        let members = match find_group(config, name) {
+            Some((_, group)) => group.packages.clone(),
+            None => vec![name.clone()],
+        };
+        for member in members {
+            let release = (member, version.clone());
+            if !expanded.contains(&release) {
+                expanded.push(release);
+            }
+        }
+    }
+    expanded
+}
+
+/// Branch the release of `releases` is based on: `base` if the issue names one, otherwise the
+/// first of `branch_patterns` matching a released version, or the default branch
+pub fn base_branch<'a>(
+    config: &'a Config,
+    base: Option<&'a str>,
+    releases: &[(String, Version)],
+) -> &'a str {
+    if let Some(base) = base {
+        return base;
+    }
+    config
+        .branch_patterns
+        .iter()
+        .find(|pattern| releases.iter().any(|(_, version)| pattern.matches(version)))
+        .map_or(&config.default_branch, |pattern| &pattern.branch)
+}
+
+/// Name of the branch the release changes are committed to
+pub fn release_branch(package: &str, version: &Version) -> String {
+    format!("release-butler/{}@{}", package, version)
+}
+
+/// Branch of a release of one or more packages, i.e. `release-butler/foo@1.2.3+bar@0.4.0`.
+/// Same as [`release_branch`] for a single package.
+pub fn batch_branch(releases: &[(String, Version)]) -> String {
+    let releases: Vec<_> = releases
+        .iter()
+        .map(|(package, version)| format!("{}@{}", package, version))
+        .collect();
+    format!("release-butler/{}", releases.join("+"))
+}
+
+/// Title of the release pull request, i.e. `RELEASE @v1.2.3` or `RELEASE foo@v1.2.3, bar@v0.4.0`
+pub fn pull_title(releases: &[(String, Version)]) -> String {
+    let releases: Vec<_> = releases
+        .iter()
+        .map(|(package, version)| format!("{}@v{}", package, version))
+        .collect();
+    format!("RELEASE {}", releases.join(", "))
+}
+
+/// Name of the tag created for the release, i.e. `v1.2.3` or `foo@v1.2.3`
+pub fn tag_name(package: &str, version: &Version) -> String {
+    if package.is_empty() {
+        format!("v{}", version)
+    } else {
+        format!("{}@v{}", package, version)
+    }
+}
+
+/// Version of the release of `package` tagged `tag`, `None` if the tag is of another package.
+/// See [`tag_name`].
+pub fn tag_version(package: &str, tag: &str) -> Option<Version> {
+    let version = if package.is_empty() {
+        tag.strip_prefix('v')?
+    } else {
+        tag.strip_prefix(package)?.strip_prefix("@v")?
+    };
+    Version::parse(version).ok()
+}
+
+/// Whether the release of `version` is marked as the latest one, given the tags of the existing
+/// releases (`None` if they couldn't be listed). Only the highest stable version of the package
+/// is, so that a `1.9.5` patch released after `2.0.0` doesn't take the badge.
+///
+/// GitHub has a single latest release per repository, but versions of different packages can't
+/// be compared, so in a repository with several packages the badge goes to the last package
+/// released at its highest version. Keeping it on the highest version is only supported when the
+/// repository has a single package.
+pub fn make_latest(package: &str, version: &Version, tags: Option<&[String]>) -> MakeLatest {
+    if !version.pre.is_empty() {
+        return MakeLatest::False;
+    }
+    let Some(tags) = tags else {
+        return MakeLatest::Legacy;
+    };
+
+    let newer_release = tags
+        .iter()
+        .filter_map(|tag| tag_version(package, tag))
+        .any(|existing| existing.pre.is_empty() && existing > *version);
+    if newer_release {
+        MakeLatest::False
+    } else {
+        MakeLatest::True
+    }
+}
+
+/// Version set in the manifest, at the key edited by [`bump_manifest`]
+pub fn manifest_version(package: &Package, content: &str) -> Option<Version> {
+    let doc = content.parse::<toml_edit::DocumentMut>().ok()?;
+
+    let table = match package.package_manager {
+        PackageManager::Cargo => doc.get("package"),
+        PackageManager::CargoWorkspace => doc.get("workspace").and_then(|item| item.get("package")),
+    };
+
+    table?.get("version")?.as_str()?.parse().ok()
+}
+
+/// Sets the version in the manifest, keeping its formatting
+pub fn bump_manifest(
+    package: &Package,
+    content: &str,
+    version: &Version,
+) -> Result<String, toml_edit::TomlError> {
+    let mut doc = content.parse::<toml_edit::DocumentMut>()?;
+
+    match package.package_manager {
+        PackageManager::Cargo => {
+            doc["package"]["version"] = toml_edit::value(version.to_string());
+        }
+        PackageManager::CargoWorkspace => {
+            doc["workspace"]["package"]["version"] = toml_edit::value(version.to_string());
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Adds a section for `version` above the latest release, or creates the changelog if `existing`
+/// is `None`
+pub fn update_changelog(
+    existing: Option<&str>,
+    version: &Version,
+    date: NaiveDate,
+    notes: &str,
+) -> String {
+    let header = format!("## [{}] - {}\n", version, date.format("%Y-%m-%d"));
+
+    let Some(existing) = existing else {
+        return format!("# Changelog\n\n{}{}", header, notes);
+    };
+
+    let mut new_content = String::new();
+    let mut added_version = false;
+
+    // Find the first "## [" line to insert the new version before it
+    for line in existing.lines() {
+        if !added_version && line.starts_with("## [") {
+            new_content.push_str(&header);
+            new_content.push_str(notes);
+            new_content.push_str("\n\n");
+            added_version = true;
+        }
+        new_content.push_str(line);
+        new_content.push('\n');
+    }
+
+    // If no version headers found, append to the end
+    if !added_version {
+        new_content.push_str(&header);
+        new_content.push_str(notes);
+        new_content.push('\n');
+    }
+
+    new_content
+}
+
+/// Issues and pull requests referenced as `#123` in the release notes, in order of appearance
+pub fn referenced_issues(notes: &str) -> Vec<u64> {
+    let mut numbers = Vec::new();
+    let mut previous = None;
+    let mut chars = notes.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        let preceded_by_word = previous.is_some_and(|p: char| p.is_alphanumeric() || p == '/');
+        previous = Some(c);
+        if c != '#' || preceded_by_word {
+            continue;
+        }
+
+        let start = index + 1;
+        let mut end = start;
+        while let Some((index, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+            end = index + 1;
+            previous = Some(c);
+        }
+        if let Ok(number) = notes[start..end].parse::<u64>() {
+            if !numbers.contains(&number) {
+                numbers.push(number);
+            }
+        }
+    }
+
+    numbers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(package_manager: PackageManager) -> Package {
+        Package {
+            path: String::from("crates/foo"),
+            changelog_file: String::from("crates/foo/CHANGELOG.md"),
+            pre_release_changelog_file: String::new(),
+            create_gh_release: false,
+            package_manager,
+        }
+    }
+
+    #[test]
+    fn test_paths() {
+        let package = package(PackageManager::Cargo);
+        assert_eq!(manifest_path(&package), "crates/foo/Cargo.toml");
+        assert_eq!(
+            changelog_path(&package, &Version::new(1, 2, 3)),
+            Some("crates/foo/CHANGELOG.md")
+        );
+        assert_eq!(
+            changelog_path(&package, &Version::parse("1.2.3-rc.1").unwrap()),
+            None
+        );
+        assert_eq!(
+            release_branch("foo", &Version::new(1, 2, 3)),
+            "release-butler/foo@1.2.3"
+        );
+        assert_eq!(tag_name("", &Version::new(1, 2, 3)), "v1.2.3");
+        assert_eq!(tag_name("foo", &Version::new(1, 2, 3)), "foo@v1.2.3");
+
+        let releases = [
+            (String::from("foo"), Version::new(1, 2, 3)),
+            (String::from("bar"), Version::new(0, 4, 0)),
+        ];
+        assert_eq!(
+            batch_branch(&releases),
+            "release-butler/foo@1.2.3+bar@0.4.0"
+        );
+        assert_eq!(
+            batch_branch(&releases[..1]),
+            release_branch("foo", &Version::new(1, 2, 3))
+        );
+        assert_eq!(pull_title(&releases), "RELEASE foo@v1.2.3, bar@v0.4.0");
+        assert_eq!(
+            pull_title(&[(String::new(), Version::new(1, 2, 3))]),
+            "RELEASE @v1.2.3"
+        );
+    }
+
+    #[test]
+    fn test_expand_groups() {
+        let config: Config = toml::from_str(
+            "version = 1\ndefault_branch = \"main\"\n[issues_meta_data]\nallowed_authors = []\n\
+            [packages.foo]\npackage_manager = \"cargo\"\n[packages.bar]\npackage_manager = \"cargo\"\n\
+            [packages.baz]\npackage_manager = \"cargo\"\n\
+            [groups.core]\npackages = [\"foo\", \"bar\"]\nchangelog_file = \"CHANGELOG.md\"\n",
+        )
+        .unwrap();
+        let version = Version::new(1, 4, 0);
+        let (name, group) = find_group(&config, "bar").unwrap();
+        assert_eq!(name, "core");
+        assert_eq!(find_group(&config, "core").unwrap().0, "core");
+        assert!(find_group(&config, "baz").is_none());
+        assert!(has_shared_changelog(group));
+        assert_eq!(group_changelog_path(group, &version), Some("CHANGELOG.md"));
+
+        let releases = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| (name.to_string(), version.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            expand_groups(&config, &releases(&["core", "baz"])),
+            releases(&["foo", "bar", "baz"])
+        );
+        assert_eq!(
+            expand_groups(&config, &releases(&["bar", "foo"])),
+            releases(&["foo", "bar"])
+        );
+    }
+
+    #[test]
+    fn test_base_branch() {
+        let config: Config = toml::from_str(
+            "version = 1\ndefault_branch = \"main\"\n[issues_meta_data]\nallowed_authors = []\n\
+            [packages.foo]\npackage_manager = \"cargo\"\n\
+            [[branch_patterns]]\nversions = \"1.4.x\"\nbranch = \"release/1.4\"\n\
+            [[branch_patterns]]\nversions = \"1.x\"\nbranch = \"release/1.x\"\n\
+            [[branch_patterns]]\nversions = \"<1\"\nbranch = \"release/0.x\"\n",
+        )
+        .unwrap();
+        let releases = |version| [(String::from("foo"), Version::parse(version).unwrap())];
+        // Listed first, so it takes precedence over `1.x`
+        assert_eq!(
+            base_branch(&config, None, &releases("1.4.1")),
+            "release/1.4"
+        );
+        assert_eq!(
+            base_branch(&config, None, &releases("1.5.0")),
+            "release/1.x"
+        );
+        assert_eq!(
+            base_branch(&config, None, &releases("1.5.0-beta.1")),
+            "release/1.x"
+        );
+        assert_eq!(
+            base_branch(&config, None, &releases("0.9.0")),
+            "release/0.x"
+        );
+        assert_eq!(base_branch(&config, None, &releases("2.0.0")), "main");
+        assert_eq!(
+            base_branch(&config, Some("hotfix"), &releases("1.4.1")),
+            "hotfix"
+        );
+    }
+
+    #[test]
+    fn test_make_latest() {
+        let tags = [
+            String::from("v1.9.4"),
+            String::from("v2.0.0"),
+            String::from("v2.1.0-rc.1"),
+            String::from("foo@v3.0.0"),
+        ];
+        let version = |version| Version::parse(version).unwrap();
+        assert_eq!(
+            make_latest("", &version("2.0.1"), Some(&tags)),
+            MakeLatest::True
+        );
+        assert_eq!(
+            make_latest("", &version("1.9.5"), Some(&tags)),
+            MakeLatest::False
+        );
+        assert_eq!(
+            make_latest("", &version("2.1.0-rc.2"), Some(&tags)),
+            MakeLatest::False
+        );
+        assert_eq!(
+            make_latest("foo", &version("2.5.0"), Some(&tags)),
+            MakeLatest::False
+        );
+        assert_eq!(
+            make_latest("foo", &version("3.0.1"), Some(&[])),
+            MakeLatest::True
+        );
+        assert_eq!(make_latest("", &version("1.9.5"), None), MakeLatest::Legacy);
+
+        assert_eq!(tag_version("", "v2.0.0"), Some(version("2.0.0")));
+        assert_eq!(tag_version("foo", "foo@v3.0.0"), Some(version("3.0.0")));
+        assert_eq!(tag_version("foo", "foobar@v3.0.0"), None);
+        assert_eq!(tag_version("", "foo@v3.0.0"), None);
+    }
+
+    #[test]
+    fn test_bump_manifest() {
+        let version = Version::new(1, 2, 3);
+        assert_eq!(
+            bump_manifest(
+                &package(PackageManager::Cargo),
+                "[package]\nname = \"foo\" # the name\nversion = \"1.2.2\"\n",
+                &version
+            )
+            .unwrap(),
+            "[package]\nname = \"foo\" # the name\nversion = \"1.2.3\"\n"
+        );
+        assert_eq!(
+            bump_manifest(
+                &package(PackageManager::CargoWorkspace),
+                "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nversion = \"1.2.2\"\n",
+                &version
+            )
+            .unwrap(),
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nversion = \"1.2.3\"\n"
+        );
+        assert!(bump_manifest(&package(PackageManager::Cargo), "[package", &version).is_err());
+    }
+
+    #[test]
+    fn test_manifest_version() {
+        assert_eq!(
+            manifest_version(
+                &package(PackageManager::Cargo),
+                "[package]\nversion = \"1.2.2\"\n"
+            ),
+            Some(Version::new(1, 2, 2))
+        );
+        assert_eq!(
+            manifest_version(
+                &package(PackageManager::CargoWorkspace),
+                "[package]\nversion = \"1.2.2\"\n"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let edit = FileEdit {
+            path: String::from("Cargo.toml"),
+            old: Some(String::from(
+                "[package]\nname = \"foo\"\nversion = \"1.2.2\"\n",
+            )),
+            new: String::from("[package]\nname = \"foo\"\nversion = \"1.2.3\"\n"),
+        };
+        assert_eq!(
+            edit.unified_diff(),
+            "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -1,3 +1,3 @@\n [package]\n name = \"foo\"\n-version = \"1.2.2\"\n+version = \"1.2.3\"\n"
+        );
+
+        let edit = FileEdit {
+            path: String::from("CHANGELOG.md"),
+            old: None,
+            new: String::from("# Changelog\n"),
+        };
+        assert_eq!(
+            edit.unified_diff(),
+            "--- /dev/null\n+++ b/CHANGELOG.md\n@@ -0,0 +1 @@\n+# Changelog\n"
+        );
+    }
+
+    #[test]
+    fn test_update_changelog() {
+        let version = Version::new(1, 2, 3);
+        let date = NaiveDate::from_ymd_opt(2025, 3, 12).unwrap();
+
+        assert_eq!(
+            update_changelog(None, &version, date, "- Fix"),
+            "# Changelog\n\n## [1.2.3] - 2025-03-12\n- Fix"
+        );
+        assert_eq!(
+            update_changelog(
+                Some("# Changelog\n\n## [1.2.2] - 2025-01-01\n- Old\n"),
+                &version,
+                date,
+                "- Fix"
+            ),
+            "# Changelog\n\n## [1.2.3] - 2025-03-12\n- Fix\n\n## [1.2.2] - 2025-01-01\n- Old\n"
+        );
+        assert_eq!(
+            update_changelog(Some("# Changelog\n"), &version, date, "- Fix"),
+            "# Changelog\n## [1.2.3] - 2025-03-12\n- Fix\n"
+        );
+    }
+
+    #[test]
+    fn test_referenced_issues() {
+        assert_eq!(
+            referenced_issues("### Fixed\n- Bug Y (#12)\n- Bug Z #3, #12\n- See org/repo#4"),
+            vec![12, 3]
+        );
+        assert_eq!(
+            referenced_issues("Heading ##\n#abc, a#5"),
+            Vec::<u64>::new()
+        );
+    }
+}
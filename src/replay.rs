@@ -0,0 +1,202 @@
+//! Replays a captured GitHub webhook delivery, to reproduce a release that went wrong.
+
+use crate::{common::generate_hmac_sha256_hex, webhook::parse_event, State};
+use actix_web::{test as actix_test, web::Data, App};
+use derive_more::{Display, Error};
+use serde_json::Value;
+
+#[derive(Debug, Display, Error)]
+pub enum ReplayError {
+    #[display("The delivery is not valid JSON. {source}")]
+    Parse { source: serde_json::Error },
+    #[display("The delivery has no `X-GitHub-Event` header, pass the event name")]
+    MissingEvent,
+    #[display("Failed to send the delivery. {source}")]
+    Request { source: reqwest::Error },
+}
+
+/// A captured webhook delivery
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    /// Value of the `X-GitHub-Event` header
+    pub event: String,
+    /// Value of the `X-GitHub-Delivery` header, if captured
+    pub id: Option<String>,
+    pub payload: Vec<u8>,
+}
+
+/// Status and body of the response to a replayed delivery
+#[derive(Debug, Clone)]
+pub struct ReplayResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Delivery {
+    /// Parses a delivery saved in one of the following formats:
+    /// - as returned by GitHub's "Get a delivery for a webhook" API, i.e.
+    ///   `{"request": {"headers": {...}, "payload": {...}}}`
+    /// - `{"headers": {...}, "payload": {...}}`
+    /// - the payload only (like the files in `tests_payload/`), in which case `event` is required
+    ///
+    /// `event` takes precedence over the `X-GitHub-Event` header of the delivery.
+    pub fn parse(content: &[u8], event: Option<&str>) -> Result<Self, ReplayError> {
+        let value: Value =
+            serde_json::from_slice(content).map_err(|source| ReplayError::Parse { source })?;
+
+        let request = match value.get("request") {
+            Some(request) if request.get("headers").is_some() => request,
+            _ => &value,
+        };
+        let (headers, payload) = match (request.get("headers"), request.get("payload")) {
+            (Some(headers), Some(payload)) => {
+                let payload = match payload {
+                    // Raw body, kept byte for byte
+                    Value::String(body) => body.clone().into_bytes(),
+                    payload => payload.to_string().into_bytes(),
+                };
+                (Some(headers), payload)
+            }
+            _ => (None, content.to_vec()),
+        };
+
+        let header = |name: &str| {
+            headers
+                .and_then(Value::as_object)
+                .and_then(|headers| {
+                    headers
+                        .iter()
+                        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                })
+                .and_then(|(_, value)| value.as_str())
+                .map(str::to_owned)
+        };
+
+        let event = event
+            .map(str::to_owned)
+            .or_else(|| header("X-GitHub-Event"))
+            .ok_or(ReplayError::MissingEvent)?;
+
+        Ok(Self {
+            event,
+            id: header("X-GitHub-Delivery"),
+            payload,
+        })
+    }
+
+    /// Value of the `X-Hub-Signature-256` header for `secret`
+    pub fn signature(&self, secret: &str) -> String {
+        format!(
+            "sha256={}",
+            generate_hmac_sha256_hex(&self.payload, secret.as_bytes()).unwrap_or_default()
+        )
+    }
+
+    /// Posts the delivery to `url` (i.e. `http://localhost:8000/github/webhook/`), signed with
+    /// `secret`
+    pub async fn post(&self, url: &str, secret: &str) -> Result<ReplayResponse, ReplayError> {
+        let mut request = reqwest::Client::new()
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header("X-GitHub-Event", &self.event)
+            .header("X-Hub-Signature-256", self.signature(secret))
+            .body(self.payload.clone());
+        if let Some(id) = &self.id {
+            request = request.header("X-GitHub-Delivery", id);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|source| ReplayError::Request { source })?;
+        let status = response.status().as_u16();
+        let body = response
+            .text()
+            .await
+            .map_err(|source| ReplayError::Request { source })?;
+
+        Ok(ReplayResponse { status, body })
+    }
+
+    /// Feeds the delivery, signed with the current webhook secret of `state`, to the webhook
+    /// endpoint without starting the server
+    pub async fn replay(&self, mut state: State) -> ReplayResponse {
+        if state.webhook_secrets.is_empty() {
+            state
+                .webhook_secrets
+                .push(String::from("release-butler-replay"));
+        }
+        let signature = self.signature(&state.webhook_secrets[0]);
+        let app =
+            actix_test::init_service(App::new().app_data(Data::new(state)).service(parse_event))
+                .await;
+
+        let mut request = actix_test::TestRequest::post()
+            .uri("/github/webhook/")
+            .insert_header(("X-GitHub-Event", self.event.as_str()))
+            .insert_header(("X-Hub-Signature-256", signature))
+            .set_payload(self.payload.clone());
+        if let Some(id) = &self.id {
+            request = request.insert_header(("X-GitHub-Delivery", id.as_str()));
+        }
+
+        let response = actix_test::call_service(&app, request.to_request()).await;
+        let status = response.status().as_u16();
+        let body = actix_test::read_body(response).await;
+
+        ReplayResponse {
+            status,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_delivery() {
+        let delivery = Delivery::parse(
+            br#"{"id": 1, "event": "issues", "request": {"headers": {"X-GitHub-Event": "issues", "X-GitHub-Delivery": "72d3162e"}, "payload": {"action": "labeled"}}}"#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(delivery.event, "issues");
+        assert_eq!(delivery.id.as_deref(), Some("72d3162e"));
+        assert_eq!(delivery.payload, br#"{"action":"labeled"}"#);
+
+        let delivery = Delivery::parse(
+            br#"{"headers": {"x-github-event": "fork"}, "payload": "{ \"forkee\": {} }"}"#,
+            Some("issues"),
+        )
+        .unwrap();
+        assert_eq!(delivery.event, "issues");
+        assert_eq!(delivery.id, None);
+        assert_eq!(delivery.payload, br#"{ "forkee": {} }"#);
+
+        let payload = br#"{"action": "closed"}"#;
+        let delivery = Delivery::parse(payload, Some("pull_request")).unwrap();
+        assert_eq!(delivery.payload, payload);
+        assert!(matches!(
+            Delivery::parse(payload, None),
+            Err(ReplayError::MissingEvent)
+        ));
+        assert!(matches!(
+            Delivery::parse(b"not json", Some("issues")),
+            Err(ReplayError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_signature() {
+        let delivery = Delivery::parse(b"{}", Some("issues")).unwrap();
+        assert_eq!(
+            delivery.signature("secret"),
+            format!(
+                "sha256={}",
+                generate_hmac_sha256_hex(b"{}", b"secret").unwrap()
+            )
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! A single comment on the release issue, edited as the release progresses, instead of a new
+//! comment for every failure. Enabled with `status_comment = true`.
+
+use crate::forge::Forge;
+use std::fmt::Display;
+use tracing::error;
+
+/// Marks the status comment among the comments of the release issue
+pub static STATUS_COMMENT_MARKER: &str = "<!-- release-butler-status -->";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Step {
+    LoadConfig,
+    UpdateFiles,
+    OpenPull,
+    MergePull,
+    CreateTag,
+    PublishRelease,
+}
+
+impl Step {
+    pub const ALL: [Step; 6] = [
+        Step::LoadConfig,
+        Step::UpdateFiles,
+        Step::OpenPull,
+        Step::MergePull,
+        Step::CreateTag,
+        Step::PublishRelease,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Step::LoadConfig => "Configuration loaded",
+            Step::UpdateFiles => "Files updated",
+            Step::OpenPull => "Pull request opened",
+            Step::MergePull => "Pull request merged",
+            Step::CreateTag => "Tag created",
+            Step::PublishRelease => "Release published",
+        }
+    }
+}
+
+/// A step that failed even after retrying
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub step: Step,
+    /// What was being done, i.e. `create the tag`
+    pub action: String,
+    pub error: String,
+}
+
+/// Progress of a release, rendered as a checklist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    /// Tag of the release, i.e. `v1.2.3`
+    pub tag: String,
+    /// Completed steps, with details like a link to the pull request
+    pub completed: Vec<(Step, String)>,
+    pub failure: Option<Failure>,
+    /// Whether a release is published once the tag is created
+    pub publish_release: bool,
+}
+
+impl Status {
+    pub fn new(tag: impl Into<String>, publish_release: bool) -> Self {
+        Self {
+            tag: tag.into(),
+            completed: Vec::new(),
+            failure: None,
+            publish_release,
+        }
+    }
+
+    /// Parses a comment rendered by [`Status::render`], `None` if it isn't a status comment.
+    /// The failure isn't kept, as the release is retried.
+    pub fn parse(comment: &str) -> Option<Self> {
+        let mut lines = comment.lines();
+        if lines.next()? != STATUS_COMMENT_MARKER {
+            return None;
+        }
+
+        let tag = lines
+            .next()?
+            .strip_prefix("### Release `")?
+            .strip_suffix('`')?;
+        let mut status = Status::new(tag, false);
+
+        for line in lines {
+            if line.contains(Step::PublishRelease.label()) {
+                status.publish_release = true;
+            }
+
+            let Some(line) = line.strip_prefix("- [x] ") else {
+                continue;
+            };
+            if let Some(step) = Step::ALL
+                .into_iter()
+                .find(|step| line.starts_with(step.label()))
+            {
+                let detail = line[step.label().len()..].trim();
+                status.completed.push((step, detail.to_owned()));
+            }
+        }
+
+        Some(status)
+    }
+
+    /// Marks `step` as completed, `detail` being shown next to it
+    pub fn complete(&mut self, step: Step, detail: impl Into<String>) {
+        self.completed.retain(|(completed, _)| *completed != step);
+        self.completed.push((step, detail.into()));
+        if self
+            .failure
+            .as_ref()
+            .is_some_and(|failure| failure.step == step)
+        {
+            self.failure = None;
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut comment = format!("{}\n### Release `{}`\n\n", STATUS_COMMENT_MARKER, self.tag);
+
+        for step in Step::ALL {
+            if step == Step::PublishRelease && !self.publish_release {
+                continue;
+            }
+
+            let completed = self
+                .completed
+                .iter()
+                .find(|(completed, _)| *completed == step);
+            match (completed, &self.failure) {
+                (Some((_, detail)), _) if detail.is_empty() => {
+                    comment.push_str(&format!("- [x] {}\n", step.label()))
+                }
+                (Some((_, detail)), _) => {
+                    comment.push_str(&format!("- [x] {} {}\n", step.label(), detail))
+                }
+                (None, Some(failure)) if failure.step == step => {
+                    comment.push_str(&format!("- [ ] :x: **{}**\n", step.label()))
+                }
+                (None, _) => comment.push_str(&format!("- [ ] {}\n", step.label())),
+            }
+        }
+
+        if let Some(failure) = &self.failure {
+            comment.push_str(&format!(
+                "\nI failed to {} even after retrying, so the release has been stopped.\n\n\
+                <details>\n\
+                <summary>Error</summary>\n\n\
+                ```\n{}\n```\n\
+                </details>\n",
+                failure.action, failure.error
+            ));
+            if failure.step < Step::MergePull {
+                comment.push_str(&format!(
+                    "\nEdit the issue or re-add the `{}` label to try again.\n",
+                    crate::RELEASE_ISSUE_LABEL
+                ));
+            }
+        }
+
+        comment
+    }
+}
+
+/// The status comment of a release issue
+pub struct StatusComment<'a> {
+    forge: &'a dyn Forge,
+    issue_number: u64,
+    pub status: Status,
+}
+
+impl<'a> StatusComment<'a> {
+    /// Starts over, replacing the status comment of the issue (if any) on the next update
+    pub fn new(forge: &'a dyn Forge, issue_number: u64, status: Status) -> Self {
+        Self {
+            forge,
+            issue_number,
+            status,
+        }
+    }
+
+    /// The status comment of the issue, `None` if it wasn't posted (or can't be fetched)
+    pub async fn find(forge: &'a dyn Forge, issue_number: u64) -> Option<Self> {
+        let comments = match forge.list_comments(issue_number).await {
+            Ok(comments) => comments,
+            Err(err) => {
+                error!(
+                    "Failed to fetch the comments of issue #{}. Error: {}",
+                    issue_number, err
+                );
+                return None;
+            }
+        };
+
+        let status = comments
+            .iter()
+            .find_map(|comment| Status::parse(comment.body.as_deref()?))?;
+        Some(Self::new(forge, issue_number, status))
+    }
+
+    pub async fn complete(&mut self, step: Step, detail: impl Into<String>) {
+        self.status.complete(step, detail);
+        self.save().await;
+    }
+
+    pub async fn fail(&mut self, step: Step, action: &str, err: &impl Display) {
+        error!(
+            "Failed to {} for issue #{}. Error: {}",
+            action, self.issue_number, err
+        );
+
+        self.status.failure = Some(Failure {
+            step,
+            action: action.to_owned(),
+            error: err.to_string(),
+        });
+        self.save().await;
+    }
+
+    /// Edits the status comment, or posts it if the issue doesn't have one yet
+    async fn save(&self) {
+        let body = self.status.render();
+
+        let existing = match self.forge.list_comments(self.issue_number).await {
+            Ok(comments) => comments.into_iter().find(|comment| {
+                comment
+                    .body
+                    .as_deref()
+                    .is_some_and(|body| body.starts_with(STATUS_COMMENT_MARKER))
+            }),
+            Err(err) => {
+                error!(
+                    "Failed to fetch the comments of issue #{}. Error: {}",
+                    self.issue_number, err
+                );
+                return;
+            }
+        };
+
+        let result = match existing {
+            Some(comment) => {
+                self.forge
+                    .update_comment(self.issue_number, comment.id, &body)
+                    .await
+            }
+            None => self.forge.comment(self.issue_number, &body).await,
+        };
+        if let Err(err) = result {
+            error!(
+                "Failed to update the status comment of issue #{}. Error: {}",
+                self.issue_number, err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut status = Status::new("v1.2.3", false);
+        status.complete(Step::LoadConfig, "");
+        status.complete(Step::UpdateFiles, "in abc123");
+        status.failure = Some(Failure {
+            step: Step::OpenPull,
+            action: String::from("create the release pull request"),
+            error: String::from("API error 500"),
+        });
+
+        assert_eq!(
+            status.render(),
+            "<!-- release-butler-status -->\n### Release `v1.2.3`\n\n\
+            - [x] Configuration loaded\n\
+            - [x] Files updated in abc123\n\
+            - [ ] :x: **Pull request opened**\n\
+            - [ ] Pull request merged\n\
+            - [ ] Tag created\n\
+            \nI failed to create the release pull request even after retrying, so the release has been stopped.\n\n\
+            <details>\n<summary>Error</summary>\n\n```\nAPI error 500\n```\n</details>\n\
+            \nEdit the issue or re-add the `release-butler` label to try again.\n"
+        );
+
+        status.complete(Step::OpenPull, "https://forge.test/pulls/101");
+        assert!(status.failure.is_none());
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut status = Status::new("foo@v1.2.3", true);
+        status.complete(Step::LoadConfig, "");
+        status.complete(Step::OpenPull, "https://forge.test/pulls/101");
+        status.failure = Some(Failure {
+            step: Step::MergePull,
+            action: String::from("create the tag"),
+            error: String::from("Reference already exists"),
+        });
+
+        let parsed = Status::parse(&status.render()).unwrap();
+        assert_eq!(parsed.tag, "foo@v1.2.3");
+        assert_eq!(parsed.completed, status.completed);
+        assert_eq!(parsed.failure, None);
+        assert!(parsed.publish_release);
+
+        assert!(
+            !Status::parse(&Status::new("v1", false).render())
+                .unwrap()
+                .publish_release
+        );
+        assert_eq!(Status::parse("Thanks!"), None);
+    }
+}
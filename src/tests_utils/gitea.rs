@@ -0,0 +1,734 @@
+//! An in-process fake of the subset of Gitea REST API (v1) used by release-butler, keeping a
+//! single repository ([`OWNER`]/[`REPO`], same as the payloads in `tests_payload`) in memory.
+
+use super::paginate;
+use actix_web::{
+    dev::ServerHandle,
+    http::{Method, StatusCode},
+    web::{self, Bytes, Data},
+    App, HttpRequest, HttpResponse, HttpServer,
+};
+use base64::Engine;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, MutexGuard},
+};
+
+pub static OWNER: &str = "example_org";
+pub static REPO: &str = "example_repo";
+/// Username of the access token's user
+pub static USERNAME: &str = "release-butler-bot";
+
+pub type Files = BTreeMap<String, String>;
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    pub author: String,
+    /// Label ids, see [`Repository::labels`]
+    pub labels: Vec<u64>,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub head: String,
+    pub base: String,
+    pub body: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    pub name: String,
+    pub body: String,
+    pub prerelease: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Repository {
+    /// Snapshot of every file, keyed by commit sha
+    pub commits: HashMap<String, Files>,
+    pub branches: BTreeMap<String, String>,
+    pub tags: BTreeMap<String, String>,
+    pub labels: BTreeMap<u64, String>,
+    /// Issues and pull requests share the numbering
+    pub issues: BTreeMap<u64, Issue>,
+    pub pulls: BTreeMap<u64, PullRequest>,
+    pub comments: BTreeMap<u64, Vec<String>>,
+    pub releases: Vec<Release>,
+    /// Every request received, i.e. `POST /api/v1/repos/example_org/example_repo/issues`
+    pub requests: Vec<String>,
+    next_sha: u64,
+}
+
+type Response = (StatusCode, Value);
+
+fn not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        json!({ "message": "The target couldn't be found." }),
+    )
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, json!({ "message": message }))
+}
+
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], segment.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) if u8::from_str_radix(hex, 16).is_ok() => {
+                decoded.push(u8::from_str_radix(hex, 16).unwrap());
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).expect("Path isn't valid UTF-8")
+}
+
+/// Blob sha, only used to detect stale updates
+fn blob_sha(content: &str) -> String {
+    Sha256::digest(content.as_bytes())[..20]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+impl Repository {
+    fn next_sha(&mut self) -> String {
+        self.next_sha += 1;
+        format!("{:040x}", self.next_sha)
+    }
+
+    fn next_number(&self) -> u64 {
+        self.issues
+            .keys()
+            .chain(self.pulls.keys())
+            .max()
+            .map_or(1, |number| number + 1)
+    }
+
+    /// Snapshot of `ref_`, which is either a branch or a commit sha
+    fn snapshot(&self, ref_: &str) -> Option<&Files> {
+        let sha = self.branches.get(ref_).map_or(ref_, String::as_str);
+        self.commits.get(sha)
+    }
+
+    /// Content of the file at `path` on `branch`
+    pub fn file(&self, branch: &str, path: &str) -> Option<String> {
+        let sha = self.branches.get(branch)?;
+        self.commits.get(sha)?.get(path).cloned()
+    }
+
+    /// Commits the files on top of `branch`, creating the branch if it doesn't exist
+    pub fn commit(&mut self, branch: &str, files: &[(&str, &str)]) -> String {
+        let mut snapshot = self
+            .branches
+            .get(branch)
+            .and_then(|sha| self.commits.get(sha))
+            .cloned()
+            .unwrap_or_default();
+        for (path, content) in files {
+            snapshot.insert(path.to_string(), content.to_string());
+        }
+
+        let sha = self.next_sha();
+        self.commits.insert(sha.clone(), snapshot);
+        self.branches.insert(branch.to_owned(), sha.clone());
+        sha
+    }
+
+    /// Merges the pull request by pointing the base branch to the head branch
+    pub fn merge(&mut self, number: u64) -> Option<String> {
+        let pull = self.pulls.get_mut(&number)?;
+        pull.state = String::from("closed");
+        let sha = self.branches.get(&pull.head)?.clone();
+        self.branches.insert(pull.base.clone(), sha.clone());
+        Some(sha)
+    }
+
+    /// Names of the labels of issue `number`
+    pub fn issue_labels(&self, number: u64) -> Vec<String> {
+        self.issues
+            .get(&number)
+            .map(|issue| {
+                issue
+                    .labels
+                    .iter()
+                    .filter_map(|id| self.labels.get(id).cloned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn label_ids(value: &Value) -> Vec<u64> {
+        value
+            .as_array()
+            .map(|ids| ids.iter().filter_map(Value::as_u64).collect())
+            .unwrap_or_default()
+    }
+
+    fn issue_json(&self, issue: &Issue) -> Value {
+        let labels: Vec<_> = issue
+            .labels
+            .iter()
+            .filter_map(|id| {
+                self.labels
+                    .get(id)
+                    .map(|name| json!({ "id": id, "name": name }))
+            })
+            .collect();
+        json!({
+            "number": issue.number,
+            "title": issue.title,
+            "body": issue.body,
+            "user": { "login": issue.author },
+            "labels": labels,
+            "state": issue.state,
+            "html_url": format!("https://gitea.example.com/{}/{}/issues/{}", OWNER, REPO, issue.number),
+        })
+    }
+
+    fn pull_json(pull: &PullRequest) -> Value {
+        json!({
+            "number": pull.number,
+            "title": pull.title,
+            "body": pull.body,
+            "state": pull.state,
+            "head": { "ref": pull.head },
+            "base": { "ref": pull.base },
+            "html_url": format!("https://gitea.example.com/{}/{}/pulls/{}", OWNER, REPO, pull.number),
+        })
+    }
+
+    fn route(
+        &mut self,
+        method: &Method,
+        rest: &[String],
+        query: &HashMap<String, String>,
+        body: &Value,
+    ) -> Response {
+        let rest: Vec<&str> = rest.iter().map(String::as_str).collect();
+        let ref_ = query.get("ref").cloned().unwrap_or_default();
+
+        match (method.as_str(), rest.as_slice()) {
+            ("GET", ["raw", path @ ..]) => {
+                match self
+                    .snapshot(&ref_)
+                    .and_then(|files| files.get(&path.join("/")))
+                {
+                    // Raw content isn't JSON, it is unwrapped in `handle`
+                    Some(content) => (StatusCode::OK, Value::String(content.clone())),
+                    None => not_found(),
+                }
+            }
+            ("GET", ["contents", path @ ..]) => {
+                let path = path.join("/");
+                match self.snapshot(&ref_).and_then(|files| files.get(&path)) {
+                    Some(content) => (
+                        StatusCode::OK,
+                        json!({ "path": path, "type": "file", "sha": blob_sha(content) }),
+                    ),
+                    None => not_found(),
+                }
+            }
+            ("GET", ["branches", branch @ ..]) => match self.branches.get(&branch.join("/")) {
+                Some(sha) => (
+                    StatusCode::OK,
+                    json!({ "name": branch.join("/"), "commit": { "id": sha } }),
+                ),
+                None => not_found(),
+            },
+            ("DELETE", ["branches", branch @ ..]) => {
+                let branch = branch.join("/");
+                match self.branches.remove(&branch) {
+                    Some(_) => {
+                        // Same as Gitea, the pull requests of the branch are closed
+                        for pull in self.pulls.values_mut().filter(|pull| pull.head == branch) {
+                            pull.state = String::from("closed");
+                        }
+                        (StatusCode::NO_CONTENT, Value::Null)
+                    }
+                    None => not_found(),
+                }
+            }
+            ("POST", ["branches"]) => {
+                let name = body["new_branch_name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned();
+                if self.branches.contains_key(&name) {
+                    return error(StatusCode::CONFLICT, "The branch already exists.");
+                }
+                let old_ref = body["old_ref_name"].as_str().unwrap_or_default();
+                let sha = match self.branches.get(old_ref) {
+                    Some(sha) => sha.clone(),
+                    None if self.commits.contains_key(old_ref) => old_ref.to_owned(),
+                    None => return not_found(),
+                };
+                self.branches.insert(name.clone(), sha.clone());
+                (
+                    StatusCode::CREATED,
+                    json!({ "name": name, "commit": { "id": sha } }),
+                )
+            }
+            ("POST", ["contents"]) => {
+                let branch = body["branch"].as_str().unwrap_or_default().to_owned();
+                let Some(mut snapshot) = self
+                    .branches
+                    .get(&branch)
+                    .and_then(|sha| self.commits.get(sha))
+                    .cloned()
+                else {
+                    return not_found();
+                };
+
+                for file in body["files"].as_array().cloned().unwrap_or_default() {
+                    let path = file["path"].as_str().unwrap_or_default().to_owned();
+                    let current_sha = snapshot.get(&path).map(|content| blob_sha(content));
+                    let content = base64::prelude::BASE64_STANDARD
+                        .decode(file["content"].as_str().unwrap_or_default())
+                        .ok()
+                        .and_then(|content| String::from_utf8(content).ok())
+                        .unwrap_or_default();
+                    match (file["operation"].as_str(), current_sha) {
+                        (Some("create"), Some(_)) => {
+                            return error(
+                                StatusCode::UNPROCESSABLE_ENTITY,
+                                "repository file already exists",
+                            )
+                        }
+                        (Some("create"), None) => {
+                            snapshot.insert(path, content);
+                        }
+                        (Some("update") | Some("delete"), None) => return not_found(),
+                        (Some("update") | Some("delete"), Some(sha))
+                            if file["sha"].as_str() != Some(&sha) =>
+                        {
+                            return error(StatusCode::UNPROCESSABLE_ENTITY, "sha does not match")
+                        }
+                        (Some("update"), Some(_)) => {
+                            snapshot.insert(path, content);
+                        }
+                        (Some("delete"), Some(_)) => {
+                            snapshot.remove(&path);
+                        }
+                        _ => return error(StatusCode::UNPROCESSABLE_ENTITY, "unknown operation"),
+                    }
+                }
+
+                let sha = self.next_sha();
+                self.commits.insert(sha.clone(), snapshot);
+                self.branches.insert(branch, sha.clone());
+                (StatusCode::CREATED, json!({ "commit": { "sha": sha } }))
+            }
+            ("GET", ["pulls"]) => {
+                let pulls: Vec<_> = self
+                    .pulls
+                    .values()
+                    .filter(|pull| query.get("state").is_none_or(|state| *state == pull.state))
+                    .map(Self::pull_json)
+                    .collect();
+                (StatusCode::OK, Value::Array(pulls))
+            }
+            ("POST", ["pulls"]) => {
+                let head = body["head"].as_str().unwrap_or_default().to_owned();
+                let base = body["base"].as_str().unwrap_or_default().to_owned();
+                if !self.branches.contains_key(&head) || !self.branches.contains_key(&base) {
+                    return not_found();
+                }
+                if self
+                    .pulls
+                    .values()
+                    .any(|pull| pull.state == "open" && pull.head == head && pull.base == base)
+                {
+                    return error(StatusCode::CONFLICT, "pull request already exists");
+                }
+
+                let pull = PullRequest {
+                    number: self.next_number(),
+                    title: body["title"].as_str().unwrap_or_default().to_owned(),
+                    head,
+                    base,
+                    body: body["body"].as_str().unwrap_or_default().to_owned(),
+                    state: String::from("open"),
+                };
+                let response = Self::pull_json(&pull);
+                self.pulls.insert(pull.number, pull);
+                (StatusCode::CREATED, response)
+            }
+            ("GET", ["issues"]) => {
+                let wanted: Vec<&str> = query
+                    .get("labels")
+                    .map(|labels| labels.split(',').filter(|l| !l.is_empty()).collect())
+                    .unwrap_or_default();
+                let issues: Vec<_> = self
+                    .issues
+                    .values()
+                    .filter(|issue| issue.state == "open")
+                    .filter(|issue| {
+                        query
+                            .get("created_by")
+                            .is_none_or(|author| *author == issue.author)
+                    })
+                    .filter(|issue| {
+                        let labels = self.issue_labels(issue.number);
+                        wanted.iter().all(|label| labels.iter().any(|l| l == label))
+                    })
+                    .map(|issue| self.issue_json(issue))
+                    .collect();
+                (StatusCode::OK, Value::Array(issues))
+            }
+            ("GET", ["issues", number]) => match number
+                .parse::<u64>()
+                .ok()
+                .and_then(|number| self.issues.get(&number))
+            {
+                Some(issue) => (StatusCode::OK, self.issue_json(issue)),
+                None => not_found(),
+            },
+            ("POST", ["issues"]) => {
+                let issue = Issue {
+                    number: self.next_number(),
+                    title: body["title"].as_str().unwrap_or_default().to_owned(),
+                    body: body["body"].as_str().unwrap_or_default().to_owned(),
+                    author: USERNAME.to_owned(),
+                    labels: Self::label_ids(&body["labels"]),
+                    state: String::from("open"),
+                };
+                let response = self.issue_json(&issue);
+                self.issues.insert(issue.number, issue);
+                (StatusCode::CREATED, response)
+            }
+            ("PATCH", ["issues", number]) => {
+                let Some(number) = number
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|number| self.issues.contains_key(number))
+                else {
+                    return not_found();
+                };
+                let issue = self.issues.get_mut(&number).unwrap();
+                if let Some(title) = body["title"].as_str() {
+                    issue.title = title.to_owned();
+                }
+                if let Some(state) = body["state"].as_str() {
+                    issue.state = state.to_owned();
+                }
+                (StatusCode::OK, self.issue_json(&self.issues[&number]))
+            }
+            ("POST", ["issues", number, "comments"]) => {
+                let Some(number) = number.parse::<u64>().ok().filter(|number| {
+                    self.issues.contains_key(number) || self.pulls.contains_key(number)
+                }) else {
+                    return not_found();
+                };
+                let body = body["body"].as_str().unwrap_or_default().to_owned();
+                self.comments.entry(number).or_default().push(body.clone());
+                (StatusCode::CREATED, json!({ "id": 1, "body": body }))
+            }
+            ("GET", ["issues", number, "comments"]) => {
+                let Ok(number) = number.parse::<u64>() else {
+                    return not_found();
+                };
+                let comments = paginate(
+                    self.comments
+                        .get(&number)
+                        .into_iter()
+                        .flatten()
+                        .enumerate()
+                        .map(|(index, body)| json!({ "id": index + 1, "body": body })),
+                    query,
+                    "limit",
+                    30,
+                );
+                (StatusCode::OK, Value::Array(comments))
+            }
+            ("POST", ["issues", number, "labels"]) => {
+                let Some(issue) = number
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|number| self.issues.get_mut(&number))
+                else {
+                    return not_found();
+                };
+                for id in Self::label_ids(&body["labels"]) {
+                    if !issue.labels.contains(&id) {
+                        issue.labels.push(id);
+                    }
+                }
+                (StatusCode::OK, json!([]))
+            }
+            ("DELETE", ["issues", number, "labels", id]) => {
+                let Some(issue) = number
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|number| self.issues.get_mut(&number))
+                else {
+                    return not_found();
+                };
+                let Ok(id) = id.parse::<u64>() else {
+                    return not_found();
+                };
+                issue.labels.retain(|label| *label != id);
+                (StatusCode::NO_CONTENT, Value::Null)
+            }
+            ("GET", ["labels"]) => {
+                let labels: Vec<_> = self
+                    .labels
+                    .iter()
+                    .map(|(id, name)| json!({ "id": id, "name": name }))
+                    .collect();
+                (StatusCode::OK, Value::Array(labels))
+            }
+            ("POST", ["labels"]) => {
+                if body["color"].as_str().is_none_or(str::is_empty) {
+                    return error(StatusCode::UNPROCESSABLE_ENTITY, "color is required");
+                }
+                let name = body["name"].as_str().unwrap_or_default().to_owned();
+                let id = self.labels.keys().max().map_or(1, |id| id + 1);
+                self.labels.insert(id, name.clone());
+                (StatusCode::CREATED, json!({ "id": id, "name": name }))
+            }
+            ("POST", ["tags"]) => {
+                let tag_name = body["tag_name"].as_str().unwrap_or_default().to_owned();
+                let target = body["target"].as_str().unwrap_or_default().to_owned();
+                if self.tags.contains_key(&tag_name) {
+                    return error(StatusCode::CONFLICT, "tag already exists");
+                }
+                if !self.commits.contains_key(&target) {
+                    return not_found();
+                }
+                self.tags.insert(tag_name.clone(), target.clone());
+                (
+                    StatusCode::CREATED,
+                    json!({ "name": tag_name, "commit": { "sha": target } }),
+                )
+            }
+            ("POST", ["releases"]) => {
+                let tag_name = body["tag_name"].as_str().unwrap_or_default().to_owned();
+                if !self.tags.contains_key(&tag_name) {
+                    return not_found();
+                }
+                if self
+                    .releases
+                    .iter()
+                    .any(|release| release.tag_name == tag_name)
+                {
+                    return error(StatusCode::CONFLICT, "release already exists");
+                }
+                let release = Release {
+                    name: body["name"].as_str().unwrap_or(&tag_name).to_owned(),
+                    body: body["body"].as_str().unwrap_or_default().to_owned(),
+                    prerelease: body["prerelease"].as_bool().unwrap_or_default(),
+                    tag_name,
+                };
+                let response = json!({
+                    "id": self.releases.len() + 1,
+                    "tag_name": release.tag_name,
+                    "name": release.name,
+                    "body": release.body,
+                    "prerelease": release.prerelease,
+                    "html_url": format!(
+                        "https://gitea.example.com/{}/{}/releases/tag/{}",
+                        OWNER, REPO, release.tag_name
+                    )
+                });
+                self.releases.push(release);
+                (StatusCode::CREATED, response)
+            }
+            _ => not_found(),
+        }
+    }
+}
+
+async fn handle(
+    req: HttpRequest,
+    body: Bytes,
+    repository: Data<Mutex<Repository>>,
+) -> HttpResponse {
+    let mut repository = repository.lock().unwrap();
+    let path = req.uri().path().to_owned();
+    repository
+        .requests
+        .push(format!("{} {}", req.method(), path));
+
+    let authorized = req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("token "));
+    if !authorized {
+        return HttpResponse::Unauthorized().json(json!({ "message": "token is required" }));
+    }
+
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .map(|query| query.into_inner())
+        .unwrap_or_default();
+    let body = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+    let segments: Vec<String> = path.trim_matches('/').split('/').map(decode).collect();
+
+    let (status, response) = match segments.as_slice() {
+        [api, v1, repos, owner, repo, rest @ ..]
+            if api == "api" && v1 == "v1" && repos == "repos" && owner == OWNER && repo == REPO =>
+        {
+            repository.route(req.method(), rest, &query, &body)
+        }
+        _ => not_found(),
+    };
+
+    let raw = segments.get(5).is_some_and(|segment| segment == "raw");
+    match response {
+        Value::String(content) if raw && status.is_success() => {
+            HttpResponse::build(status).body(content)
+        }
+        Value::Null => HttpResponse::build(status).finish(),
+        response => HttpResponse::build(status).json(response),
+    }
+}
+
+/// A running fake Gitea API server, stopped when dropped
+pub struct MockGitea {
+    repository: Data<Mutex<Repository>>,
+    uri: String,
+    handle: ServerHandle,
+}
+
+impl MockGitea {
+    /// Starts the server on a random port. Must be called from within an actix runtime, i.e.
+    /// `#[actix_web::test]`
+    pub async fn start() -> Self {
+        let repository = Data::new(Mutex::new(Repository::default()));
+
+        let app_repository = repository.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_repository.clone())
+                .default_service(web::to(handle))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("Failed to bind mock Gitea server");
+
+        let uri = format!("http://{}", server.addrs()[0]);
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        Self {
+            repository,
+            uri,
+            handle,
+        }
+    }
+
+    /// URL of the Gitea instance, without `/api/v1`
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn repository(&self) -> MutexGuard<'_, Repository> {
+        self.repository.lock().unwrap()
+    }
+
+    /// See [`Repository::commit`]
+    pub fn commit_files(&self, branch: &str, files: &[(&str, &str)]) -> String {
+        self.repository().commit(branch, files)
+    }
+
+    pub fn file(&self, branch: &str, path: &str) -> Option<String> {
+        self.repository().file(branch, path)
+    }
+
+    /// Opens issue `number`, creating the labels if needed
+    pub fn open_issue(&self, number: u64, title: &str, body: &str, author: &str, labels: &[&str]) {
+        let mut repository = self.repository();
+        let labels = labels
+            .iter()
+            .map(|label| {
+                match repository
+                    .labels
+                    .iter()
+                    .find(|(_, name)| name.as_str() == *label)
+                {
+                    Some((id, _)) => *id,
+                    None => {
+                        let id = repository.labels.keys().max().map_or(1, |id| id + 1);
+                        repository.labels.insert(id, label.to_string());
+                        id
+                    }
+                }
+            })
+            .collect();
+        repository.issues.insert(
+            number,
+            Issue {
+                number,
+                title: title.to_owned(),
+                body: body.to_owned(),
+                author: author.to_owned(),
+                labels,
+                state: String::from("open"),
+            },
+        );
+    }
+
+    /// See [`Repository::merge`]
+    pub fn merge(&self, number: u64) -> String {
+        self.repository()
+            .merge(number)
+            .unwrap_or_else(|| panic!("Failed to merge pull request #{}", number))
+    }
+
+    pub fn pulls(&self) -> Vec<PullRequest> {
+        self.repository().pulls.values().cloned().collect()
+    }
+
+    /// Names of the labels of issue `number`, see [`Repository::issue_labels`]
+    pub fn issue(&self, number: u64) -> Option<Issue> {
+        self.repository().issues.get(&number).cloned()
+    }
+
+    pub fn issue_labels(&self, number: u64) -> Vec<String> {
+        self.repository().issue_labels(number)
+    }
+
+    pub fn comments(&self, number: u64) -> Vec<String> {
+        self.repository()
+            .comments
+            .get(&number)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn tag(&self, name: &str) -> Option<String> {
+        self.repository().tags.get(name).cloned()
+    }
+
+    pub fn releases(&self) -> Vec<Release> {
+        self.repository().releases.clone()
+    }
+}
+
+impl Drop for MockGitea {
+    fn drop(&mut self) {
+        // `stop` only signals the server, the returned future doesn't need to be awaited
+        #[allow(clippy::let_underscore_future)]
+        let _ = self.handle.stop(false);
+    }
+}
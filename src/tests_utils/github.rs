@@ -0,0 +1,1056 @@
+//! An in-process fake of the subset of GitHub REST and GraphQL API used by release-butler. It
+//! keeps a single repository ([`OWNER`]/[`REPO`], same as the payloads in `tests_payload`) in
+//! memory, so the handlers can be exercised end-to-end without network access.
+//!
+//! Failures can be injected with [`MockGitHub::fail`] to exercise the error paths.
+
+use super::paginate;
+use actix_web::{
+    dev::ServerHandle,
+    http::{Method, StatusCode},
+    web::{self, Bytes, Data},
+    App, HttpRequest, HttpResponse, HttpServer,
+};
+use base64::Engine;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, MutexGuard},
+};
+
+pub static OWNER: &str = "example_org";
+pub static REPO: &str = "example_repo";
+/// Author of the issues created by the app, same as [`crate::State::app_username`] in tests
+pub static APP_USERNAME: &str = "app/release-butler";
+
+#[derive(Debug, Clone)]
+pub struct TreeItem {
+    pub mode: String,
+    pub sha: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Commit {
+    pub tree: String,
+    pub parents: Vec<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    /// Branch name, without the owner prefix
+    pub head: String,
+    pub base: String,
+    pub body: String,
+    pub state: String,
+    pub merge_commit_sha: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    pub author: String,
+    pub labels: Vec<String>,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub id: u64,
+    pub tag_name: String,
+    pub name: String,
+    pub body: String,
+    pub prerelease: bool,
+    pub make_latest: String,
+}
+
+/// A failure injected with [`MockGitHub::fail`]
+#[derive(Debug, Clone)]
+struct Failure {
+    method: Method,
+    path: String,
+    status: StatusCode,
+    remaining: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Repository {
+    pub default_branch: String,
+    pub blobs: HashMap<String, Vec<u8>>,
+    /// Trees are flat, i.e. every item is keyed by its full path
+    pub trees: HashMap<String, BTreeMap<String, TreeItem>>,
+    pub commits: HashMap<String, Commit>,
+    /// Fully qualified references without `refs/` prefix, i.e. `heads/main`
+    pub refs: BTreeMap<String, String>,
+    pub pulls: BTreeMap<u64, PullRequest>,
+    pub issues: BTreeMap<u64, Issue>,
+    pub releases: Vec<Release>,
+    /// Comments on issues and pull requests
+    pub comments: BTreeMap<u64, Vec<String>>,
+    /// Issue (or pull request) number and index in [`Repository::comments`] of every comment,
+    /// the comment id being the position + 1
+    pub comment_ids: Vec<(u64, usize)>,
+    /// Labels removed from issues and pull requests
+    pub removed_labels: BTreeMap<u64, Vec<String>>,
+    /// Reactions to comments, keyed by comment id
+    pub reactions: BTreeMap<u64, Vec<String>>,
+    /// Every request received, i.e. `POST /repos/example_org/example_repo/git/blobs`
+    pub requests: Vec<String>,
+    failures: Vec<Failure>,
+    next_number: u64,
+    next_sha: u64,
+}
+
+impl Default for Repository {
+    fn default() -> Self {
+        Self {
+            default_branch: String::from("main"),
+            blobs: HashMap::new(),
+            trees: HashMap::new(),
+            commits: HashMap::new(),
+            refs: BTreeMap::new(),
+            pulls: BTreeMap::new(),
+            issues: BTreeMap::new(),
+            releases: Vec::new(),
+            comments: BTreeMap::new(),
+            comment_ids: Vec::new(),
+            removed_labels: BTreeMap::new(),
+            reactions: BTreeMap::new(),
+            requests: Vec::new(),
+            failures: Vec::new(),
+            next_number: 100,
+            next_sha: 0,
+        }
+    }
+}
+
+type Response = (StatusCode, Value);
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, json!({ "message": "Not Found" }))
+}
+
+fn unprocessable(message: &str) -> Response {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        json!({ "message": message }),
+    )
+}
+
+impl Repository {
+    fn next_sha(&mut self) -> String {
+        self.next_sha += 1;
+        format!("{:040x}", self.next_sha)
+    }
+
+    fn next_number(&mut self) -> u64 {
+        self.next_number += 1;
+        self.next_number
+    }
+
+    /// Resolves a branch name, fully qualified reference or commit sha to a commit sha
+    pub fn resolve(&self, ref_: &str) -> Option<String> {
+        let ref_ = ref_.strip_prefix("refs/").unwrap_or(ref_);
+        self.refs
+            .get(ref_)
+            .or_else(|| self.refs.get(&format!("heads/{}", ref_)))
+            .cloned()
+            .or_else(|| self.commits.contains_key(ref_).then(|| ref_.to_owned()))
+    }
+
+    /// Returns the tree of the commit or the tree itself
+    fn tree_of(&self, tree_ish: &str) -> Option<&BTreeMap<String, TreeItem>> {
+        match self.commits.get(tree_ish) {
+            Some(commit) => self.trees.get(&commit.tree),
+            None => self.trees.get(tree_ish),
+        }
+    }
+
+    /// Content of the file at `path` on `ref_`, see [`Repository::resolve`]
+    pub fn file(&self, ref_: &str, path: &str) -> Option<Vec<u8>> {
+        let commit = self.resolve(ref_)?;
+        let item = self.tree_of(&commit)?.get(path)?;
+        self.blobs.get(&item.sha).cloned()
+    }
+
+    /// Mode of the file at `path` on `ref_`, see [`Repository::resolve`]
+    pub fn file_mode(&self, ref_: &str, path: &str) -> Option<String> {
+        let commit = self.resolve(ref_)?;
+        Some(self.tree_of(&commit)?.get(path)?.mode.clone())
+    }
+
+    /// Commits the files on top of `branch`, creating the branch if it doesn't exist. Files with
+    /// `None` content are removed. Returns the sha of the commit.
+    pub fn commit(
+        &mut self,
+        branch: &str,
+        message: &str,
+        files: &[(&str, Option<&str>, &str)],
+    ) -> String {
+        let files: Vec<_> = files
+            .iter()
+            .map(|(path, content, mode)| {
+                (
+                    path.to_string(),
+                    content.map(|content| content.as_bytes().to_vec()),
+                    mode.to_string(),
+                )
+            })
+            .collect();
+        let ref_ = format!("heads/{}", branch);
+        let parents: Vec<_> = self.refs.get(&ref_).cloned().into_iter().collect();
+
+        let sha = self.commit_tree(&parents, message, files);
+        self.refs.insert(ref_, sha.clone());
+        sha
+    }
+
+    /// Creates a commit with the tree of the first parent and `files` applied on top of it
+    fn commit_tree(
+        &mut self,
+        parents: &[String],
+        message: &str,
+        files: Vec<(String, Option<Vec<u8>>, String)>,
+    ) -> String {
+        let mut tree = parents
+            .first()
+            .and_then(|parent| self.tree_of(parent))
+            .cloned()
+            .unwrap_or_default();
+
+        for (path, content, mode) in files {
+            match content {
+                Some(content) => {
+                    let sha = self.next_sha();
+                    self.blobs.insert(sha.clone(), content);
+                    tree.insert(path, TreeItem { mode, sha });
+                }
+                None => {
+                    tree.remove(&path);
+                }
+            }
+        }
+
+        let tree_sha = self.next_sha();
+        self.trees.insert(tree_sha.clone(), tree);
+
+        let sha = self.next_sha();
+        self.commits.insert(
+            sha.clone(),
+            Commit {
+                tree: tree_sha,
+                parents: parents.to_vec(),
+                message: message.to_owned(),
+            },
+        );
+        sha
+    }
+
+    /// Merges the pull request into its base branch with a merge commit, returning its sha
+    pub fn merge_pull(&mut self, number: u64) -> Option<String> {
+        let pull = self.pulls.get(&number)?.clone();
+        let base = self.refs.get(&format!("heads/{}", pull.base))?.clone();
+        let head = self.refs.get(&format!("heads/{}", pull.head))?.clone();
+
+        // The head tree is taken as is, the mock doesn't need a real three-way merge
+        let tree = self.commits[&head].tree.clone();
+        let sha = self.next_sha();
+        self.commits.insert(
+            sha.clone(),
+            Commit {
+                tree,
+                parents: vec![base, head],
+                message: format!("Merge pull request #{} from {}", number, pull.head),
+            },
+        );
+        self.refs
+            .insert(format!("heads/{}", pull.base), sha.clone());
+
+        let pull = self.pulls.get_mut(&number)?;
+        pull.state = String::from("closed");
+        pull.merge_commit_sha = Some(sha.clone());
+        Some(sha)
+    }
+
+    fn route(
+        &mut self,
+        method: &Method,
+        rest: &[&str],
+        query: &HashMap<String, String>,
+        body: &Value,
+    ) -> Response {
+        match (method.as_str(), rest) {
+            ("GET", ["contents", path @ ..]) => {
+                let path = path.join("/");
+                let ref_ = query
+                    .get("ref")
+                    .cloned()
+                    .unwrap_or_else(|| self.default_branch.clone());
+                let Some(commit) = self.resolve(&ref_) else {
+                    return not_found();
+                };
+                let Some(item) = self
+                    .tree_of(&commit)
+                    .and_then(|tree| tree.get(&path))
+                    .cloned()
+                else {
+                    return not_found();
+                };
+                let content = &self.blobs[&item.sha];
+
+                (
+                    StatusCode::OK,
+                    json!({
+                        "type": "file",
+                        "name": path.rsplit('/').next().unwrap_or_default(),
+                        "path": path,
+                        "sha": item.sha,
+                        "encoding": "base64",
+                        "content": base64::prelude::BASE64_STANDARD.encode(content),
+                    }),
+                )
+            }
+            ("GET", ["git", "ref", ref_ @ ..]) => {
+                let ref_ = ref_.join("/");
+                match self.refs.get(&ref_) {
+                    Some(sha) => (
+                        StatusCode::OK,
+                        json!({
+                            "ref": format!("refs/{}", ref_),
+                            "object": { "type": "commit", "sha": sha }
+                        }),
+                    ),
+                    None => not_found(),
+                }
+            }
+            ("POST", ["git", "refs"]) => {
+                let ref_ = body["ref"].as_str().unwrap_or_default();
+                let ref_ = ref_.strip_prefix("refs/").unwrap_or(ref_).to_owned();
+                let sha = body["sha"].as_str().unwrap_or_default().to_owned();
+
+                if self.refs.contains_key(&ref_) {
+                    return unprocessable("Reference already exists");
+                }
+                if !self.commits.contains_key(&sha) {
+                    return unprocessable("Object does not exist");
+                }
+
+                self.refs.insert(ref_.clone(), sha.clone());
+                (
+                    StatusCode::CREATED,
+                    json!({
+                        "ref": format!("refs/{}", ref_),
+                        "object": { "type": "commit", "sha": sha }
+                    }),
+                )
+            }
+            ("PATCH", ["git", "refs", ref_ @ ..]) => {
+                let ref_ = ref_.join("/");
+                let sha = body["sha"].as_str().unwrap_or_default().to_owned();
+
+                if !self.refs.contains_key(&ref_) {
+                    return unprocessable("Reference does not exist");
+                }
+                if !self.commits.contains_key(&sha) {
+                    return unprocessable("Object does not exist");
+                }
+
+                self.refs.insert(ref_.clone(), sha.clone());
+                (
+                    StatusCode::OK,
+                    json!({
+                        "ref": format!("refs/{}", ref_),
+                        "object": { "type": "commit", "sha": sha }
+                    }),
+                )
+            }
+            ("DELETE", ["git", "refs", ref_ @ ..]) => match self.refs.remove(&ref_.join("/")) {
+                Some(_) => (StatusCode::NO_CONTENT, Value::Null),
+                None => unprocessable("Reference does not exist"),
+            },
+            ("POST", ["git", "blobs"]) => {
+                let content = body["content"].as_str().unwrap_or_default();
+                let content = match body["encoding"].as_str() {
+                    Some("base64") => match base64::prelude::BASE64_STANDARD.decode(content) {
+                        Ok(content) => content,
+                        Err(_) => return unprocessable("Invalid base64 content"),
+                    },
+                    _ => content.as_bytes().to_vec(),
+                };
+
+                let sha = self.next_sha();
+                self.blobs.insert(sha.clone(), content);
+                (StatusCode::CREATED, json!({ "sha": sha }))
+            }
+            ("GET", ["git", "trees", tree_ish]) => match self.tree_of(tree_ish) {
+                Some(tree) => {
+                    let items: Vec<_> = tree
+                        .iter()
+                        .map(|(path, item)| {
+                            json!({
+                                "path": path,
+                                "mode": item.mode,
+                                "type": "blob",
+                                "sha": item.sha
+                            })
+                        })
+                        .collect();
+                    (
+                        StatusCode::OK,
+                        json!({ "sha": tree_ish, "tree": items, "truncated": false }),
+                    )
+                }
+                None => not_found(),
+            },
+            ("POST", ["git", "trees"]) => {
+                let mut tree = match body["base_tree"].as_str() {
+                    Some(base) => match self.tree_of(base) {
+                        Some(tree) => tree.clone(),
+                        None => return unprocessable("base_tree does not exist"),
+                    },
+                    None => BTreeMap::new(),
+                };
+
+                for item in body["tree"].as_array().cloned().unwrap_or_default() {
+                    let path = item["path"].as_str().unwrap_or_default().to_owned();
+                    let mode = item["mode"].as_str().unwrap_or("100644").to_owned();
+
+                    if let Some(content) = item["content"].as_str() {
+                        let sha = self.next_sha();
+                        self.blobs.insert(sha.clone(), content.as_bytes().to_vec());
+                        tree.insert(path, TreeItem { mode, sha });
+                    } else if let Some(sha) = item["sha"].as_str() {
+                        if !self.blobs.contains_key(sha) {
+                            return unprocessable("Blob does not exist");
+                        }
+                        tree.insert(
+                            path,
+                            TreeItem {
+                                mode,
+                                sha: sha.to_owned(),
+                            },
+                        );
+                    } else if tree.remove(&path).is_none() {
+                        return unprocessable("Can't delete a file which doesn't exist");
+                    }
+                }
+
+                let sha = self.next_sha();
+                self.trees.insert(sha.clone(), tree);
+                (StatusCode::CREATED, json!({ "sha": sha }))
+            }
+            ("POST", ["git", "commits"]) => {
+                let tree = body["tree"].as_str().unwrap_or_default().to_owned();
+                if !self.trees.contains_key(&tree) {
+                    return unprocessable("Tree does not exist");
+                }
+
+                let sha = self.next_sha();
+                self.commits.insert(
+                    sha.clone(),
+                    Commit {
+                        tree,
+                        parents: body["parents"]
+                            .as_array()
+                            .cloned()
+                            .unwrap_or_default()
+                            .iter()
+                            .filter_map(|parent| parent.as_str().map(str::to_owned))
+                            .collect(),
+                        message: body["message"].as_str().unwrap_or_default().to_owned(),
+                    },
+                );
+                (StatusCode::CREATED, json!({ "sha": sha }))
+            }
+            ("GET", ["pulls"]) => {
+                let pulls: Vec<_> = self
+                    .pulls
+                    .values()
+                    .filter(|pull| query.get("state").is_none_or(|state| *state == pull.state))
+                    .filter(|pull| query.get("base").is_none_or(|base| *base == pull.base))
+                    .filter(|pull| {
+                        query
+                            .get("head")
+                            .is_none_or(|head| *head == format!("{}:{}", OWNER, pull.head))
+                    })
+                    .map(pull_json)
+                    .collect();
+                (StatusCode::OK, Value::Array(pulls))
+            }
+            ("POST", ["pulls"]) => {
+                let head = body["head"].as_str().unwrap_or_default().to_owned();
+                if !self.refs.contains_key(&format!("heads/{}", head)) {
+                    return unprocessable("head does not exist");
+                }
+                if self
+                    .pulls
+                    .values()
+                    .any(|pull| pull.head == head && pull.state == "open")
+                {
+                    return unprocessable("A pull request already exists");
+                }
+
+                let pull = PullRequest {
+                    number: self.next_number(),
+                    title: body["title"].as_str().unwrap_or_default().to_owned(),
+                    head,
+                    base: body["base"].as_str().unwrap_or_default().to_owned(),
+                    body: body["body"].as_str().unwrap_or_default().to_owned(),
+                    state: String::from("open"),
+                    merge_commit_sha: None,
+                };
+                let response = pull_json(&pull);
+                self.pulls.insert(pull.number, pull);
+                (StatusCode::CREATED, response)
+            }
+            ("PATCH", ["pulls", number]) => {
+                let Some(pull) = number
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|number| self.pulls.get_mut(&number))
+                else {
+                    return not_found();
+                };
+                if let Some(state) = body["state"].as_str() {
+                    pull.state = state.to_owned();
+                }
+                (StatusCode::OK, pull_json(pull))
+            }
+            ("GET", ["issues"]) => {
+                let issues: Vec<_> = self
+                    .issues
+                    .values()
+                    .filter(|issue| {
+                        issue.state == query.get("state").map_or("open", String::as_str)
+                            || query.get("state").is_some_and(|state| state == "all")
+                    })
+                    .filter(|issue| {
+                        query
+                            .get("creator")
+                            .is_none_or(|creator| *creator == issue.author)
+                    })
+                    .filter(|issue| {
+                        query.get("labels").is_none_or(|labels| {
+                            labels
+                                .split(',')
+                                .all(|label| issue.labels.iter().any(|l| l == label))
+                        })
+                    })
+                    .map(issue_json)
+                    .collect();
+                (StatusCode::OK, Value::Array(issues))
+            }
+            ("GET", ["issues", number]) => {
+                match number
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|number| self.issues.get(&number))
+                {
+                    Some(issue) => (StatusCode::OK, issue_json(issue)),
+                    None => not_found(),
+                }
+            }
+            ("POST", ["issues"]) => {
+                let issue = Issue {
+                    number: self.next_number(),
+                    title: body["title"].as_str().unwrap_or_default().to_owned(),
+                    body: body["body"].as_str().unwrap_or_default().to_owned(),
+                    author: APP_USERNAME.to_owned(),
+                    labels: body["labels"]
+                        .as_array()
+                        .cloned()
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|label| label.as_str().map(str::to_owned))
+                        .collect(),
+                    state: String::from("open"),
+                };
+                let response = issue_json(&issue);
+                self.issues.insert(issue.number, issue);
+                (StatusCode::CREATED, response)
+            }
+            ("PATCH", ["issues", number]) => {
+                let Some(issue) = number
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|number| self.issues.get_mut(&number))
+                else {
+                    return not_found();
+                };
+                if let Some(title) = body["title"].as_str() {
+                    issue.title = title.to_owned();
+                }
+                if let Some(body) = body["body"].as_str() {
+                    issue.body = body.to_owned();
+                }
+                if let Some(state) = body["state"].as_str() {
+                    issue.state = state.to_owned();
+                }
+                (StatusCode::OK, issue_json(issue))
+            }
+            ("POST", ["issues", number, "comments"]) => {
+                let Ok(number) = number.parse::<u64>() else {
+                    return not_found();
+                };
+                let body = body["body"].as_str().unwrap_or_default().to_owned();
+                let comments = self.comments.entry(number).or_default();
+                comments.push(body.clone());
+                self.comment_ids.push((number, comments.len() - 1));
+                (
+                    StatusCode::CREATED,
+                    json!({ "id": self.comment_ids.len(), "body": body }),
+                )
+            }
+            ("GET", ["issues", number, "comments"]) => {
+                let Ok(number) = number.parse::<u64>() else {
+                    return not_found();
+                };
+                let comments = paginate(
+                    self.comment_ids
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (n, _))| *n == number)
+                        .map(|(id, (_, index))| {
+                            json!({ "id": id + 1, "body": self.comments[&number][*index] })
+                        }),
+                    query,
+                    "per_page",
+                    30,
+                );
+                (StatusCode::OK, Value::Array(comments))
+            }
+            ("PATCH", ["issues", "comments", id]) => {
+                let Some((number, index)) = id
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|id| self.comment_ids.get(id.checked_sub(1)?))
+                    .copied()
+                else {
+                    return not_found();
+                };
+                let body = body["body"].as_str().unwrap_or_default().to_owned();
+                self.comments.get_mut(&number).unwrap()[index] = body.clone();
+                (StatusCode::OK, json!({ "id": id, "body": body }))
+            }
+            ("POST", ["issues", "comments", id, "reactions"]) => {
+                let Ok(id) = id.parse::<u64>() else {
+                    return not_found();
+                };
+                let content = body["content"].as_str().unwrap_or_default().to_owned();
+                self.reactions.entry(id).or_default().push(content.clone());
+                (StatusCode::CREATED, json!({ "id": 1, "content": content }))
+            }
+            ("POST", ["issues", number, "labels"]) => {
+                let Some(issue) = number
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|number| self.issues.get_mut(&number))
+                else {
+                    return not_found();
+                };
+                for label in body["labels"].as_array().cloned().unwrap_or_default() {
+                    let label = label.as_str().unwrap_or_default().to_owned();
+                    if !issue.labels.contains(&label) {
+                        issue.labels.push(label);
+                    }
+                }
+                (StatusCode::OK, labels_json(&issue.labels))
+            }
+            ("DELETE", ["issues", number, "labels", label]) => {
+                let Ok(number) = number.parse::<u64>() else {
+                    return not_found();
+                };
+                self.removed_labels
+                    .entry(number)
+                    .or_default()
+                    .push(label.to_string());
+
+                match self.issues.get_mut(&number) {
+                    Some(issue) => {
+                        issue.labels.retain(|l| l != label);
+                        (StatusCode::OK, labels_json(&issue.labels))
+                    }
+                    None => (StatusCode::OK, json!([])),
+                }
+            }
+            ("GET", ["releases"]) => {
+                // Newest first, same as GitHub
+                let releases = paginate(
+                    self.releases.iter().rev().map(release_json),
+                    query,
+                    "per_page",
+                    30,
+                );
+                (StatusCode::OK, Value::Array(releases))
+            }
+            ("POST", ["releases"]) => {
+                let tag_name = body["tag_name"].as_str().unwrap_or_default().to_owned();
+                if self
+                    .releases
+                    .iter()
+                    .any(|release| release.tag_name == tag_name)
+                {
+                    return unprocessable("Release with this tag_name already exists");
+                }
+
+                let release = Release {
+                    id: self.releases.len() as u64 + 1,
+                    name: body["name"].as_str().unwrap_or(&tag_name).to_owned(),
+                    tag_name,
+                    body: body["body"].as_str().unwrap_or_default().to_owned(),
+                    prerelease: body["prerelease"].as_bool().unwrap_or_default(),
+                    make_latest: body["make_latest"].as_str().unwrap_or("true").to_owned(),
+                };
+                let response = release_json(&release);
+                self.releases.push(release);
+                (StatusCode::CREATED, response)
+            }
+            _ => not_found(),
+        }
+    }
+
+    /// Only the `createCommitOnBranch` mutation is supported
+    fn graphql(&mut self, body: &Value) -> Response {
+        let query = body["query"].as_str().unwrap_or_default();
+        if !query.contains("createCommitOnBranch") {
+            return graphql_error("Unsupported query");
+        }
+
+        let input = &body["variables"]["input"];
+        let branch = input["branch"]["branchName"].as_str().unwrap_or_default();
+        let Some(head) = self.refs.get(&format!("heads/{}", branch)).cloned() else {
+            return graphql_error(&format!("Branch {} doesn't exist", branch));
+        };
+        if input["expectedHeadOid"].as_str() != Some(&head) {
+            return graphql_error(&format!("Expected branch to point to {}", head));
+        }
+
+        let changes = &input["fileChanges"];
+        let mut files = Vec::new();
+        for addition in changes["additions"].as_array().cloned().unwrap_or_default() {
+            let contents = addition["contents"].as_str().unwrap_or_default();
+            let Ok(contents) = base64::prelude::BASE64_STANDARD.decode(contents) else {
+                return graphql_error("Invalid base64 contents");
+            };
+            let path = addition["path"].as_str().unwrap_or_default().to_owned();
+            let mode = self
+                .tree_of(&head)
+                .and_then(|tree| tree.get(&path))
+                .map_or_else(|| String::from("100644"), |item| item.mode.clone());
+            files.push((path, Some(contents), mode));
+        }
+        for deletion in changes["deletions"].as_array().cloned().unwrap_or_default() {
+            let path = deletion["path"].as_str().unwrap_or_default().to_owned();
+            files.push((path, None, String::new()));
+        }
+
+        let message = input["message"]["headline"].as_str().unwrap_or_default();
+        let sha = self.commit_tree(&[head], message, files);
+        self.refs.insert(format!("heads/{}", branch), sha.clone());
+
+        (
+            StatusCode::OK,
+            json!({ "data": { "createCommitOnBranch": { "commit": { "oid": sha } } } }),
+        )
+    }
+
+    /// Returns the injected failure for the request, if any
+    fn failure(&mut self, method: &Method, path: &str) -> Option<StatusCode> {
+        let failure = self.failures.iter_mut().find(|failure| {
+            failure.remaining > 0 && failure.method == method && path.ends_with(&failure.path)
+        })?;
+        failure.remaining -= 1;
+        Some(failure.status)
+    }
+}
+
+fn graphql_error(message: &str) -> Response {
+    (
+        StatusCode::OK,
+        json!({ "errors": [{ "message": message }] }),
+    )
+}
+
+fn labels_json(labels: &[String]) -> Value {
+    labels
+        .iter()
+        .map(|label| json!({ "name": label }))
+        .collect()
+}
+
+fn issue_json(issue: &Issue) -> Value {
+    json!({
+        "number": issue.number,
+        "title": issue.title,
+        "body": issue.body,
+        "state": issue.state,
+        "user": { "login": issue.author },
+        "labels": labels_json(&issue.labels),
+        "html_url": format!("https://github.com/{}/{}/issues/{}", OWNER, REPO, issue.number),
+    })
+}
+
+fn release_json(release: &Release) -> Value {
+    json!({
+        "id": release.id,
+        "tag_name": release.tag_name,
+        "name": release.name,
+        "body": release.body,
+        "prerelease": release.prerelease,
+        "draft": false,
+        "html_url": format!(
+            "https://github.com/{}/{}/releases/tag/{}",
+            OWNER, REPO, release.tag_name
+        ),
+    })
+}
+
+fn pull_json(pull: &PullRequest) -> Value {
+    json!({
+        "number": pull.number,
+        "title": pull.title,
+        "body": pull.body,
+        "state": pull.state,
+        "merged": pull.merge_commit_sha.is_some(),
+        "merge_commit_sha": pull.merge_commit_sha,
+        "html_url": format!("https://github.com/{}/{}/pull/{}", OWNER, REPO, pull.number),
+        "head": { "ref": pull.head, "label": format!("{}:{}", OWNER, pull.head) },
+        "base": { "ref": pull.base },
+    })
+}
+
+async fn handle(
+    req: HttpRequest,
+    body: Bytes,
+    repository: Data<Mutex<Repository>>,
+) -> HttpResponse {
+    let mut repository = repository.lock().unwrap();
+    repository
+        .requests
+        .push(format!("{} {}", req.method(), req.path()));
+
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .map(|query| query.into_inner())
+        .unwrap_or_default();
+    let body = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+    let segments: Vec<&str> = req.path().trim_matches('/').split('/').collect();
+
+    if let Some(status) = repository.failure(req.method(), req.path()) {
+        return HttpResponse::build(status).json(json!({ "message": "Injected failure" }));
+    }
+
+    let (status, response) = match segments.as_slice() {
+        ["app", "installations", _, "access_tokens"] if req.method() == Method::POST => (
+            StatusCode::CREATED,
+            json!({
+                "token": "ghs_mock_installation_token",
+                "expires_at": "2099-01-01T00:00:00Z",
+                "permissions": {}
+            }),
+        ),
+        ["graphql"] if req.method() == Method::POST => repository.graphql(&body),
+        ["repos", owner, repo, rest @ ..] if *owner == OWNER && *repo == REPO => {
+            repository.route(req.method(), rest, &query, &body)
+        }
+        _ => not_found(),
+    };
+
+    HttpResponse::build(status).json(response)
+}
+
+/// A running fake GitHub API server, stopped when dropped
+pub struct MockGitHub {
+    repository: Data<Mutex<Repository>>,
+    uri: String,
+    handle: ServerHandle,
+}
+
+impl MockGitHub {
+    /// Starts the server on a random port. Must be called from within an actix runtime, i.e.
+    /// `#[actix_web::test]`
+    pub async fn start() -> Self {
+        let repository = Data::new(Mutex::new(Repository::default()));
+
+        let app_repository = repository.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_repository.clone())
+                .default_service(web::to(handle))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("Failed to bind mock GitHub server");
+
+        let uri = format!("http://{}", server.addrs()[0]);
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        Self {
+            repository,
+            uri,
+            handle,
+        }
+    }
+
+    /// Base URI to point octocrab to
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn repository(&self) -> MutexGuard<'_, Repository> {
+        self.repository.lock().unwrap()
+    }
+
+    /// Commits text files to `branch`, see [`Repository::commit`]
+    pub fn commit_files(&self, branch: &str, files: &[(&str, &str)]) -> String {
+        let files: Vec<_> = files
+            .iter()
+            .map(|(path, content)| (*path, Some(*content), "100644"))
+            .collect();
+        self.repository().commit(branch, "Initial commit", &files)
+    }
+
+    /// Content of the file on `ref_` as UTF-8 text, see [`Repository::resolve`]
+    pub fn file(&self, ref_: &str, path: &str) -> Option<String> {
+        self.repository()
+            .file(ref_, path)
+            .map(|content| String::from_utf8(content).expect("File isn't valid UTF-8"))
+    }
+
+    /// Adds an open issue with the given number, i.e. the issue from a webhook payload
+    pub fn open_issue(&self, number: u64, title: &str, body: &str, author: &str, labels: &[&str]) {
+        self.repository().issues.insert(
+            number,
+            Issue {
+                number,
+                title: title.to_owned(),
+                body: body.to_owned(),
+                author: author.to_owned(),
+                labels: labels.iter().map(|label| label.to_string()).collect(),
+                state: String::from("open"),
+            },
+        );
+    }
+
+    /// See [`Repository::merge_pull`]
+    pub fn merge_pull(&self, number: u64) -> String {
+        self.repository()
+            .merge_pull(number)
+            .unwrap_or_else(|| panic!("Failed to merge pull request #{}", number))
+    }
+
+    /// Responds with `status` to the next `times` requests with `method` whose path ends with
+    /// `path`, i.e. `fail(Method::POST, "/releases", StatusCode::INTERNAL_SERVER_ERROR, 1)`
+    pub fn fail(&self, method: Method, path: &str, status: StatusCode, times: usize) {
+        self.repository().failures.push(Failure {
+            method,
+            path: path.to_owned(),
+            status,
+            remaining: times,
+        });
+    }
+
+    pub fn pulls(&self) -> Vec<PullRequest> {
+        self.repository().pulls.values().cloned().collect()
+    }
+
+    pub fn issue(&self, number: u64) -> Option<Issue> {
+        self.repository().issues.get(&number).cloned()
+    }
+
+    pub fn issues(&self) -> Vec<Issue> {
+        self.repository().issues.values().cloned().collect()
+    }
+
+    pub fn releases(&self) -> Vec<Release> {
+        self.repository().releases.clone()
+    }
+
+    /// Commit sha the tag points to
+    pub fn tag(&self, name: &str) -> Option<String> {
+        self.repository()
+            .refs
+            .get(&format!("tags/{}", name))
+            .cloned()
+    }
+
+    /// Comments `body` on issue `number`, as another user would
+    pub fn comment(&self, number: u64, body: &str) {
+        let mut repository = self.repository();
+        let comments = repository.comments.entry(number).or_default();
+        comments.push(body.to_owned());
+        let index = comments.len() - 1;
+        repository.comment_ids.push((number, index));
+    }
+
+    pub fn comments(&self, number: u64) -> Vec<String> {
+        self.repository()
+            .comments
+            .get(&number)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Reactions to the comment `id`, i.e. `+1`
+    pub fn reactions(&self, id: u64) -> Vec<String> {
+        self.repository()
+            .reactions
+            .get(&id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Every request received, i.e. `POST /repos/example_org/example_repo/git/blobs`
+    pub fn requests(&self) -> Vec<String> {
+        self.repository().requests.clone()
+    }
+
+    /// Number of requests received with `method` whose path ends with `path`
+    pub fn request_count(&self, method: &str, path: &str) -> usize {
+        self.repository()
+            .requests
+            .iter()
+            .filter_map(|request| request.split_once(' '))
+            .filter(|(m, p)| *m == method && p.ends_with(path))
+            .count()
+    }
+
+    /// Panics unless a comment on `number` contains `text`
+    #[track_caller]
+    pub fn assert_commented(&self, number: u64, text: &str) {
+        let comments = self.comments(number);
+        assert!(
+            comments.iter().any(|comment| comment.contains(text)),
+            "No comment on #{} contains {:?}. Comments: {:#?}",
+            number,
+            text,
+            comments
+        );
+    }
+
+    /// Panics if any request with `method` whose path ends with `path` was received
+    #[track_caller]
+    pub fn assert_not_requested(&self, method: &str, path: &str) {
+        assert_eq!(
+            self.request_count(method, path),
+            0,
+            "Unexpected {} {}. Requests: {:#?}",
+            method,
+            path,
+            self.requests()
+        );
+    }
+}
+
+impl Drop for MockGitHub {
+    fn drop(&mut self) {
+        // `stop` only signals the server, the returned future doesn't need to be awaited
+        #[allow(clippy::let_underscore_future)]
+        let _ = self.handle.stop(false);
+    }
+}
@@ -0,0 +1,600 @@
+//! An in-process fake of the subset of GitLab REST API (v4) used by release-butler, keeping a
+//! single project ([`PROJECT`], same as the payloads in `tests_payload`) in memory.
+
+use super::paginate;
+use actix_web::{
+    dev::ServerHandle,
+    http::{Method, StatusCode},
+    web::{self, Bytes, Data},
+    App, HttpRequest, HttpResponse, HttpServer,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, MutexGuard},
+};
+
+pub static PROJECT: &str = "example_org/example_repo";
+/// Username of the access token's user
+pub static USERNAME: &str = "release-butler-bot";
+
+pub type Files = BTreeMap<String, String>;
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub iid: u64,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub labels: Vec<String>,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeRequest {
+    pub iid: u64,
+    pub title: String,
+    pub source_branch: String,
+    pub target_branch: String,
+    pub description: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Project {
+    /// Snapshot of every file, keyed by commit sha
+    pub commits: HashMap<String, Files>,
+    pub branches: BTreeMap<String, String>,
+    pub tags: BTreeMap<String, String>,
+    pub issues: BTreeMap<u64, Issue>,
+    pub merge_requests: BTreeMap<u64, MergeRequest>,
+    pub issue_notes: BTreeMap<u64, Vec<String>>,
+    pub merge_request_notes: BTreeMap<u64, Vec<String>>,
+    pub releases: Vec<Release>,
+    /// Every request received, i.e. `POST /api/v4/projects/example_org%2Fexample_repo/issues`
+    pub requests: Vec<String>,
+    next_sha: u64,
+}
+
+type Response = (StatusCode, Value);
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, json!({ "message": "404 Not Found" }))
+}
+
+fn bad_request(message: &str) -> Response {
+    (StatusCode::BAD_REQUEST, json!({ "message": message }))
+}
+
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], segment.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) if u8::from_str_radix(hex, 16).is_ok() => {
+                decoded.push(u8::from_str_radix(hex, 16).unwrap());
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).expect("Path isn't valid UTF-8")
+}
+
+fn labels(value: &Value) -> Vec<String> {
+    value
+        .as_str()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|label| !label.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+impl Project {
+    fn next_sha(&mut self) -> String {
+        self.next_sha += 1;
+        format!("{:040x}", self.next_sha)
+    }
+
+    fn next_iid<T>(map: &BTreeMap<u64, T>) -> u64 {
+        map.keys().max().map_or(1, |iid| iid + 1)
+    }
+
+    /// Content of the file at `path` on `branch`
+    pub fn file(&self, branch: &str, path: &str) -> Option<String> {
+        let sha = self.branches.get(branch)?;
+        self.commits.get(sha)?.get(path).cloned()
+    }
+
+    /// Commits the files on top of `branch`, creating the branch if it doesn't exist
+    pub fn commit(&mut self, branch: &str, files: &[(&str, &str)]) -> String {
+        let mut snapshot = self
+            .branches
+            .get(branch)
+            .and_then(|sha| self.commits.get(sha))
+            .cloned()
+            .unwrap_or_default();
+        for (path, content) in files {
+            snapshot.insert(path.to_string(), content.to_string());
+        }
+
+        let sha = self.next_sha();
+        self.commits.insert(sha.clone(), snapshot);
+        self.branches.insert(branch.to_owned(), sha.clone());
+        sha
+    }
+
+    /// Merges the merge request by pointing the target branch to the source branch
+    pub fn merge(&mut self, iid: u64) -> Option<String> {
+        let merge_request = self.merge_requests.get_mut(&iid)?;
+        merge_request.state = String::from("merged");
+        let sha = self.branches.get(&merge_request.source_branch)?.clone();
+        self.branches
+            .insert(merge_request.target_branch.clone(), sha.clone());
+        Some(sha)
+    }
+
+    fn issue_json(issue: &Issue) -> Value {
+        json!({
+            "iid": issue.iid,
+            "title": issue.title,
+            "description": issue.description,
+            "labels": issue.labels,
+            "author": { "username": issue.author },
+            "state": issue.state,
+            "web_url": format!("https://gitlab.example.com/{}/-/issues/{}", PROJECT, issue.iid),
+        })
+    }
+
+    fn merge_request_json(merge_request: &MergeRequest) -> Value {
+        json!({
+            "iid": merge_request.iid,
+            "title": merge_request.title,
+            "source_branch": merge_request.source_branch,
+            "target_branch": merge_request.target_branch,
+            "description": merge_request.description,
+            "state": merge_request.state,
+            "web_url": format!(
+                "https://gitlab.example.com/{}/-/merge_requests/{}",
+                PROJECT, merge_request.iid
+            ),
+        })
+    }
+
+    fn route(
+        &mut self,
+        method: &Method,
+        rest: &[String],
+        query: &HashMap<String, String>,
+        body: &Value,
+    ) -> Response {
+        let rest: Vec<&str> = rest.iter().map(String::as_str).collect();
+
+        match (method.as_str(), rest.as_slice()) {
+            ("GET", ["repository", "files", path, "raw"]) => {
+                let ref_ = query.get("ref").cloned().unwrap_or_default();
+                match self.file(&ref_, path).or_else(|| {
+                    self.commits
+                        .get(&ref_)
+                        .and_then(|files| files.get(*path).cloned())
+                }) {
+                    // Raw content isn't JSON, it is unwrapped in `handle`
+                    Some(content) => (StatusCode::OK, Value::String(content)),
+                    None => not_found(),
+                }
+            }
+            ("GET", ["repository", "files", path]) => {
+                let ref_ = query.get("ref").cloned().unwrap_or_default();
+                let exists = self.file(&ref_, path).is_some()
+                    || self
+                        .commits
+                        .get(&ref_)
+                        .is_some_and(|files| files.contains_key(*path));
+                if exists {
+                    (StatusCode::OK, json!({ "file_path": path }))
+                } else {
+                    not_found()
+                }
+            }
+            ("GET", ["repository", "branches", branch]) => match self.branches.get(*branch) {
+                Some(sha) => (
+                    StatusCode::OK,
+                    json!({ "name": branch, "commit": { "id": sha } }),
+                ),
+                None => not_found(),
+            },
+            ("POST", ["repository", "commits"]) => {
+                let branch = body["branch"].as_str().unwrap_or_default().to_owned();
+                let parent = match body["start_sha"].as_str() {
+                    Some(sha) => sha.to_owned(),
+                    None => match self.branches.get(&branch) {
+                        Some(sha) => sha.clone(),
+                        None => {
+                            return bad_request(
+                                "You can only create or edit files when you are on a branch",
+                            )
+                        }
+                    },
+                };
+                if self.branches.contains_key(&branch)
+                    && body["start_sha"].is_string()
+                    && !body["force"].as_bool().unwrap_or_default()
+                {
+                    return bad_request("A branch called this already exists");
+                }
+                let Some(mut snapshot) = self.commits.get(&parent).cloned() else {
+                    return bad_request("Start sha doesn't exist");
+                };
+
+                for action in body["actions"].as_array().cloned().unwrap_or_default() {
+                    let path = action["file_path"].as_str().unwrap_or_default().to_owned();
+                    let content = action["content"].as_str().unwrap_or_default().to_owned();
+                    match action["action"].as_str() {
+                        Some("create") if snapshot.contains_key(&path) => {
+                            return bad_request("A file with this name already exists")
+                        }
+                        Some("update") | Some("delete") if !snapshot.contains_key(&path) => {
+                            return bad_request("A file with this name doesn't exist")
+                        }
+                        Some("create") | Some("update") => {
+                            snapshot.insert(path, content);
+                        }
+                        Some("delete") => {
+                            snapshot.remove(&path);
+                        }
+                        _ => return bad_request("Unknown action"),
+                    }
+                }
+
+                let sha = self.next_sha();
+                self.commits.insert(sha.clone(), snapshot);
+                self.branches.insert(branch, sha.clone());
+                (StatusCode::CREATED, json!({ "id": sha }))
+            }
+            ("GET", ["merge_requests"]) => {
+                let merge_requests: Vec<_> = self
+                    .merge_requests
+                    .values()
+                    .filter(|mr| query.get("state").is_none_or(|state| *state == mr.state))
+                    .filter(|mr| {
+                        query
+                            .get("source_branch")
+                            .is_none_or(|branch| *branch == mr.source_branch)
+                    })
+                    .filter(|mr| {
+                        query
+                            .get("target_branch")
+                            .is_none_or(|branch| *branch == mr.target_branch)
+                    })
+                    .map(Self::merge_request_json)
+                    .collect();
+                (StatusCode::OK, Value::Array(merge_requests))
+            }
+            ("POST", ["merge_requests"]) => {
+                let source_branch = body["source_branch"].as_str().unwrap_or_default();
+                if !self.branches.contains_key(source_branch) {
+                    return bad_request("Source branch does not exist");
+                }
+
+                let merge_request = MergeRequest {
+                    iid: Self::next_iid(&self.merge_requests),
+                    title: body["title"].as_str().unwrap_or_default().to_owned(),
+                    source_branch: source_branch.to_owned(),
+                    target_branch: body["target_branch"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_owned(),
+                    description: body["description"].as_str().unwrap_or_default().to_owned(),
+                    state: String::from("opened"),
+                };
+                let response = Self::merge_request_json(&merge_request);
+                self.merge_requests.insert(merge_request.iid, merge_request);
+                (StatusCode::CREATED, response)
+            }
+            ("POST", ["merge_requests", iid, "notes"]) | ("POST", ["issues", iid, "notes"]) => {
+                let Ok(iid) = iid.parse::<u64>() else {
+                    return not_found();
+                };
+                let notes = if rest[0] == "issues" {
+                    &mut self.issue_notes
+                } else {
+                    &mut self.merge_request_notes
+                };
+                let body = body["body"].as_str().unwrap_or_default().to_owned();
+                notes.entry(iid).or_default().push(body.clone());
+                (StatusCode::CREATED, json!({ "id": 1, "body": body }))
+            }
+            ("GET", ["issues", iid, "notes"]) => {
+                let Ok(iid) = iid.parse::<u64>() else {
+                    return not_found();
+                };
+                let notes = paginate(
+                    self.issue_notes
+                        .get(&iid)
+                        .into_iter()
+                        .flatten()
+                        .enumerate()
+                        .map(|(index, body)| json!({ "id": index + 1, "body": body })),
+                    query,
+                    "per_page",
+                    20,
+                );
+                (StatusCode::OK, Value::Array(notes))
+            }
+            ("GET", ["issues"]) => {
+                let wanted = query.get("labels").map(|value| labels(&json!(value)));
+                let issues: Vec<_> = self
+                    .issues
+                    .values()
+                    .filter(|issue| query.get("state").is_none_or(|state| *state == issue.state))
+                    .filter(|issue| {
+                        query
+                            .get("author_username")
+                            .is_none_or(|author| *author == issue.author)
+                    })
+                    .filter(|issue| {
+                        wanted.as_ref().is_none_or(|wanted| {
+                            wanted.iter().all(|label| issue.labels.contains(label))
+                        })
+                    })
+                    .map(Self::issue_json)
+                    .collect();
+                (StatusCode::OK, Value::Array(issues))
+            }
+            ("GET", ["issues", iid]) => match iid
+                .parse::<u64>()
+                .ok()
+                .and_then(|iid| self.issues.get(&iid))
+            {
+                Some(issue) => (StatusCode::OK, Self::issue_json(issue)),
+                None => not_found(),
+            },
+            ("POST", ["issues"]) => {
+                let issue = Issue {
+                    iid: Self::next_iid(&self.issues),
+                    title: body["title"].as_str().unwrap_or_default().to_owned(),
+                    description: body["description"].as_str().unwrap_or_default().to_owned(),
+                    author: USERNAME.to_owned(),
+                    labels: labels(&body["labels"]),
+                    state: String::from("opened"),
+                };
+                let response = Self::issue_json(&issue);
+                self.issues.insert(issue.iid, issue);
+                (StatusCode::CREATED, response)
+            }
+            ("PUT", ["issues", iid]) => {
+                let Some(issue) = iid
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|iid| self.issues.get_mut(&iid))
+                else {
+                    return not_found();
+                };
+                for label in labels(&body["add_labels"]) {
+                    if !issue.labels.contains(&label) {
+                        issue.labels.push(label);
+                    }
+                }
+                let removed = labels(&body["remove_labels"]);
+                issue.labels.retain(|label| !removed.contains(label));
+                if let Some(title) = body["title"].as_str() {
+                    issue.title = title.to_owned();
+                }
+                match body["state_event"].as_str() {
+                    Some("close") => issue.state = String::from("closed"),
+                    Some("reopen") => issue.state = String::from("opened"),
+                    _ => {}
+                }
+                (StatusCode::OK, Self::issue_json(issue))
+            }
+            ("POST", ["repository", "tags"]) => {
+                let tag_name = body["tag_name"].as_str().unwrap_or_default().to_owned();
+                let ref_ = body["ref"].as_str().unwrap_or_default().to_owned();
+                if self.tags.contains_key(&tag_name) {
+                    return bad_request("Tag already exists");
+                }
+                if !self.commits.contains_key(&ref_) {
+                    return bad_request("Target ref is invalid");
+                }
+                self.tags.insert(tag_name.clone(), ref_.clone());
+                (
+                    StatusCode::CREATED,
+                    json!({ "name": tag_name, "target": ref_ }),
+                )
+            }
+            ("POST", ["releases"]) => {
+                let tag_name = body["tag_name"].as_str().unwrap_or_default().to_owned();
+                if !self.tags.contains_key(&tag_name) {
+                    return bad_request("Tag does not exist");
+                }
+                let release = Release {
+                    name: body["name"].as_str().unwrap_or(&tag_name).to_owned(),
+                    description: body["description"].as_str().unwrap_or_default().to_owned(),
+                    tag_name,
+                };
+                let response = json!({
+                    "tag_name": release.tag_name,
+                    "name": release.name,
+                    "description": release.description,
+                    "_links": {
+                        "self": format!(
+                            "https://gitlab.example.com/{}/-/releases/{}",
+                            PROJECT, release.tag_name
+                        )
+                    }
+                });
+                self.releases.push(release);
+                (StatusCode::CREATED, response)
+            }
+            _ => not_found(),
+        }
+    }
+}
+
+async fn handle(req: HttpRequest, body: Bytes, project: Data<Mutex<Project>>) -> HttpResponse {
+    let mut project = project.lock().unwrap();
+    let path = req.uri().path().to_owned();
+    project.requests.push(format!("{} {}", req.method(), path));
+
+    if req.headers().get("PRIVATE-TOKEN").is_none() {
+        return HttpResponse::Unauthorized().json(json!({ "message": "401 Unauthorized" }));
+    }
+
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .map(|query| query.into_inner())
+        .unwrap_or_default();
+    let body = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+    // Segments are decoded after splitting, as project and file paths are encoded as one segment
+    let segments: Vec<String> = path.trim_matches('/').split('/').map(decode).collect();
+
+    let (status, response) = match segments.as_slice() {
+        [api, v4, projects, project_path, rest @ ..]
+            if api == "api" && v4 == "v4" && projects == "projects" && project_path == PROJECT =>
+        {
+            project.route(req.method(), rest, &query, &body)
+        }
+        _ => not_found(),
+    };
+
+    let raw = segments.last().is_some_and(|segment| segment == "raw");
+    match response {
+        Value::String(content) if raw && status.is_success() => {
+            HttpResponse::build(status).body(content)
+        }
+        response => HttpResponse::build(status).json(response),
+    }
+}
+
+/// A running fake GitLab API server, stopped when dropped
+pub struct MockGitLab {
+    project: Data<Mutex<Project>>,
+    uri: String,
+    handle: ServerHandle,
+}
+
+impl MockGitLab {
+    /// Starts the server on a random port. Must be called from within an actix runtime, i.e.
+    /// `#[actix_web::test]`
+    pub async fn start() -> Self {
+        let project = Data::new(Mutex::new(Project::default()));
+
+        let app_project = project.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_project.clone())
+                .default_service(web::to(handle))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("Failed to bind mock GitLab server");
+
+        let uri = format!("http://{}", server.addrs()[0]);
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        Self {
+            project,
+            uri,
+            handle,
+        }
+    }
+
+    /// URL of the GitLab instance, without `/api/v4`
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn project(&self) -> MutexGuard<'_, Project> {
+        self.project.lock().unwrap()
+    }
+
+    /// See [`Project::commit`]
+    pub fn commit_files(&self, branch: &str, files: &[(&str, &str)]) -> String {
+        self.project().commit(branch, files)
+    }
+
+    pub fn file(&self, branch: &str, path: &str) -> Option<String> {
+        self.project().file(branch, path)
+    }
+
+    pub fn open_issue(
+        &self,
+        iid: u64,
+        title: &str,
+        description: &str,
+        author: &str,
+        labels: &[&str],
+    ) {
+        self.project().issues.insert(
+            iid,
+            Issue {
+                iid,
+                title: title.to_owned(),
+                description: description.to_owned(),
+                author: author.to_owned(),
+                labels: labels.iter().map(|label| label.to_string()).collect(),
+                state: String::from("opened"),
+            },
+        );
+    }
+
+    /// See [`Project::merge`]
+    pub fn merge(&self, iid: u64) -> String {
+        self.project()
+            .merge(iid)
+            .unwrap_or_else(|| panic!("Failed to merge merge request !{}", iid))
+    }
+
+    pub fn merge_requests(&self) -> Vec<MergeRequest> {
+        self.project().merge_requests.values().cloned().collect()
+    }
+
+    pub fn issue(&self, iid: u64) -> Option<Issue> {
+        self.project().issues.get(&iid).cloned()
+    }
+
+    pub fn issue_notes(&self, iid: u64) -> Vec<String> {
+        self.project()
+            .issue_notes
+            .get(&iid)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn merge_request_notes(&self, iid: u64) -> Vec<String> {
+        self.project()
+            .merge_request_notes
+            .get(&iid)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn tag(&self, name: &str) -> Option<String> {
+        self.project().tags.get(name).cloned()
+    }
+
+    pub fn releases(&self) -> Vec<Release> {
+        self.project().releases.clone()
+    }
+}
+
+impl Drop for MockGitLab {
+    fn drop(&mut self) {
+        // `stop` only signals the server, the returned future doesn't need to be awaited
+        #[allow(clippy::let_underscore_future)]
+        let _ = self.handle.stop(false);
+    }
+}
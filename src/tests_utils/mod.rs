@@ -0,0 +1,402 @@
+//! This is a tests utility crate, which contains all the necessary type defination and
+//! functions required for running tests.
+//! See `tests` directory for more information
+
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+
+use super::webhook::parse_event;
+pub use crate::webhook::WebhookError;
+use crate::{
+    client::RetryPolicy,
+    common::generate_hmac_sha256_hex,
+    forge::rest::RestClient,
+    gitea::{parse_gitea_event, GiteaState},
+    gitlab::{parse_gitlab_event, GitLabState},
+    State,
+};
+use actix_http::{header::TryIntoHeaderPair, Request};
+pub use actix_web::http::StatusCode;
+pub use actix_web::test;
+use actix_web::{dev::ServiceResponse, error::HttpError, web::Data, App};
+
+pub static WEBHOOK_ENDPOINT: &str = "/github/webhook/";
+pub static DEFAULT_HMAC_KEY: &str = "abc";
+pub static GITLAB_WEBHOOK_ENDPOINT: &str = "/gitlab/webhook/";
+pub static DEFAULT_GITLAB_TOKEN: &str = "gitlab-secret";
+pub static GITEA_WEBHOOK_ENDPOINT: &str = "/gitea/webhook/";
+pub static DEFAULT_GITEA_SECRET: &str = "gitea-secret";
+
+/// Items of the page requested by the `page` and `size_param` query parameters of a mock API
+fn paginate<T>(
+    items: impl IntoIterator<Item = T>,
+    query: &std::collections::HashMap<String, String>,
+    size_param: &str,
+    default_size: usize,
+) -> Vec<T> {
+    let size = query
+        .get(size_param)
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(default_size);
+    let page = query
+        .get("page")
+        .and_then(|page| page.parse().ok())
+        .unwrap_or(1usize);
+    items
+        .into_iter()
+        .skip((page.max(1) - 1) * size)
+        .take(size)
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct HeaderConversionError;
+
+impl From<HeaderConversionError> for HttpError {
+    fn from(_val: HeaderConversionError) -> HttpError {
+        todo!()
+    }
+}
+
+pub struct TestHeader<S: AsRef<str>>(pub &'static str, pub S);
+
+impl<S> TryIntoHeaderPair for TestHeader<S>
+where
+    S: AsRef<str>,
+{
+    type Error = HeaderConversionError;
+
+    fn try_into_pair(
+        self,
+    ) -> Result<
+        (
+            actix_http::header::HeaderName,
+            actix_http::header::HeaderValue,
+        ),
+        Self::Error,
+    > {
+        let header_name = actix_http::header::HeaderName::from_static(self.0);
+        let header_value = actix_http::header::HeaderValue::from_str(self.1.as_ref());
+
+        match header_value {
+            Ok(value) => Ok((header_name, value)),
+            Err(_) => Err(HeaderConversionError),
+        }
+    }
+}
+
+pub async fn test_endpoint(req: Request) -> ServiceResponse {
+    test_endpoint_with_state(req, State::new_basic(String::from(DEFAULT_HMAC_KEY))).await
+}
+
+/// Same as [`test_endpoint`], but GitHub API calls are served by `github`
+pub async fn test_endpoint_with_github(
+    req: Request,
+    github: &github::MockGitHub,
+) -> ServiceResponse {
+    test_endpoint_with_state(
+        req,
+        State::new_with_base_uri(String::from(DEFAULT_HMAC_KEY), github.uri()),
+    )
+    .await
+}
+
+/// Same as [`test_endpoint`], but GitLab webhooks are accepted and served by `gitlab`
+pub async fn test_endpoint_with_gitlab(
+    req: Request,
+    gitlab: &gitlab::MockGitLab,
+) -> ServiceResponse {
+    let state = State::new_basic(String::from(DEFAULT_HMAC_KEY)).with_gitlab(GitLabState {
+        gl: RestClient::gitlab(gitlab.uri(), "glpat-mock", RetryPolicy::none()),
+        webhook_token: String::from(DEFAULT_GITLAB_TOKEN),
+        username: String::from(gitlab::USERNAME),
+    });
+    test_endpoint_with_state(req, state).await
+}
+
+/// Same as [`test_endpoint`], but Gitea webhooks are accepted and served by `gitea`
+pub async fn test_endpoint_with_gitea(req: Request, gitea: &gitea::MockGitea) -> ServiceResponse {
+    let state = State::new_basic(String::from(DEFAULT_HMAC_KEY)).with_gitea(GiteaState {
+        client: RestClient::gitea(gitea.uri(), "gitea-mock", RetryPolicy::none()),
+        webhook_secret: String::from(DEFAULT_GITEA_SECRET),
+        username: String::from(gitea::USERNAME),
+    });
+    test_endpoint_with_state(req, state).await
+}
+
+/// Same as [`test_endpoint`], with the given `state`
+pub async fn test_endpoint_with_state(req: Request, state: State) -> ServiceResponse {
+    let app = test::init_service(
+        App::new()
+            .service(parse_event)
+            .service(parse_gitlab_event)
+            .service(parse_gitea_event)
+            .app_data(Data::new(state)),
+    )
+    .await;
+    test::call_service(&app, req).await
+}
+
+/// Builds a signed webhook request for `event`
+pub fn webhook_request(event: &str, body: Vec<u8>) -> Request {
+    let signature = payload_template::signature(&body);
+
+    test::TestRequest::post()
+        .uri(WEBHOOK_ENDPOINT)
+        .insert_header(TestHeader("x-hub-signature-256", signature))
+        .insert_header(TestHeader("x-github-event", event))
+        .set_payload(body)
+        .to_request()
+}
+
+/// Builds a GitLab webhook request for `event` (i.e. `Issue Hook`) with [`DEFAULT_GITLAB_TOKEN`]
+pub fn gitlab_webhook_request(event: &str, body: Vec<u8>) -> Request {
+    test::TestRequest::post()
+        .uri(GITLAB_WEBHOOK_ENDPOINT)
+        .insert_header(TestHeader("x-gitlab-token", DEFAULT_GITLAB_TOKEN))
+        .insert_header(TestHeader("x-gitlab-event", event))
+        .set_payload(body)
+        .to_request()
+}
+
+/// Builds a Gitea webhook request for `event` (i.e. `issues`) signed with [`DEFAULT_GITEA_SECRET`]
+pub fn gitea_webhook_request(event: &str, body: Vec<u8>) -> Request {
+    let signature =
+        generate_hmac_sha256_hex(&body, DEFAULT_GITEA_SECRET.as_bytes()).unwrap_or_default();
+
+    test::TestRequest::post()
+        .uri(GITEA_WEBHOOK_ENDPOINT)
+        .insert_header(TestHeader("x-gitea-signature", signature))
+        .insert_header(TestHeader("x-gitea-event", event))
+        .set_payload(body)
+        .to_request()
+}
+
+pub mod payload_template {
+    use std::sync::LazyLock;
+
+    use super::DEFAULT_HMAC_KEY;
+    use crate::common::generate_hmac_sha256_hex;
+
+    /// `X-Hub-Signature-256` header value for `body` signed with [`DEFAULT_HMAC_KEY`]
+    pub fn signature(body: &[u8]) -> String {
+        let mut sha_hex = String::from("sha256=");
+        sha_hex.push_str(
+            &generate_hmac_sha256_hex(body, DEFAULT_HMAC_KEY.as_bytes()).unwrap_or_default(),
+        );
+        sha_hex
+    }
+
+    pub static GITHUB_ISSUES_LABELED: &[u8] =
+        include_str!("../../tests_payload/github_issues_labeled.json").as_bytes();
+
+    /// `issues` event with `labeled` action, on issue #7 with the `release-butler` label
+    pub fn issues_labeled(title: &str, body: &str, author: &str) -> Vec<u8> {
+        let mut payload: serde_json::Value =
+            serde_json::from_slice(GITHUB_ISSUES_LABELED).expect("Invalid payload");
+        payload["issue"]["title"] = title.into();
+        payload["issue"]["body"] = body.into();
+        payload["issue"]["user"]["login"] = author.into();
+        serde_json::to_vec(&payload).expect("Failed to serialize payload")
+    }
+
+    pub static GITHUB_ISSUE_COMMENT: &[u8] =
+        include_str!("../../tests_payload/github_issue_comment_created.json").as_bytes();
+
+    /// Id of the comment in [`GITHUB_ISSUE_COMMENT`]
+    pub static COMMENT_ID: u64 = 2714938561;
+
+    /// `issue_comment` event with `created` action, commenting `comment` as `author` on the
+    /// release issue #7 titled `title`
+    pub fn issue_comment(title: &str, comment: &str, author: &str) -> Vec<u8> {
+        let mut payload: serde_json::Value =
+            serde_json::from_slice(GITHUB_ISSUE_COMMENT).expect("Invalid payload");
+        payload["issue"]["title"] = title.into();
+        payload["comment"]["body"] = comment.into();
+        payload["comment"]["user"]["login"] = author.into();
+        serde_json::to_vec(&payload).expect("Failed to serialize payload")
+    }
+
+    /// Same as [`issue_comment`], but on the pull request `number`
+    pub fn pull_request_comment(
+        number: u64,
+        title: &str,
+        body: &str,
+        comment: &str,
+        author: &str,
+    ) -> Vec<u8> {
+        let mut payload: serde_json::Value =
+            serde_json::from_slice(&issue_comment(title, comment, author))
+                .expect("Invalid payload");
+        let issue = &mut payload["issue"];
+        issue["number"] = number.into();
+        issue["body"] = body.into();
+        issue["labels"] = serde_json::json!([]);
+        issue["pull_request"] = serde_json::json!({
+            "url": format!("https://api.github.com/repos/example_org/example_repo/pulls/{}", number),
+            "html_url": format!("https://github.com/example_org/example_repo/pull/{}", number),
+            "diff_url": format!("https://github.com/example_org/example_repo/pull/{}.diff", number),
+            "patch_url": format!("https://github.com/example_org/example_repo/pull/{}.patch", number),
+        });
+        serde_json::to_vec(&payload).expect("Failed to serialize payload")
+    }
+
+    pub static GITLAB_ISSUE_LABELED: &[u8] =
+        include_str!("../../tests_payload/gitlab_issue_labeled.json").as_bytes();
+
+    /// `Issue Hook` with `update` action adding the `release-butler` label to issue #7
+    pub fn gitlab_issue_labeled(title: &str, description: &str) -> Vec<u8> {
+        let mut payload: serde_json::Value =
+            serde_json::from_slice(GITLAB_ISSUE_LABELED).expect("Invalid payload");
+        payload["object_attributes"]["title"] = title.into();
+        payload["object_attributes"]["description"] = description.into();
+        serde_json::to_vec(&payload).expect("Failed to serialize payload")
+    }
+
+    pub static GITLAB_MR_MERGED: &[u8] =
+        include_str!("../../tests_payload/gitlab_mr_merged.json").as_bytes();
+
+    /// `Merge Request Hook` with `action`, i.e. `merge` or `close`
+    pub fn gitlab_merge_request(
+        iid: u64,
+        title: &str,
+        description: &str,
+        source_branch: &str,
+        action: &str,
+        merge_commit_sha: Option<&str>,
+    ) -> Vec<u8> {
+        let mut payload: serde_json::Value =
+            serde_json::from_slice(GITLAB_MR_MERGED).expect("Invalid payload");
+        let attributes = &mut payload["object_attributes"];
+        attributes["iid"] = iid.into();
+        attributes["title"] = title.into();
+        attributes["description"] = description.into();
+        attributes["source_branch"] = source_branch.into();
+        attributes["action"] = action.into();
+        attributes["merge_commit_sha"] = merge_commit_sha.into();
+        serde_json::to_vec(&payload).expect("Failed to serialize payload")
+    }
+
+    pub static GITEA_ISSUE_LABELED: &[u8] =
+        include_str!("../../tests_payload/gitea_issue_labeled.json").as_bytes();
+
+    /// `issues` event with `label_updated` action on issue #7, labeled `release-butler`
+    pub fn gitea_issue_labeled(title: &str, body: &str, author: &str) -> Vec<u8> {
+        let mut payload: serde_json::Value =
+            serde_json::from_slice(GITEA_ISSUE_LABELED).expect("Invalid payload");
+        payload["issue"]["title"] = title.into();
+        payload["issue"]["body"] = body.into();
+        payload["issue"]["user"]["login"] = author.into();
+        serde_json::to_vec(&payload).expect("Failed to serialize payload")
+    }
+
+    pub static GITEA_PULL_CLOSED: &[u8] =
+        include_str!("../../tests_payload/gitea_pull_closed.json").as_bytes();
+
+    /// `pull_request` event with `closed` action, merged if `merge_commit_sha` is set
+    pub fn gitea_pull_closed(
+        number: u64,
+        title: &str,
+        body: &str,
+        head: &str,
+        merge_commit_sha: Option<&str>,
+    ) -> Vec<u8> {
+        let mut payload: serde_json::Value =
+            serde_json::from_slice(GITEA_PULL_CLOSED).expect("Invalid payload");
+        let pull = &mut payload["pull_request"];
+        pull["number"] = number.into();
+        pull["title"] = title.into();
+        pull["body"] = body.into();
+        pull["head"]["ref"] = head.into();
+        pull["head"]["label"] = head.into();
+        pull["merged"] = merge_commit_sha.is_some().into();
+        pull["merge_commit_sha"] = merge_commit_sha.into();
+        serde_json::to_vec(&payload).expect("Failed to serialize payload")
+    }
+
+    pub static GITHUB_PR_CLOSED: &[u8] =
+        include_str!("../../tests_payload/github_pr_closed.json").as_bytes();
+
+    /// `pull_request` event with `closed` action. The pull request is merged if
+    /// `merge_commit_sha` is provided.
+    pub fn pull_request_closed(
+        number: u64,
+        title: &str,
+        body: &str,
+        head: &str,
+        merge_commit_sha: Option<&str>,
+    ) -> Vec<u8> {
+        let mut payload: serde_json::Value =
+            serde_json::from_slice(GITHUB_PR_CLOSED).expect("Invalid payload");
+        payload["number"] = number.into();
+        let pull = &mut payload["pull_request"];
+        pull["number"] = number.into();
+        pull["title"] = title.into();
+        pull["body"] = body.into();
+        pull["head"]["ref"] = head.into();
+        pull["head"]["label"] = format!("{}:{}", super::github::OWNER, head).into();
+        pull["merged"] = merge_commit_sha.is_some().into();
+        pull["merge_commit_sha"] = merge_commit_sha.into();
+        if merge_commit_sha.is_none() {
+            pull["merged_at"] = serde_json::Value::Null;
+        }
+        serde_json::to_vec(&payload).expect("Failed to serialize payload")
+    }
+
+    pub static GITHUB_PR_OPENED: &[u8] =
+        include_str!("../../tests_payload/github_pr_opened.json").as_bytes();
+
+    pub static GITHUB_PR_OPENED_HEX: LazyLock<String> = LazyLock::new(|| {
+        let mut sha_hex = String::from("sha256=");
+        sha_hex.push_str(
+            &generate_hmac_sha256_hex(GITHUB_PR_OPENED, DEFAULT_HMAC_KEY.as_bytes())
+                .unwrap_or_default(),
+        );
+        sha_hex
+    });
+
+    pub static GITHUB_PUSH: &[u8] = include_str!("../../tests_payload/github_push.json").as_bytes();
+
+    pub static GITHUB_PUSH_HEX: LazyLock<String> = LazyLock::new(|| {
+        let mut sha_hex = String::from("sha256=");
+        sha_hex.push_str(
+            &generate_hmac_sha256_hex(GITHUB_PUSH, DEFAULT_HMAC_KEY.as_bytes()).unwrap_or_default(),
+        );
+        sha_hex
+    });
+
+    pub static GITHUB_FORK: &[u8] = include_str!("../../tests_payload/github_fork.json").as_bytes();
+
+    pub static GITHUB_FORK_HEX: LazyLock<String> = LazyLock::new(|| {
+        let mut sha_hex = String::from("sha256=");
+        sha_hex.push_str(
+            &generate_hmac_sha256_hex(GITHUB_FORK, DEFAULT_HMAC_KEY.as_bytes()).unwrap_or_default(),
+        );
+        sha_hex
+    });
+
+    pub static GITHUB_ISSUES: &[u8] =
+        include_str!("../../tests_payload/github_issues.json").as_bytes();
+
+    pub static GITHUB_ISSUES_HEX: LazyLock<String> = LazyLock::new(|| {
+        let mut sha_hex = String::from("sha256=");
+        sha_hex.push_str(
+            &generate_hmac_sha256_hex(GITHUB_ISSUES, DEFAULT_HMAC_KEY.as_bytes())
+                .unwrap_or_default(),
+        );
+        sha_hex
+    });
+
+    pub static GITHUB_INVALID_ISSUES_PAYLOAD: &[u8] =
+        include_str!("../../tests_payload/github_invalid_issues_payload.json").as_bytes();
+
+    pub static GITHUB_INVALID_ISSUES_PAYLOAD_HEX: LazyLock<String> = LazyLock::new(|| {
+        let mut sha_hex = String::from("sha256=");
+        sha_hex.push_str(
+            &generate_hmac_sha256_hex(GITHUB_INVALID_ISSUES_PAYLOAD, DEFAULT_HMAC_KEY.as_bytes())
+                .unwrap_or_default(),
+        );
+        sha_hex
+    });
+}
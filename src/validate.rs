@@ -0,0 +1,461 @@
+//! Offline checks of the configuration and the files edited on release, i.e. for a pre-commit
+//! hook. Reports every problem found instead of stopping at the first one.
+
+use crate::{
+    config::{Config, Package, PackageManager, CONFIG_VERSION},
+    release, DEFAULT_CONFIG_FILE_PATH,
+};
+use chrono::NaiveDate;
+use std::{collections::HashSet, fmt};
+
+/// A problem found in a file of the repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Path of the file, relative to repository root
+    pub path: String,
+    /// 1-based line of the problem, if known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(path: &str, line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_owned(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path, line, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Validates [`DEFAULT_CONFIG_FILE_PATH`] and the manifests and changelogs of every package.
+/// `read_file` returns the content of a file relative to repository root, `Ok(None)` if it
+/// doesn't exist.
+pub fn validate<F>(read_file: F) -> Vec<Diagnostic>
+where
+    F: Fn(&str) -> Result<Option<String>, String>,
+{
+    let mut diagnostics = Vec::new();
+
+    let content = match read_file(DEFAULT_CONFIG_FILE_PATH) {
+        Ok(Some(content)) => content,
+        Ok(None) => {
+            diagnostics.push(Diagnostic::new(
+                DEFAULT_CONFIG_FILE_PATH,
+                None,
+                "The file doesn't exist",
+            ));
+            return diagnostics;
+        }
+        Err(msg) => {
+            diagnostics.push(Diagnostic::new(DEFAULT_CONFIG_FILE_PATH, None, msg));
+            return diagnostics;
+        }
+    };
+
+    let config = match validate_config(&content) {
+        Ok(config) => config,
+        Err(diagnostic) => {
+            diagnostics.push(diagnostic);
+            return diagnostics;
+        }
+    };
+
+    if config.version != CONFIG_VERSION {
+        diagnostics.push(Diagnostic::new(
+            DEFAULT_CONFIG_FILE_PATH,
+            None,
+            format!(
+                "Configuration version {} is not supported, expected {}",
+                config.version, CONFIG_VERSION
+            ),
+        ));
+    }
+    if config.packages.is_empty() {
+        diagnostics.push(Diagnostic::new(
+            DEFAULT_CONFIG_FILE_PATH,
+            None,
+            "No package is configured in `packages`",
+        ));
+    }
+
+    for pattern in &config.branch_patterns {
+        if let Err(err) = semver::VersionReq::parse(&pattern.versions) {
+            diagnostics.push(Diagnostic::new(
+                DEFAULT_CONFIG_FILE_PATH,
+                None,
+                format!(
+                    "The requirement `{}` of the branch `{}` is invalid: {}",
+                    pattern.versions, pattern.branch, err
+                ),
+            ));
+        }
+    }
+
+    let mut grouped = HashSet::new();
+    for (name, group) in &config.groups {
+        for member in &group.packages {
+            if !config.packages.contains_key(member) {
+                diagnostics.push(Diagnostic::new(
+                    DEFAULT_CONFIG_FILE_PATH,
+                    None,
+                    format!(
+                        "The member `{}` of the group `{}` is not in `packages`",
+                        member, name
+                    ),
+                ));
+            } else if !grouped.insert(member.as_str()) {
+                diagnostics.push(Diagnostic::new(
+                    DEFAULT_CONFIG_FILE_PATH,
+                    None,
+                    format!(
+                        "The package `{}` is a member of more than one group",
+                        member
+                    ),
+                ));
+            }
+        }
+    }
+
+    let mut changelogs = HashSet::new();
+    let group_changelogs = config
+        .groups
+        .values()
+        .flat_map(|group| [&group.changelog_file, &group.pre_release_changelog_file]);
+    for path in group_changelogs {
+        if path.is_empty() || !changelogs.insert(path.as_str()) {
+            continue;
+        }
+        match read_file(path) {
+            Ok(Some(content)) => diagnostics.extend(validate_changelog(path, &content)),
+            Ok(None) => {}
+            Err(msg) => diagnostics.push(Diagnostic::new(path, None, msg)),
+        }
+    }
+    for (name, package) in &config.packages {
+        let manifest_path = release::manifest_path(package);
+        match read_file(&manifest_path) {
+            Ok(Some(content)) => {
+                diagnostics.extend(validate_manifest(package, &manifest_path, &content))
+            }
+            Ok(None) => diagnostics.push(Diagnostic::new(
+                &manifest_path,
+                None,
+                format!("The manifest of the package `{}` doesn't exist", name),
+            )),
+            Err(msg) => diagnostics.push(Diagnostic::new(&manifest_path, None, msg)),
+        }
+
+        // A missing changelog is created on the first release
+        for path in [&package.changelog_file, &package.pre_release_changelog_file] {
+            if path.is_empty() || !changelogs.insert(path.as_str()) {
+                continue;
+            }
+            match read_file(path) {
+                Ok(Some(content)) => diagnostics.extend(validate_changelog(path, &content)),
+                Ok(None) => {}
+                Err(msg) => diagnostics.push(Diagnostic::new(path, None, msg)),
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Parses the configuration the same way the app does
+pub fn validate_config(content: &str) -> Result<Config, Diagnostic> {
+    toml::from_str::<Config>(content).map_err(|err| {
+        Diagnostic::new(
+            DEFAULT_CONFIG_FILE_PATH,
+            err.span().map(|span| line_of(content, span.start)),
+            err.message(),
+        )
+    })
+}
+
+/// Checks that the manifest has a version at the key edited by [`release::bump_manifest`]
+pub fn validate_manifest(package: &Package, path: &str, content: &str) -> Vec<Diagnostic> {
+    // Unlike `DocumentMut`, `ImDocument` keeps the spans used for the line numbers
+    let doc = match toml_edit::ImDocument::parse(content) {
+        Ok(doc) => doc,
+        Err(err) => {
+            return vec![Diagnostic::new(
+                path,
+                err.span().map(|span| line_of(content, span.start)),
+                err.message(),
+            )]
+        }
+    };
+
+    let (key, version) = match package.package_manager {
+        PackageManager::Cargo => ("package.version", doc.get("package")),
+        PackageManager::CargoWorkspace => (
+            "workspace.package.version",
+            doc.get("workspace").and_then(|item| item.get("package")),
+        ),
+    };
+    let version = version.and_then(|item| item.get("version"));
+
+    let message = match version {
+        None => format!("`{}` is missing", key),
+        Some(item) => match item.as_str() {
+            Some(version) => match semver::Version::parse(version) {
+                Ok(_) => return Vec::new(),
+                Err(err) => format!("`{}` is not a valid semver version. {}", key, err),
+            },
+            None if item.get("workspace").is_some() => format!(
+                "`{}` is inherited from the workspace, configure the workspace root with `package_manager = \"cargo_workspace\"` instead",
+                key
+            ),
+            None => format!("`{}` must be a string", key),
+        },
+    };
+    let line = version
+        .and_then(|item| item.span())
+        .map(|span| line_of(content, span.start));
+
+    vec![Diagnostic::new(path, line, message)]
+}
+
+/// Checks the `## [<VERSION>] - <YYYY-MM-DD>` release headers, the ones
+/// [`release::update_changelog`] inserts new releases above
+pub fn validate_changelog(path: &str, content: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut versions = HashSet::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let Some(header) = line.strip_prefix("## [") else {
+            continue;
+        };
+        let line_number = Some(index + 1);
+
+        let Some((version, rest)) = header.split_once(']') else {
+            diagnostics.push(Diagnostic::new(path, line_number, "Unclosed `[` in header"));
+            continue;
+        };
+        if version.eq_ignore_ascii_case("unreleased") {
+            continue;
+        }
+
+        let version = version.strip_prefix('v').unwrap_or(version);
+        match semver::Version::parse(version) {
+            Ok(version) => {
+                if !versions.insert(version.clone()) {
+                    diagnostics.push(Diagnostic::new(
+                        path,
+                        line_number,
+                        format!("Version {} is listed more than once", version),
+                    ));
+                }
+            }
+            Err(err) => diagnostics.push(Diagnostic::new(
+                path,
+                line_number,
+                format!("`{}` is not a valid semver version. {}", version, err),
+            )),
+        }
+
+        let date = rest.trim();
+        if date.is_empty() {
+            continue;
+        }
+        let date = date.strip_prefix("- ").unwrap_or(date);
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            diagnostics.push(Diagnostic::new(
+                path,
+                line_number,
+                format!("`{}` is not a `YYYY-MM-DD` date", date),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn package(package_manager: PackageManager) -> Package {
+        Package {
+            path: String::from("crates/foo"),
+            changelog_file: String::from("crates/foo/CHANGELOG.md"),
+            pre_release_changelog_file: String::new(),
+            create_gh_release: false,
+            package_manager,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut files = HashMap::from([
+            (
+                DEFAULT_CONFIG_FILE_PATH,
+                "version = 1\ndefault_branch = \"main\"\n\n[issues_meta_data]\nallowed_authors = []\n\n\
+                [packages.foo]\npath = \"crates/foo\"\nchangelog_file = \"crates/foo/CHANGELOG.md\"\npackage_manager = \"cargo\"\n\n\
+                [packages.bar]\npath = \"crates/bar\"\nchangelog_file = \"crates/bar/CHANGELOG.md\"\npackage_manager = \"cargo\"\n",
+            ),
+            (
+                "crates/foo/Cargo.toml",
+                "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                "crates/foo/CHANGELOG.md",
+                "# Changelog\n\n## [0.1.0] - 2025-01-01\n- Initial\n",
+            ),
+        ]);
+        let read = |files: &HashMap<&str, &str>| {
+            validate(|path| Ok(files.get(path).map(|content| content.to_string())))
+        };
+
+        assert_eq!(
+            read(&files),
+            [Diagnostic::new(
+                "crates/bar/Cargo.toml",
+                None,
+                "The manifest of the package `bar` doesn't exist"
+            )]
+        );
+
+        files.insert(
+            "crates/bar/Cargo.toml",
+            "[package]\nname = \"bar\"\nversion = \"0.1.0\"\n",
+        );
+        assert_eq!(read(&files), []);
+
+        let config = format!(
+            "{}\n[groups.core]\npackages = [\"foo\", \"baz\"]\n\n[groups.all]\npackages = [\"foo\", \"bar\"]\n\n\
+            [[branch_patterns]]\nversions = \"1.x\"\nbranch = \"release/1.x\"\n\n\
+            [[branch_patterns]]\nversions = \"one\"\nbranch = \"release/one\"\n",
+            files[DEFAULT_CONFIG_FILE_PATH]
+        );
+        files.insert(DEFAULT_CONFIG_FILE_PATH, &config);
+        assert_eq!(
+            read(&files),
+            [
+                Diagnostic::new(
+                    DEFAULT_CONFIG_FILE_PATH,
+                    None,
+                    "The requirement `one` of the branch `release/one` is invalid: unexpected \
+                    character 'o' while parsing major version number"
+                ),
+                Diagnostic::new(
+                    DEFAULT_CONFIG_FILE_PATH,
+                    None,
+                    "The package `foo` is a member of more than one group"
+                ),
+                Diagnostic::new(
+                    DEFAULT_CONFIG_FILE_PATH,
+                    None,
+                    "The member `baz` of the group `core` is not in `packages`"
+                ),
+            ]
+        );
+
+        files.remove(DEFAULT_CONFIG_FILE_PATH);
+        assert_eq!(
+            read(&files),
+            [Diagnostic::new(
+                DEFAULT_CONFIG_FILE_PATH,
+                None,
+                "The file doesn't exist"
+            )]
+        );
+
+        files.insert(DEFAULT_CONFIG_FILE_PATH, "version = 1\n\n[packages.foo]\n");
+        let diagnostics = read(&files);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, DEFAULT_CONFIG_FILE_PATH);
+    }
+
+    #[test]
+    fn test_validate_manifest() {
+        let cargo = package(PackageManager::Cargo);
+        let workspace = package(PackageManager::CargoWorkspace);
+
+        assert_eq!(
+            validate_manifest(&cargo, "Cargo.toml", "[package]\nversion = \"1.2.3\"\n"),
+            []
+        );
+        assert_eq!(
+            validate_manifest(
+                &workspace,
+                "Cargo.toml",
+                "[workspace.package]\nversion = \"1.2.3\"\n"
+            ),
+            []
+        );
+        assert_eq!(
+            validate_manifest(&workspace, "Cargo.toml", "[package]\nversion = \"1.2.3\"\n"),
+            [Diagnostic::new(
+                "Cargo.toml",
+                None,
+                "`workspace.package.version` is missing"
+            )]
+        );
+        assert_eq!(
+            validate_manifest(
+                &cargo,
+                "Cargo.toml",
+                "[package]\nname = \"foo\"\nversion.workspace = true\n"
+            )[0]
+            .message,
+            "`package.version` is inherited from the workspace, configure the workspace root with `package_manager = \"cargo_workspace\"` instead"
+        );
+        assert_eq!(
+            validate_manifest(&cargo, "Cargo.toml", "[package]\nversion = \"1.2\"\n")[0].line,
+            Some(2)
+        );
+        assert_eq!(
+            validate_manifest(&cargo, "Cargo.toml", "[package]\nversion = 1\n")[0].message,
+            "`package.version` must be a string"
+        );
+        assert_eq!(
+            validate_manifest(&cargo, "Cargo.toml", "[package\n")[0].line,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_validate_changelog() {
+        assert_eq!(
+            validate_changelog(
+                "CHANGELOG.md",
+                "# Changelog\n\n## [Unreleased]\n\n## [1.0.0] - 2025-01-01\n\n## [v0.1.0]\n"
+            ),
+            []
+        );
+
+        let diagnostics = validate_changelog(
+            "CHANGELOG.md",
+            "# Changelog\n## [1.0] - 2025-01-01\n## [0.2.0] - 01/01/2025\n## [0.1.0\n## [0.2.0]\n",
+        );
+        let lines: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.line.unwrap())
+            .collect();
+        assert_eq!(lines, [2, 3, 4, 5]);
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "CHANGELOG.md:3: `01/01/2025` is not a `YYYY-MM-DD` date"
+        );
+        assert_eq!(
+            diagnostics[3].message,
+            "Version 0.2.0 is listed more than once"
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use crate::{
-    common::generate_hmac_sha256_hex,
+    client::{ClientError, GitHubClient},
+    common::{verify_hmac_sha1_hex, verify_hmac_sha256_hex},
     config::{Config, CONFIG_VERSION},
     events::{self, Handler},
+    forge::Forge,
     State, DEFAULT_CONFIG_FILE_PATH,
 };
 use actix_web::{
@@ -11,14 +13,8 @@ use actix_web::{
     HttpRequest, HttpResponse, ResponseError,
 };
 use derive_more::{Display, Error};
-use octocrab::{
-    models::{
-        repos::ContentItems,
-        webhook_events::{EventInstallation, WebhookEvent, WebhookEventType},
-    },
-    Octocrab,
-};
-use tracing::{error, info};
+use octocrab::models::webhook_events::{EventInstallation, WebhookEvent, WebhookEventType};
+use tracing::{error, info, warn};
 
 // The Webhook Payload size limit is 25MB
 pub static WEBHOOK_SIZE_LIMIT: usize = 25_000_000; // 25 * 1000 * 1000
@@ -33,6 +29,10 @@ pub enum WebhookError {
     MalformatedBody { msg: String },
     #[display("Signature in X-Hub-Signature-256 and computed from payload didn't matched")]
     InvalidSignature,
+    #[display("Token in X-Gitlab-Token didn't match the configured webhook token")]
+    InvalidToken,
+    #[display("Signature in X-Gitea-Signature and computed from payload didn't matched")]
+    InvalidGiteaSignature,
     #[display("Failed to serialize the payload")]
     SerializationFailed,
     #[display("Got an unsupported webhook event")]
@@ -60,6 +60,8 @@ impl ResponseError for WebhookError {
             WebhookError::LargeBodySize => StatusCode::PAYLOAD_TOO_LARGE,
             WebhookError::MalformatedBody { .. } => StatusCode::BAD_REQUEST,
             WebhookError::InvalidSignature => StatusCode::UNAUTHORIZED,
+            WebhookError::InvalidToken => StatusCode::UNAUTHORIZED,
+            WebhookError::InvalidGiteaSignature => StatusCode::UNAUTHORIZED,
             WebhookError::SerializationFailed => StatusCode::INTERNAL_SERVER_ERROR,
             WebhookError::UnsupportedEvent => StatusCode::NOT_IMPLEMENTED,
             WebhookError::InvalidEncodingOrLength => StatusCode::BAD_REQUEST,
@@ -75,16 +77,23 @@ pub async fn parse_event(
 ) -> Result<HttpResponse, WebhookError> {
     let headers = req.headers();
 
-    let github_signature_256 = match headers.get("X-Hub-Signature-256") {
-        Some(value) => {
-            let value = value.to_str().unwrap_or("");
-            value
-        }
-        None => {
-            error!("The request on `/github/webhook` didn't contained `X-Hub-Signature-256` header. Request -> {:?}", req);
-            ""
-        }
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("")
+    };
+
+    let github_signature_256 = header("X-Hub-Signature-256");
+    // Only sent alone by GitHub Enterprise Server versions older than 3.1
+    let github_signature_1 = if state.legacy_sha1_signature {
+        header("X-Hub-Signature")
+    } else {
+        ""
     };
+    if github_signature_256.is_empty() && github_signature_1.is_empty() {
+        error!("The request on `/github/webhook` didn't contained `X-Hub-Signature-256` header. Request -> {:?}", req);
+    }
 
     let github_event = match headers.get("X-GitHub-Event") {
         Some(value) => {
@@ -97,7 +106,8 @@ pub async fn parse_event(
         }
     };
 
-    if github_signature_256.is_empty() || github_event.is_empty() {
+    if (github_signature_256.is_empty() && github_signature_1.is_empty()) || github_event.is_empty()
+    {
         error!("Either the header `X-Hub-Signature-256` or `X-GitHub-Event` was empty or one of them failed to parse");
         return Err(WebhookError::RequiredHeadersNotAvailable);
     }
@@ -122,19 +132,41 @@ pub async fn parse_event(
         });
     }
 
-    let Some(signature_256) = generate_hmac_sha256_hex(&body, state.webhook_secret.as_bytes())
-    else {
-        error!("hmm! InvalidLengthError Insufficient output buffer length.");
-        return Err(WebhookError::InvalidEncodingOrLength);
+    let verify = |secret: &String| {
+        let key = secret.as_bytes();
+        if !github_signature_256.is_empty() {
+            github_signature_256
+                .strip_prefix("sha256=")
+                .is_some_and(|signature| verify_hmac_sha256_hex(&body, key, signature))
+        } else {
+            github_signature_1
+                .strip_prefix("sha1=")
+                .is_some_and(|signature| verify_hmac_sha1_hex(&body, key, signature))
+        }
     };
-    let signature_256 = format!("sha256={}", signature_256);
 
-    if github_signature_256.as_bytes() != signature_256.as_bytes() {
-        error!("Invalid Signature. This is not a valid webhook event send by GitHub. Our signature = {}, header signature = {}", signature_256, github_signature_256);
+    let Some(secret_index) = state.webhook_secrets.iter().position(verify) else {
+        error!("Invalid Signature. This is not a valid webhook event send by GitHub.");
         return Err(WebhookError::InvalidSignature);
+    };
+    if secret_index > 0 {
+        warn!(
+            "The webhook was signed with the previous secret #{}. Retire it once deliveries are only signed with the current secret.",
+            secret_index
+        );
     }
+
     // Great, go ahead now it's verified that this is send from GitHub
-    let Ok(event) = WebhookEvent::try_from_header_and_body(github_event, &body) else {
+    dispatch_event(github_event, &body, &state).await
+}
+
+/// Handles a verified GitHub event, `github_event` being the value of the `X-GitHub-Event` header
+pub async fn dispatch_event(
+    github_event: &str,
+    body: &[u8],
+    state: &State,
+) -> Result<HttpResponse, WebhookError> {
+    let Ok(event) = WebhookEvent::try_from_header_and_body(github_event, body) else {
         error!("Failed to serialize webhook payload. body => {:?}", body);
         return Err(WebhookError::SerializationFailed);
     };
@@ -158,12 +190,17 @@ pub async fn parse_event(
 
     match &event.kind {
         WebhookEventType::Issues => {
-            events::issues::IssuesHandler::new(repository, &event, &state)
+            events::issues::IssuesHandler::new(repository, &event, state)
                 .execute()
                 .await
         }
         WebhookEventType::PullRequest => {
-            events::pulls::PullsHandler::new(repository, &event, &state)
+            events::pulls::PullsHandler::new(repository, &event, state)
+                .execute()
+                .await
+        }
+        WebhookEventType::IssueComment => {
+            events::comments::CommentsHandler::new(repository, &event, state)
                 .execute()
                 .await
         }
@@ -174,10 +211,15 @@ pub async fn parse_event(
     }
 }
 
+/// Client authenticated for the repository of `event`, i.e. as the installation of the app
 pub fn generate_gh_from_event(
     event: &WebhookEvent,
-    old_gh: &Octocrab,
-) -> Result<Octocrab, WebhookError> {
+    state: &State,
+) -> Result<GitHubClient, WebhookError> {
+    if state.token_auth {
+        return Ok(state.gh.clone());
+    }
+
     // Use installation provided by the event
     let Some(event_installation) = &event.installation else {
         error!("The payload didn't contained installation information. Ignoring Event...");
@@ -192,7 +234,7 @@ pub fn generate_gh_from_event(
         EventInstallation::Minimal(minimal) => minimal.id,
     };
 
-    old_gh.installation(installation_id).map_err(|err| {
+    state.gh.installation(installation_id).map_err(|err| {
         error!(
             "Failed to generate new Octocrab with installation id provided by event. Error: {}",
             err
@@ -203,149 +245,151 @@ pub fn generate_gh_from_event(
     })
 }
 
+/// Fetches and parses [`DEFAULT_CONFIG_FILE_PATH`]. Returns `Ok(None)` if the configuration is
+/// missing or malformed, in which case an issue is opened highlighting the problem. `Err` is only
+/// returned when the forge API kept failing after retries.
 pub async fn get_config(
-    repo_owner: &str,
-    repo: &str,
-    state: &State,
-    gh: &Octocrab,
-) -> Option<Config> {
-    let repos_handle = gh.repos(repo_owner, repo);
-
-    let config_files = match repos_handle
-        .get_content()
-        .path(DEFAULT_CONFIG_FILE_PATH)
-        .send()
-        .await
-    {
-        Ok(content) => Some(content),
-        Err(err) => match err {
-            octocrab::Error::GitHub { source, .. } => {
-                if source.status_code.as_u16() == 404 {
-                    Some(ContentItems { items: Vec::new() })
-                } else {
-                    None
-                }
-            }
-            _ => {
-                error!(
-                    "Failed to get reponse from github api when trying to find `{}`. Error: {:?}",
-                    DEFAULT_CONFIG_FILE_PATH, err
-                );
-                None
-            }
-        },
-    }?;
-
-    let mut config_file = String::new();
-    for file in config_files.items {
-        if file.path.as_str() != DEFAULT_CONFIG_FILE_PATH {
-            continue;
-        }
-
-        if let Some(file_content) = file.decoded_content() {
-            config_file.push_str(&file_content);
+    forge: &dyn Forge,
+    app_username: &str,
+) -> Result<Option<Config>, ClientError> {
+    let config_file = match forge.read_file(DEFAULT_CONFIG_FILE_PATH).await {
+        Ok(file) => file.unwrap_or_default(),
+        Err(err) => {
+            error!(
+                "Failed to get reponse from forge api when trying to find `{}`. Error: {:?}",
+                DEFAULT_CONFIG_FILE_PATH, err
+            );
+            return Err(err);
         }
-    }
+    };
 
     let Ok(config) = toml::from_str::<Config>(&config_file) else {
         error!("Failed to parse configuration file. Posting error as an issue if not exists...");
 
-        let issues = gh.issues(repo_owner, repo);
-
-        // Check if issue already exists or not with label `$CONFIG_ISSUE_LABEL`
-        let Ok(issues_list) = issues
-            .list()
-            .creator(&state.app_username)
-            .labels(&[String::from(crate::CONFIG_ISSUE_LABEL)])
-            .send()
-            .await
-        else {
-            error!(
-                "Failed to get information if issue with label {} by user {} was created or not.",
-                crate::CONFIG_ISSUE_LABEL,
-                state.app_username
-            );
-            return None;
-        };
-
-        if issues_list.items.is_empty() {
-            info!(
-                "There is no issue created with label {} by user {}, creating one...",
-                crate::CONFIG_ISSUE_LABEL,
-                state.app_username
-            );
-
-            match issues
-                .create(format!("`{}` file is malformatted", DEFAULT_CONFIG_FILE_PATH))
-                .body(
-                    format!(
-                        "Hi there, I just a webhook event for this repository and I failed to get information from `{}`.\n\
-                        It is possible that this file doesn't exists or there is an issue with it. Please fix it as it will allow me to work smoothly.\n\n\
-                        For more information refer https://github.com/rs-workspace/release-butler\nSample File https://github.com/rs-workspace/release-butler/blob/main/repository.template.toml",
-                        DEFAULT_CONFIG_FILE_PATH
-                    )
-                )
-                .labels(vec![String::from(crate::CONFIG_ISSUE_LABEL)])
-                .send().await {
-                    Ok(_) => {
-                        info!("Created an issue highlighting problem with {} in {}/{}", DEFAULT_CONFIG_FILE_PATH, repo_owner, repo);
-                    },
-                    Err(err) => {
-                        error!("Failed to create issue. Error: {:?}", err)
-                    }
-                }
-        }
-
-        return None;
+        create_config_issue(
+            forge,
+            app_username,
+            &format!("`{}` file is malformatted", DEFAULT_CONFIG_FILE_PATH),
+            &format!(
+                "Hi there, I just a webhook event for this repository and I failed to get information from `{}`.\n\
+                It is possible that this file doesn't exists or there is an issue with it. Please fix it as it will allow me to work smoothly.\n\n\
+                For more information refer https://github.com/rs-workspace/release-butler\nSample File https://github.com/rs-workspace/release-butler/blob/main/repository.template.toml",
+                DEFAULT_CONFIG_FILE_PATH
+            ),
+        )
+        .await?;
+
+        return Ok(None);
     };
 
     if config.version != CONFIG_VERSION {
         error!("Unsupport Configuration Version");
 
-        let issues = gh.issues(repo_owner, repo);
+        create_config_issue(
+            forge,
+            app_username,
+            "Outdated release-butler configuration file",
+            &format!(
+                "The current version of `.github/release-butler.toml` is {}. It is not the latest version supported by me. Please look at [release-butler repository](https://github.com/rs-workspace/release-butler) for more information for upgrading.",
+                config.version
+            ),
+        )
+        .await?;
+    }
+
+    // Releases matching no valid pattern fall back to the default branch, so the problem is
+    // reported without stopping
+    let invalid_patterns: Vec<_> = config
+        .branch_patterns
+        .iter()
+        .filter_map(|pattern| {
+            semver::VersionReq::parse(&pattern.versions)
+                .err()
+                .map(|err| (pattern, err))
+        })
+        .collect();
+    if !invalid_patterns.is_empty() {
+        error!("Invalid `branch_patterns` in the configuration");
+
+        // The requirements are part of the title, so that new problems get their own issue
+        let requirements: Vec<_> = invalid_patterns
+            .iter()
+            .map(|(pattern, _)| format!("`{}`", pattern.versions))
+            .collect();
+        let invalid_patterns: Vec<_> = invalid_patterns
+            .iter()
+            .map(|(pattern, err)| {
+                format!("- `{}` (`{}`): {}", pattern.versions, pattern.branch, err)
+            })
+            .collect();
+        create_config_issue(
+            forge,
+            app_username,
+            &format!(
+                "Invalid `branch_patterns` in the release-butler configuration: {}",
+                requirements.join(", ")
+            ),
+            &format!(
+                "The following version requirements of `branch_patterns` in `{}` are invalid, releases are never based on their branch:\n{}",
+                DEFAULT_CONFIG_FILE_PATH,
+                invalid_patterns.join("\n")
+            ),
+        )
+        .await?;
+    }
 
-        // Check if issue already exists or not with label `$CONFIG_ISSUE_LABEL`
-        let Ok(issues_list) = issues
-            .list()
-            .creator(&state.app_username)
-            .labels(&[String::from(crate::CONFIG_ISSUE_LABEL)])
-            .send()
-            .await
-        else {
+    Ok(Some(config))
+}
+
+/// Creates an issue with label [`crate::CONFIG_ISSUE_LABEL`], unless the app already opened one
+/// with the same title.
+async fn create_config_issue(
+    forge: &dyn Forge,
+    app_username: &str,
+    title: &str,
+    body: &str,
+) -> Result<(), ClientError> {
+    // Check if issue already exists or not with label `$CONFIG_ISSUE_LABEL`
+    let issues_list = match forge
+        .find_issues(app_username, crate::CONFIG_ISSUE_LABEL)
+        .await
+    {
+        Ok(issues_list) => issues_list,
+        Err(err) => {
             error!(
-                "Failed to get information if issue with label {} by user {} was created or not.",
+                "Failed to get information if issue with label {} by user {} was created or not. Error: {}",
                 crate::CONFIG_ISSUE_LABEL,
-                state.app_username
+                app_username,
+                err
             );
-            return None;
-        };
+            return Err(err);
+        }
+    };
 
-        if issues_list.items.is_empty() {
-            info!(
-                "There is no issue created with label {} by user {}, creating one...",
-                crate::CONFIG_ISSUE_LABEL,
-                state.app_username
-            );
+    if issues_list.iter().all(|issue| issue.title != title) {
+        info!(
+            "There is no issue `{}` created with label {} by user {}, creating one...",
+            title,
+            crate::CONFIG_ISSUE_LABEL,
+            app_username
+        );
 
-            match issues
-                .create("Outdated release-butler configuration file")
-                .body(
-                    format!(
-                        "The current version of `.github/release-butler.toml` is {}. It is not the latest version supported by me. Please look at [release-butler repository](https://github.com/rs-workspace/release-butler) for more information for upgrading.",
-                        config.version
-                    )
-                )
-                .labels(vec![String::from(crate::CONFIG_ISSUE_LABEL)])
-                .send().await {
-                    Ok(_) => {
-                        info!("Created an issue highlighting problem with {} in {}/{}", DEFAULT_CONFIG_FILE_PATH, repo_owner, repo);
-                    },
-                    Err(err) => {
-                        error!("Failed to create issue. Error: {:?}", err)
-                    }
-                }
+        match forge
+            .create_issue(title, body, &[crate::CONFIG_ISSUE_LABEL])
+            .await
+        {
+            Ok(_) => {
+                info!(
+                    "Created an issue highlighting problem with {}",
+                    DEFAULT_CONFIG_FILE_PATH
+                );
+            }
+            Err(err) => {
+                error!("Failed to create issue. Error: {:?}", err);
+                return Err(err);
+            }
         }
     }
 
-    Some(config)
+    Ok(())
 }
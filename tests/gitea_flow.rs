@@ -0,0 +1,238 @@
+use release_butler::tests_utils::{
+    gitea::{MockGitea, PullRequest, USERNAME},
+    *,
+};
+
+static CONFIG: &str = r#"
+version = 1
+default_branch = "main"
+
+[issues_meta_data]
+allowed_authors = ["jdoe"]
+
+[packages.example]
+changelog_file = "CHANGELOG.md"
+create_gh_release = true
+package_manager = "cargo"
+"#;
+
+fn repository(gitea: &MockGitea) {
+    gitea.commit_files(
+        "main",
+        &[
+            (".github/release-butler.toml", CONFIG),
+            (
+                "Cargo.toml",
+                "[package]\nname = \"example\"\nversion = \"1.2.2\"\n",
+            ),
+            ("CHANGELOG.md", "# Changelog\n"),
+        ],
+    );
+}
+
+async fn label_issue(gitea: &MockGitea, title: &str, body: &str, author: &str) -> StatusCode {
+    gitea.open_issue(7, title, body, author, &["release-butler"]);
+    let req = gitea_webhook_request(
+        "issues",
+        payload_template::gitea_issue_labeled(title, body, author),
+    );
+    test_endpoint_with_gitea(req, gitea).await.status()
+}
+
+#[actix_web::test]
+async fn test_missing_signature() {
+    let gitea = MockGitea::start().await;
+    let req = test::TestRequest::post()
+        .uri(GITEA_WEBHOOK_ENDPOINT)
+        .insert_header(TestHeader("x-gitea-event", "issues"))
+        .set_payload(payload_template::gitea_issue_labeled("v1.2.3", "", "jdoe"))
+        .to_request();
+
+    let resp = test_endpoint_with_gitea(req, &gitea).await;
+    assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+}
+
+#[actix_web::test]
+async fn test_invalid_signature() {
+    let gitea = MockGitea::start().await;
+    let body = payload_template::gitea_issue_labeled("v1.2.3", "", "jdoe");
+    // Signed with the GitHub secret instead of the Gitea one
+    let signature = payload_template::signature(&body);
+    let req = test::TestRequest::post()
+        .uri(GITEA_WEBHOOK_ENDPOINT)
+        .insert_header(TestHeader(
+            "x-gitea-signature",
+            signature.trim_start_matches("sha256="),
+        ))
+        .insert_header(TestHeader("x-gitea-event", "issues"))
+        .set_payload(body)
+        .to_request();
+
+    let resp = test_endpoint_with_gitea(req, &gitea).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert!(gitea.repository().requests.is_empty());
+}
+
+#[actix_web::test]
+async fn test_gitea_not_configured() {
+    let req = gitea_webhook_request(
+        "issues",
+        payload_template::gitea_issue_labeled("v1.2.3", "", "jdoe"),
+    );
+    let resp = test_endpoint(req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_IMPLEMENTED);
+}
+
+#[actix_web::test]
+async fn test_release_flow() {
+    let gitea = MockGitea::start().await;
+    repository(&gitea);
+
+    let status = label_issue(&gitea, "v1.2.3", "### Fixed\n- Bug Y", "jdoe").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let branch = "release-butler/@1.2.3";
+    assert_eq!(
+        gitea.file(branch, "Cargo.toml").unwrap(),
+        "[package]\nname = \"example\"\nversion = \"1.2.3\"\n"
+    );
+    assert!(gitea
+        .file(branch, "CHANGELOG.md")
+        .unwrap()
+        .contains("## [1.2.3] - 2025-03-12\n### Fixed\n- Bug Y"));
+
+    let pulls = gitea.pulls();
+    assert_eq!(pulls.len(), 1);
+    let pull = &pulls[0];
+    assert_eq!(pull.title, "RELEASE @v1.2.3");
+    assert_eq!(pull.head, branch);
+    assert_eq!(pull.base, "main");
+    assert!(pull.body.starts_with("Fixes #7"));
+
+    // Editing the issue updates the existing pull request. The branch can't be moved, so the
+    // commit goes on top of the previous one, without the new changes of `main`.
+    gitea.commit_files("main", &[("README.md", "# Example\n")]);
+    let status = label_issue(&gitea, "v1.2.3", "### Fixed\n- Bug Z", "jdoe").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(gitea.pulls().len(), 1);
+    assert_eq!(gitea.pulls()[0].state, "open");
+    assert!(gitea
+        .file(branch, "CHANGELOG.md")
+        .unwrap()
+        .contains("- Bug Z"));
+    assert!(gitea.file(branch, "README.md").is_none());
+
+    let merge_commit_sha = gitea.merge(pull.number);
+    let req = gitea_webhook_request(
+        "pull_request",
+        payload_template::gitea_pull_closed(
+            pull.number,
+            &pull.title,
+            &pull.body,
+            &pull.head,
+            Some(&merge_commit_sha),
+        ),
+    );
+    let resp = test_endpoint_with_gitea(req, &gitea).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(gitea.tag("v1.2.3"), Some(merge_commit_sha));
+    let releases = gitea.releases();
+    assert_eq!(releases.len(), 1);
+    assert_eq!(releases[0].tag_name, "v1.2.3");
+    assert_eq!(releases[0].body, "### Fixed\n- Bug Z");
+    assert!(!releases[0].prerelease);
+
+    assert_eq!(
+        gitea.comments(7),
+        ["Released in [`v1.2.3`](https://gitea.example.com/example_org/example_repo/releases/tag/v1.2.3)"]
+    );
+    assert_eq!(gitea.issue(7).unwrap().state, "closed");
+    assert!(gitea.comments(pull.number).is_empty());
+}
+
+#[actix_web::test]
+async fn test_unauthorized_author() {
+    let gitea = MockGitea::start().await;
+    repository(&gitea);
+
+    let status = label_issue(&gitea, "v1.2.3", "", "mallory").await;
+    assert_eq!(status, StatusCode::OK);
+
+    assert!(gitea.pulls().is_empty());
+    assert!(gitea.issue_labels(7).is_empty());
+    assert_eq!(gitea.comments(7).len(), 1);
+}
+
+#[actix_web::test]
+async fn test_closed_without_merge() {
+    let gitea = MockGitea::start().await;
+    repository(&gitea);
+    gitea.repository().pulls.insert(
+        8,
+        PullRequest {
+            number: 8,
+            title: String::from("RELEASE @v1.2.3"),
+            head: String::from("release-butler/@1.2.3"),
+            base: String::from("main"),
+            body: String::from("Fixes #7"),
+            state: String::from("closed"),
+        },
+    );
+
+    let req = gitea_webhook_request(
+        "pull_request",
+        payload_template::gitea_pull_closed(
+            8,
+            "RELEASE @v1.2.3",
+            "Fixes #7",
+            "release-butler/@1.2.3",
+            None,
+        ),
+    );
+    let resp = test_endpoint_with_gitea(req, &gitea).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert!(gitea.tag("v1.2.3").is_none());
+    let comments = gitea.comments(8);
+    assert_eq!(comments.len(), 1);
+    assert!(comments[0].contains("You should remove the label `release-butler`"));
+
+    // Closed by the app account
+    let mut payload: serde_json::Value =
+        serde_json::from_slice(&payload_template::gitea_pull_closed(
+            8,
+            "RELEASE @v1.2.3",
+            "Fixes #7",
+            "release-butler/@1.2.3",
+            None,
+        ))
+        .unwrap();
+    payload["sender"]["login"] = USERNAME.into();
+    let req = gitea_webhook_request("pull_request", serde_json::to_vec(&payload).unwrap());
+    let resp = test_endpoint_with_gitea(req, &gitea).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(gitea.comments(8).len(), 1);
+}
+
+#[actix_web::test]
+async fn test_malformed_config_opens_issue() {
+    let gitea = MockGitea::start().await;
+    gitea.commit_files("main", &[(".github/release-butler.toml", "version = ")]);
+
+    for _ in 0..2 {
+        label_issue(&gitea, "v1.2.3", "", "jdoe").await;
+    }
+
+    let repository = gitea.repository();
+    let config_issues: Vec<_> = repository
+        .issues
+        .values()
+        .filter(|issue| issue.author == USERNAME)
+        .collect();
+    assert_eq!(config_issues.len(), 1);
+    assert_eq!(
+        repository.issue_labels(config_issues[0].number),
+        ["release-butler-config-error"]
+    );
+}
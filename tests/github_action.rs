@@ -0,0 +1,127 @@
+use release_butler::{
+    action::{handle_event, ActionError},
+    tests_utils::{
+        github::{MockGitHub, APP_USERNAME},
+        *,
+    },
+    State,
+};
+
+static CONFIG: &str = r#"
+version = 1
+default_branch = "main"
+
+[issues_meta_data]
+allowed_authors = ["jdoe"]
+
+[packages.example]
+changelog_file = "CHANGELOG.md"
+create_gh_release = true
+package_manager = "cargo"
+"#;
+
+fn token_state(github: &MockGitHub) -> State {
+    State::from_token(
+        String::from("ghp_workflow_token"),
+        String::from(APP_USERNAME),
+        github.uri(),
+    )
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn test_action_release_flow() {
+    let github = MockGitHub::start().await;
+    github.commit_files(
+        "main",
+        &[
+            (".github/release-butler.toml", CONFIG),
+            (
+                "Cargo.toml",
+                "[package]\nname = \"example\"\nversion = \"1.2.2\"\n",
+            ),
+            ("CHANGELOG.md", "# Changelog\n"),
+        ],
+    );
+    github.open_issue(
+        7,
+        "v1.2.3",
+        "### Fixed\n- Bug Y",
+        "jdoe",
+        &["release-butler"],
+    );
+    let state = token_state(&github);
+
+    handle_event(
+        &state,
+        "issues",
+        &payload_template::issues_labeled("v1.2.3", "### Fixed\n- Bug Y", "jdoe"),
+    )
+    .await
+    .unwrap();
+
+    let pulls = github.pulls();
+    assert_eq!(pulls.len(), 1);
+    let pull = pulls[0].clone();
+
+    // Runs in the context of the base repository, but the payload is the same
+    let merge_commit_sha = github.merge_pull(pull.number);
+    handle_event(
+        &state,
+        "pull_request_target",
+        &payload_template::pull_request_closed(
+            pull.number,
+            &pull.title,
+            &pull.body,
+            &pull.head,
+            Some(&merge_commit_sha),
+        ),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(github.tag("v1.2.3"), Some(merge_commit_sha));
+    assert_eq!(github.releases().len(), 1);
+    github.assert_not_requested("POST", "/access_tokens");
+}
+
+#[actix_web::test]
+async fn test_action_ignores_unsupported_events() {
+    let github = MockGitHub::start().await;
+    let state = token_state(&github);
+
+    handle_event(&state, "fork", payload_template::GITHUB_FORK)
+        .await
+        .unwrap();
+    assert!(github.requests().is_empty());
+}
+
+#[actix_web::test]
+async fn test_action_fails_without_config() {
+    let github = MockGitHub::start().await;
+    github.open_issue(7, "v1.2.3", "", "jdoe", &["release-butler"]);
+    let state = token_state(&github);
+
+    let result = handle_event(
+        &state,
+        "issues",
+        &payload_template::issues_labeled("v1.2.3", "", "jdoe"),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(ActionError::Failed { status: 500, .. })
+    ));
+
+    // The configuration issue is opened like with the app
+    assert_eq!(github.issues().len(), 2);
+}
+
+#[actix_web::test]
+async fn test_action_malformed_payload() {
+    let github = MockGitHub::start().await;
+    let state = token_state(&github);
+
+    let result = handle_event(&state, "issues", b"{}").await;
+    assert!(matches!(result, Err(ActionError::Webhook { .. })));
+}
@@ -0,0 +1,252 @@
+use release_butler::tests_utils::{
+    gitlab::{MockGitLab, USERNAME},
+    *,
+};
+
+static CONFIG: &str = r#"
+version = 1
+default_branch = "main"
+
+[issues_meta_data]
+allowed_authors = ["jdoe"]
+
+[packages.example]
+changelog_file = "CHANGELOG.md"
+create_gh_release = true
+package_manager = "cargo"
+"#;
+
+fn project(gitlab: &MockGitLab) {
+    gitlab.commit_files(
+        "main",
+        &[
+            (".github/release-butler.toml", CONFIG),
+            (
+                "Cargo.toml",
+                "[package]\nname = \"example\"\nversion = \"1.2.2\"\n",
+            ),
+        ],
+    );
+}
+
+#[actix_web::test]
+async fn test_missing_token() {
+    let gitlab = MockGitLab::start().await;
+    let req = test::TestRequest::post()
+        .uri(GITLAB_WEBHOOK_ENDPOINT)
+        .insert_header(TestHeader("x-gitlab-event", "Issue Hook"))
+        .set_payload(payload_template::gitlab_issue_labeled("v1.2.3", ""))
+        .to_request();
+
+    let resp = test_endpoint_with_gitlab(req, &gitlab).await;
+    assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+}
+
+#[actix_web::test]
+async fn test_invalid_token() {
+    let gitlab = MockGitLab::start().await;
+    let req = test::TestRequest::post()
+        .uri(GITLAB_WEBHOOK_ENDPOINT)
+        .insert_header(TestHeader("x-gitlab-token", "wrong-secret"))
+        .insert_header(TestHeader("x-gitlab-event", "Issue Hook"))
+        .set_payload(payload_template::gitlab_issue_labeled("v1.2.3", ""))
+        .to_request();
+
+    let resp = test_endpoint_with_gitlab(req, &gitlab).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert!(gitlab.project().requests.is_empty());
+}
+
+#[actix_web::test]
+async fn test_gitlab_not_configured() {
+    let req = gitlab_webhook_request(
+        "Issue Hook",
+        payload_template::gitlab_issue_labeled("v1.2.3", ""),
+    );
+    let resp = test_endpoint(req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_IMPLEMENTED);
+}
+
+#[actix_web::test]
+async fn test_release_flow() {
+    let gitlab = MockGitLab::start().await;
+    project(&gitlab);
+    gitlab.open_issue(
+        7,
+        "v1.2.3",
+        "### Fixed\n- Bug Y",
+        "jdoe",
+        &["release-butler"],
+    );
+
+    let req = gitlab_webhook_request(
+        "Issue Hook",
+        payload_template::gitlab_issue_labeled("v1.2.3", "### Fixed\n- Bug Y"),
+    );
+    let resp = test_endpoint_with_gitlab(req, &gitlab).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let branch = "release-butler/@1.2.3";
+    assert_eq!(
+        gitlab.file(branch, "Cargo.toml").unwrap(),
+        "[package]\nname = \"example\"\nversion = \"1.2.3\"\n"
+    );
+    assert_eq!(
+        gitlab.file(branch, "CHANGELOG.md").unwrap(),
+        "# Changelog\n\n## [1.2.3] - 2025-03-12\n### Fixed\n- Bug Y"
+    );
+
+    let merge_requests = gitlab.merge_requests();
+    assert_eq!(merge_requests.len(), 1);
+    let merge_request = &merge_requests[0];
+    assert_eq!(merge_request.title, "RELEASE @v1.2.3");
+    assert_eq!(merge_request.source_branch, branch);
+    assert_eq!(merge_request.target_branch, "main");
+    assert!(merge_request.description.starts_with("Fixes #7"));
+
+    let merge_commit_sha = gitlab.merge(merge_request.iid);
+    let req = gitlab_webhook_request(
+        "Merge Request Hook",
+        payload_template::gitlab_merge_request(
+            merge_request.iid,
+            &merge_request.title,
+            &merge_request.description,
+            &merge_request.source_branch,
+            "merge",
+            Some(&merge_commit_sha),
+        ),
+    );
+    let resp = test_endpoint_with_gitlab(req, &gitlab).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(gitlab.tag("v1.2.3"), Some(merge_commit_sha));
+    let releases = gitlab.releases();
+    assert_eq!(releases.len(), 1);
+    assert_eq!(releases[0].tag_name, "v1.2.3");
+    assert_eq!(releases[0].description, "### Fixed\n- Bug Y");
+
+    assert_eq!(
+        gitlab.issue_notes(7),
+        ["Released in [`v1.2.3`](https://gitlab.example.com/example_org/example_repo/-/releases/v1.2.3)"]
+    );
+    assert_eq!(gitlab.issue(7).unwrap().state, "closed");
+    assert!(gitlab.merge_request_notes(merge_request.iid).is_empty());
+}
+
+#[actix_web::test]
+async fn test_squash_fast_forward_merge() {
+    let gitlab = MockGitLab::start().await;
+    project(&gitlab);
+    gitlab.open_issue(7, "v1.2.3", "", "jdoe", &["release-butler"]);
+
+    let req = gitlab_webhook_request(
+        "Issue Hook",
+        payload_template::gitlab_issue_labeled("v1.2.3", ""),
+    );
+    test_endpoint_with_gitlab(req, &gitlab).await;
+    let merge_request = gitlab.merge_requests().remove(0);
+    let squash_commit_sha = gitlab.merge(merge_request.iid);
+
+    // `last_commit` is the head of the source branch, not the squashed commit
+    let mut payload: serde_json::Value =
+        serde_json::from_slice(&payload_template::gitlab_merge_request(
+            merge_request.iid,
+            &merge_request.title,
+            &merge_request.description,
+            &merge_request.source_branch,
+            "merge",
+            None,
+        ))
+        .unwrap();
+    payload["object_attributes"]["squash_commit_sha"] = squash_commit_sha.as_str().into();
+    let req = gitlab_webhook_request("Merge Request Hook", serde_json::to_vec(&payload).unwrap());
+    let resp = test_endpoint_with_gitlab(req, &gitlab).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(gitlab.tag("v1.2.3"), Some(squash_commit_sha));
+}
+
+#[actix_web::test]
+async fn test_unauthorized_author() {
+    let gitlab = MockGitLab::start().await;
+    project(&gitlab);
+    gitlab.open_issue(7, "v1.2.3", "", "mallory", &["release-butler"]);
+
+    let req = gitlab_webhook_request(
+        "Issue Hook",
+        payload_template::gitlab_issue_labeled("v1.2.3", ""),
+    );
+    let resp = test_endpoint_with_gitlab(req, &gitlab).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert!(gitlab.merge_requests().is_empty());
+    assert!(gitlab.issue(7).unwrap().labels.is_empty());
+    assert_eq!(gitlab.issue_notes(7).len(), 1);
+}
+
+#[actix_web::test]
+async fn test_closed_merge_request() {
+    let gitlab = MockGitLab::start().await;
+    project(&gitlab);
+
+    let req = gitlab_webhook_request(
+        "Merge Request Hook",
+        payload_template::gitlab_merge_request(
+            3,
+            "RELEASE @v1.2.3",
+            "Fixes #7",
+            "release-butler/@1.2.3",
+            "close",
+            None,
+        ),
+    );
+    let resp = test_endpoint_with_gitlab(req, &gitlab).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert!(gitlab.tag("v1.2.3").is_none());
+    let notes = gitlab.merge_request_notes(3);
+    assert_eq!(notes.len(), 1);
+    assert!(notes[0].contains("You should remove the label `release-butler`"));
+    assert!(gitlab.issue_notes(3).is_empty());
+
+    // Closed by the app account
+    let mut payload: serde_json::Value =
+        serde_json::from_slice(&payload_template::gitlab_merge_request(
+            3,
+            "RELEASE @v1.2.3",
+            "Fixes #7",
+            "release-butler/@1.2.3",
+            "close",
+            None,
+        ))
+        .unwrap();
+    payload["user"]["username"] = USERNAME.into();
+    let req = gitlab_webhook_request("Merge Request Hook", serde_json::to_vec(&payload).unwrap());
+    let resp = test_endpoint_with_gitlab(req, &gitlab).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(gitlab.merge_request_notes(3).len(), 1);
+}
+
+#[actix_web::test]
+async fn test_malformed_config_opens_issue() {
+    let gitlab = MockGitLab::start().await;
+    gitlab.commit_files("main", &[(".github/release-butler.toml", "version = ")]);
+
+    for _ in 0..2 {
+        let req = gitlab_webhook_request(
+            "Issue Hook",
+            payload_template::gitlab_issue_labeled("v1.2.3", ""),
+        );
+        gitlab.open_issue(7, "v1.2.3", "", "jdoe", &["release-butler"]);
+        test_endpoint_with_gitlab(req, &gitlab).await;
+    }
+
+    let project = gitlab.project();
+    let config_issues: Vec<_> = project
+        .issues
+        .values()
+        .filter(|issue| issue.author == USERNAME)
+        .collect();
+    assert_eq!(config_issues.len(), 1);
+    assert_eq!(config_issues[0].labels, ["release-butler-config-error"]);
+}
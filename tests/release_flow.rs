@@ -0,0 +1,697 @@
+use actix_web::http::Method;
+use release_butler::tests_utils::{
+    github::{MockGitHub, PullRequest, Release, APP_USERNAME},
+    *,
+};
+
+static MULTI_PACKAGE_CONFIG: &str = r#"
+version = 1
+default_branch = "main"
+
+[issues_meta_data]
+allowed_authors = ["jdoe"]
+
+[packages.foo]
+path = "crates/foo"
+changelog_file = "crates/foo/CHANGELOG.md"
+package_manager = "cargo"
+
+[packages.bar]
+path = "crates/bar"
+changelog_file = "crates/bar/CHANGELOG.md"
+package_manager = "cargo"
+"#;
+
+static WORKSPACE_CARGO_TOML: &str = "[workspace]\nmembers = [\"crates/*\"]\n";
+
+fn multi_package_repository(github: &MockGitHub) {
+    github.commit_files(
+        "main",
+        &[
+            (".github/release-butler.toml", MULTI_PACKAGE_CONFIG),
+            ("Cargo.toml", WORKSPACE_CARGO_TOML),
+            (
+                "crates/foo/Cargo.toml",
+                "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                "crates/foo/CHANGELOG.md",
+                "# Changelog\n\n## [0.1.0] - 2025-01-01\n- Initial release\n",
+            ),
+            (
+                "crates/bar/Cargo.toml",
+                "[package]\nname = \"bar\"\nversion = \"0.3.0\"\n",
+            ),
+        ],
+    );
+}
+
+#[actix_web::test]
+async fn test_nested_package_release() {
+    let github = MockGitHub::start().await;
+    multi_package_repository(&github);
+
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("foo@0.2.0", "### Added\n- Feature X", "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let branch = "release-butler/foo@0.2.0";
+    assert_eq!(
+        github.file(branch, "crates/foo/Cargo.toml").unwrap(),
+        "[package]\nname = \"foo\"\nversion = \"0.2.0\"\n"
+    );
+    assert_eq!(
+        github.file(branch, "crates/foo/CHANGELOG.md").unwrap(),
+        "# Changelog\n\n## [0.2.0] - 2025-03-12\n### Added\n- Feature X\n\n## [0.1.0] - 2025-01-01\n- Initial release\n"
+    );
+
+    // Nothing is written outside of the package
+    assert_eq!(
+        github.file(branch, "Cargo.toml").unwrap(),
+        WORKSPACE_CARGO_TOML
+    );
+    assert_eq!(
+        github.file(branch, "crates/bar/Cargo.toml"),
+        github.file("main", "crates/bar/Cargo.toml")
+    );
+    assert!(github.file(branch, "CHANGELOG.md").is_none());
+
+    let pulls = github.pulls();
+    assert_eq!(pulls.len(), 1);
+    assert_eq!(pulls[0].head, branch);
+    assert_eq!(pulls[0].base, "main");
+    assert_eq!(pulls[0].title, "RELEASE foo@v0.2.0");
+    assert!(github.comments(7).is_empty());
+}
+
+#[actix_web::test]
+async fn test_batch_release() {
+    let github = MockGitHub::start().await;
+    multi_package_repository(&github);
+
+    let pull = label_issue(
+        &github,
+        "Release",
+        "- foo@0.2.0\n- bar@0.4.0\n\n### Added\n- Feature X",
+    )
+    .await;
+    let branch = "release-butler/foo@0.2.0+bar@0.4.0";
+    assert_eq!(pull.head, branch);
+    assert_eq!(pull.title, "RELEASE foo@v0.2.0, bar@v0.4.0");
+    assert_eq!(
+        github.file(branch, "crates/foo/Cargo.toml").unwrap(),
+        "[package]\nname = \"foo\"\nversion = \"0.2.0\"\n"
+    );
+    assert_eq!(
+        github.file(branch, "crates/bar/Cargo.toml").unwrap(),
+        "[package]\nname = \"bar\"\nversion = \"0.4.0\"\n"
+    );
+    assert_eq!(
+        github.file(branch, "crates/bar/CHANGELOG.md").unwrap(),
+        "# Changelog\n\n## [0.4.0] - 2025-03-12\n### Added\n- Feature X"
+    );
+
+    let merge_commit_sha = github.merge_pull(pull.number);
+    close_pull(&github, &pull, Some(&merge_commit_sha)).await;
+
+    assert_eq!(github.tag("foo@v0.2.0"), Some(merge_commit_sha.clone()));
+    assert_eq!(github.tag("bar@v0.4.0"), Some(merge_commit_sha));
+    assert_eq!(
+        github.comments(7),
+        ["Released in `foo@v0.2.0`, `bar@v0.4.0`"]
+    );
+}
+
+#[actix_web::test]
+async fn test_batch_release_twice_opens_one_pull() {
+    let github = MockGitHub::start().await;
+    multi_package_repository(&github);
+
+    let body = "- foo@0.2.0\n- bar@0.4.0\n\n### Added\n- Feature X";
+    label_issue(&github, "Release", body).await;
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("Release", body, "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(github.pulls().len(), 1);
+    assert!(github.comments(7).is_empty());
+}
+
+#[actix_web::test]
+async fn test_batch_release_unknown_package() {
+    let github = MockGitHub::start().await;
+    multi_package_repository(&github);
+    github.open_issue(7, "foo@0.2.0, baz@1.0.0", "", "jdoe", &["release-butler"]);
+
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("foo@0.2.0, baz@1.0.0", "", "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert!(github.pulls().is_empty());
+    github.assert_commented(
+        7,
+        "The package `baz` specified in the issue title was not found",
+    );
+}
+
+#[actix_web::test]
+async fn test_group_release() {
+    let github = MockGitHub::start().await;
+    multi_package_repository(&github);
+    let config = format!(
+        "{}\n[groups.core]\npackages = [\"foo\", \"bar\"]\n",
+        MULTI_PACKAGE_CONFIG
+    );
+    github.commit_files("main", &[(".github/release-butler.toml", &config)]);
+
+    let pull = label_issue(&github, "core@0.5.0", "### Added\n- Feature X").await;
+    let branch = "release-butler/foo@0.5.0+bar@0.5.0";
+    assert_eq!(pull.head, branch);
+    assert_eq!(pull.title, "RELEASE foo@v0.5.0, bar@v0.5.0");
+    assert_eq!(
+        github.file(branch, "crates/foo/Cargo.toml").unwrap(),
+        "[package]\nname = \"foo\"\nversion = \"0.5.0\"\n"
+    );
+    assert_eq!(
+        github.file(branch, "crates/bar/Cargo.toml").unwrap(),
+        "[package]\nname = \"bar\"\nversion = \"0.5.0\"\n"
+    );
+
+    let merge_commit_sha = github.merge_pull(pull.number);
+    close_pull(&github, &pull, Some(&merge_commit_sha)).await;
+
+    assert_eq!(github.tag("foo@v0.5.0"), Some(merge_commit_sha.clone()));
+    assert_eq!(github.tag("bar@v0.5.0"), Some(merge_commit_sha));
+}
+
+#[actix_web::test]
+async fn test_dependents_are_listed() {
+    let github = MockGitHub::start().await;
+    multi_package_repository(&github);
+    github.commit_files(
+        "main",
+        &[(
+            "crates/bar/Cargo.toml",
+            "[package]\nname = \"bar\"\nversion = \"0.3.0\"\n\n\
+            [dependencies]\nfoo = { path = \"../foo\", version = \"0.1\" }\n",
+        )],
+    );
+
+    let pull = label_issue(&github, "foo@0.2.0", "### Added\n- Feature X").await;
+    assert_eq!(pull.title, "RELEASE foo@v0.2.0");
+    assert_eq!(
+        github.file(&pull.head, "crates/bar/Cargo.toml"),
+        github.file("main", "crates/bar/Cargo.toml")
+    );
+
+    let comments = github.comments(7);
+    assert_eq!(comments.len(), 1);
+    assert!(comments[0].contains("- `bar` requires `foo` 0.1\n"));
+    assert!(comments[0].contains("Set `bump_dependents = true`"));
+}
+
+static SINGLE_PACKAGE_CONFIG: &str = r#"
+version = 1
+default_branch = "main"
+
+[issues_meta_data]
+allowed_authors = ["jdoe"]
+
+[packages.example]
+changelog_file = "CHANGELOG.md"
+create_gh_release = true
+package_manager = "cargo"
+"#;
+
+static CARGO_TOML: &str = "[package]\nname = \"example\"\nversion = \"1.2.2\"\n";
+
+fn single_package_repository(github: &MockGitHub, config: &str) {
+    github.commit_files(
+        "main",
+        &[
+            (".github/release-butler.toml", config),
+            ("Cargo.toml", CARGO_TOML),
+            ("CHANGELOG.md", "# Changelog\n"),
+        ],
+    );
+}
+
+/// Labels issue #7 and returns the release PR that was opened for it
+async fn label_issue(github: &MockGitHub, title: &str, changelog: &str) -> PullRequest {
+    github.open_issue(7, title, changelog, "jdoe", &["release-butler"]);
+
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled(title, changelog, "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let pulls = github.pulls();
+    assert_eq!(pulls.len(), 1, "Expected a single release PR");
+    pulls[0].clone()
+}
+
+async fn close_pull(github: &MockGitHub, pull: &PullRequest, merge_commit_sha: Option<&str>) {
+    let req = webhook_request(
+        "pull_request",
+        payload_template::pull_request_closed(
+            pull.number,
+            &pull.title,
+            &pull.body,
+            &pull.head,
+            merge_commit_sha,
+        ),
+    );
+    let resp = test_endpoint_with_github(req, github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_release_flow() {
+    let github = MockGitHub::start().await;
+    single_package_repository(&github, SINGLE_PACKAGE_CONFIG);
+
+    let pull = label_issue(&github, "v1.2.3", "### Fixed\n- Bug Y").await;
+    assert_eq!(pull.title, "RELEASE @v1.2.3");
+    assert_eq!(pull.head, "release-butler/@1.2.3");
+    assert!(pull.body.starts_with("Fixes #7"));
+
+    let merge_commit_sha = github.merge_pull(pull.number);
+    close_pull(&github, &pull, Some(&merge_commit_sha)).await;
+
+    assert_eq!(
+        github.file("main", "Cargo.toml").unwrap(),
+        "[package]\nname = \"example\"\nversion = \"1.2.3\"\n"
+    );
+    assert_eq!(github.tag("v1.2.3"), Some(merge_commit_sha));
+
+    let releases = github.releases();
+    assert_eq!(releases.len(), 1);
+    assert_eq!(releases[0].tag_name, "v1.2.3");
+    assert_eq!(releases[0].body, "### Fixed\n- Bug Y");
+    assert!(!releases[0].prerelease);
+    assert_eq!(releases[0].make_latest, "true");
+
+    assert_eq!(
+        github.comments(7),
+        ["Released in [`v1.2.3`](https://github.com/example_org/example_repo/releases/tag/v1.2.3)"]
+    );
+    assert_eq!(github.issue(7).unwrap().state, "closed");
+    assert!(github.comments(pull.number).is_empty());
+}
+
+#[actix_web::test]
+async fn test_maintenance_release_flow() {
+    let github = MockGitHub::start().await;
+    let config = format!(
+        "{}\n[[branch_patterns]]\nversions = \"1.x\"\nbranch = \"release/1.x\"\n",
+        SINGLE_PACKAGE_CONFIG
+    );
+    single_package_repository(&github, &config);
+    github.commit_files(
+        "release/1.x",
+        &[
+            (".github/release-butler.toml", &config),
+            ("Cargo.toml", CARGO_TOML),
+            ("CHANGELOG.md", "# Changelog\n"),
+        ],
+    );
+    github.repository().releases.push(Release {
+        id: 1,
+        tag_name: String::from("v2.0.0"),
+        name: String::from("v2.0.0"),
+        body: String::new(),
+        prerelease: false,
+        make_latest: String::from("true"),
+    });
+    // Pushes `v2.0.0` to the second page of releases
+    for patch in 0..100 {
+        let tag = format!("v1.1.{}", patch);
+        github.repository().releases.push(Release {
+            id: patch + 2,
+            tag_name: tag.clone(),
+            name: tag,
+            body: String::new(),
+            prerelease: false,
+            make_latest: String::from("false"),
+        });
+    }
+
+    let pull = label_issue(&github, "v1.2.3", "### Fixed\n- Bug Y").await;
+    assert_eq!(pull.base, "release/1.x");
+    assert_eq!(
+        github.file(&pull.head, "Cargo.toml").unwrap(),
+        "[package]\nname = \"example\"\nversion = \"1.2.3\"\n"
+    );
+
+    let merge_commit_sha = github.merge_pull(pull.number);
+    close_pull(&github, &pull, Some(&merge_commit_sha)).await;
+
+    assert_eq!(github.tag("v1.2.3"), Some(merge_commit_sha));
+    let releases = github.releases();
+    assert_eq!(releases[101].tag_name, "v1.2.3");
+    assert_eq!(releases[101].make_latest, "false");
+}
+
+#[actix_web::test]
+async fn test_invalid_branch_pattern_opens_issue() {
+    let github = MockGitHub::start().await;
+    single_package_repository(
+        &github,
+        &format!(
+            "{}\n[[branch_patterns]]\nversions = \"one\"\nbranch = \"release/one\"\n",
+            SINGLE_PACKAGE_CONFIG
+        ),
+    );
+
+    let pull = label_issue(&github, "v1.2.3", "### Fixed\n- Bug Y").await;
+    assert_eq!(pull.base, "main");
+
+    let config_issue = github
+        .issues()
+        .into_iter()
+        .find(|issue| issue.author == APP_USERNAME)
+        .unwrap();
+    assert_eq!(config_issue.labels, ["release-butler-config-error"]);
+    assert!(config_issue.title.ends_with(": `one`"));
+    assert!(config_issue.body.contains("- `one` (`release/one`): "));
+
+    // A different problem isn't hidden by the open issue
+    github.commit_files(
+        "main",
+        &[(
+            ".github/release-butler.toml",
+            &format!(
+                "{}\n[[branch_patterns]]\nversions = \"two\"\nbranch = \"release/two\"\n",
+                SINGLE_PACKAGE_CONFIG
+            ),
+        )],
+    );
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("v1.2.3", "### Fixed\n- Bug Y", "jdoe"),
+    );
+    test_endpoint_with_github(req, &github).await;
+    let config_issues: Vec<_> = github
+        .issues()
+        .into_iter()
+        .filter(|issue| issue.author == APP_USERNAME)
+        .collect();
+    assert_eq!(config_issues.len(), 2);
+    assert!(config_issues
+        .iter()
+        .any(|issue| issue.title.ends_with(": `two`")));
+}
+
+#[actix_web::test]
+async fn test_prerelease_flow() {
+    let github = MockGitHub::start().await;
+    single_package_repository(&github, SINGLE_PACKAGE_CONFIG);
+
+    let pull = label_issue(&github, "v1.3.0-beta.1", "### Added\n- Feature Z").await;
+    let merge_commit_sha = github.merge_pull(pull.number);
+    close_pull(&github, &pull, Some(&merge_commit_sha)).await;
+
+    let releases = github.releases();
+    assert_eq!(releases.len(), 1);
+    assert_eq!(releases[0].tag_name, "v1.3.0-beta.1");
+    assert!(releases[0].prerelease);
+    assert_eq!(releases[0].make_latest, "false");
+}
+
+#[actix_web::test]
+async fn test_graphql_commit_method() {
+    let github = MockGitHub::start().await;
+    single_package_repository(
+        &github,
+        &format!("commit_method = \"graphql\"\n{}", SINGLE_PACKAGE_CONFIG),
+    );
+
+    let pull = label_issue(&github, "v1.2.3", "### Fixed\n- Bug Y").await;
+
+    assert_eq!(
+        github.file(&pull.head, "Cargo.toml").unwrap(),
+        "[package]\nname = \"example\"\nversion = \"1.2.3\"\n"
+    );
+    assert!(github
+        .file(&pull.head, "CHANGELOG.md")
+        .unwrap()
+        .contains("### Fixed\n- Bug Y"));
+    assert_eq!(github.request_count("POST", "/graphql"), 1);
+    github.assert_not_requested("POST", "/git/blobs");
+    github.assert_not_requested("POST", "/git/commits");
+}
+
+#[actix_web::test]
+async fn test_graphql_failure_keeps_release_branch() {
+    let github = MockGitHub::start().await;
+    single_package_repository(
+        &github,
+        &format!("commit_method = \"graphql\"\n{}", SINGLE_PACKAGE_CONFIG),
+    );
+    let pull = label_issue(&github, "v1.2.3", "### Fixed\n- Bug Y").await;
+    let head = github.repository().resolve(&pull.head).unwrap();
+    github.commit_files("main", &[("README.md", "# Example\n")]);
+
+    github.fail(
+        Method::POST,
+        "/graphql",
+        StatusCode::UNPROCESSABLE_ENTITY,
+        1,
+    );
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("v1.2.3", "### Fixed\n- Bug Y", "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(github.repository().resolve(&pull.head), Some(head.clone()));
+
+    // Rebuilt on top of the default branch once the commit succeeds
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("v1.2.3", "### Fixed\n- Bug Y", "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_ne!(github.repository().resolve(&pull.head), Some(head));
+    assert_eq!(github.file(&pull.head, "README.md").unwrap(), "# Example\n");
+    assert!(github
+        .repository()
+        .resolve("release-butler-tmp/release-butler/@1.2.3")
+        .is_none());
+    assert_eq!(github.pulls().len(), 1);
+}
+
+#[actix_web::test]
+async fn test_release_failure_is_reported() {
+    let github = MockGitHub::start().await;
+    single_package_repository(&github, SINGLE_PACKAGE_CONFIG);
+
+    let pull = label_issue(&github, "v1.2.3", "### Fixed\n- Bug Y").await;
+    let merge_commit_sha = github.merge_pull(pull.number);
+
+    github.fail(
+        Method::POST,
+        "/releases",
+        StatusCode::INTERNAL_SERVER_ERROR,
+        1,
+    );
+    close_pull(&github, &pull, Some(&merge_commit_sha)).await;
+
+    assert_eq!(github.tag("v1.2.3"), Some(merge_commit_sha));
+    assert!(github.releases().is_empty());
+    github.assert_commented(7, "failed to create the release");
+}
+
+#[actix_web::test]
+async fn test_status_comment() {
+    let github = MockGitHub::start().await;
+    single_package_repository(
+        &github,
+        &format!("status_comment = true\n{}", SINGLE_PACKAGE_CONFIG),
+    );
+
+    let pull = label_issue(&github, "v1.2.3", "### Fixed\n- Bug Y").await;
+    let comments = github.comments(7);
+    assert_eq!(comments.len(), 1);
+    assert!(comments[0].contains("- [x] Files updated in "));
+    assert!(comments[0].contains(&format!(
+        "- [x] Pull request opened https://github.com/example_org/example_repo/pull/{}\n- [ ] Pull request merged\n",
+        pull.number
+    )));
+
+    let merge_commit_sha = github.merge_pull(pull.number);
+    github.fail(
+        Method::POST,
+        "/releases",
+        StatusCode::INTERNAL_SERVER_ERROR,
+        1,
+    );
+    close_pull(&github, &pull, Some(&merge_commit_sha)).await;
+
+    // Edited in place, with the failing step highlighted
+    let comments = github.comments(7);
+    assert_eq!(comments.len(), 1);
+    assert!(comments[0].contains(&format!(
+        "- [x] Pull request merged in {}\n- [x] Tag created `v1.2.3`\n- [ ] :x: **Release published**\n",
+        merge_commit_sha
+    )));
+    assert!(comments[0].contains("failed to create the release"));
+    assert!(github.comments(pull.number).is_empty());
+}
+
+#[actix_web::test]
+async fn test_status_comment_after_many_comments() {
+    let github = MockGitHub::start().await;
+    single_package_repository(
+        &github,
+        &format!("status_comment = true\n{}", SINGLE_PACKAGE_CONFIG),
+    );
+
+    // Pushes the status comment out of the first page
+    for index in 0..100 {
+        github.comment(7, &format!("Comment {}", index));
+    }
+    let pull = label_issue(&github, "v1.2.3", "### Fixed\n- Bug Y").await;
+
+    let merge_commit_sha = github.merge_pull(pull.number);
+    close_pull(&github, &pull, Some(&merge_commit_sha)).await;
+
+    let comments = github.comments(7);
+    assert_eq!(comments.len(), 101);
+    assert!(comments[100].contains("- [x] Release published"));
+}
+
+#[actix_web::test]
+async fn test_commit_failure_is_reported() {
+    let github = MockGitHub::start().await;
+    single_package_repository(&github, SINGLE_PACKAGE_CONFIG);
+    github.open_issue(
+        7,
+        "v1.2.3",
+        "### Fixed\n- Bug Y",
+        "jdoe",
+        &["release-butler"],
+    );
+
+    github.fail(
+        Method::POST,
+        "/git/trees",
+        StatusCode::INTERNAL_SERVER_ERROR,
+        1,
+    );
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("v1.2.3", "### Fixed\n- Bug Y", "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert!(github.pulls().is_empty());
+    github.assert_commented(7, "failed to commit the release changes");
+    assert!(github
+        .repository()
+        .resolve("release-butler/@1.2.3")
+        .is_none());
+
+    // Same with the GraphQL API, which commits on top of an existing branch
+    let github = MockGitHub::start().await;
+    single_package_repository(
+        &github,
+        &format!("commit_method = \"graphql\"\n{}", SINGLE_PACKAGE_CONFIG),
+    );
+    github.open_issue(
+        7,
+        "v1.2.3",
+        "### Fixed\n- Bug Y",
+        "jdoe",
+        &["release-butler"],
+    );
+
+    github.fail(
+        Method::POST,
+        "/graphql",
+        StatusCode::UNPROCESSABLE_ENTITY,
+        1,
+    );
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("v1.2.3", "### Fixed\n- Bug Y", "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert!(github.pulls().is_empty());
+    github.assert_commented(7, "failed to commit the release changes");
+    assert!(github
+        .repository()
+        .resolve("release-butler/@1.2.3")
+        .is_none());
+    assert!(github
+        .repository()
+        .resolve("release-butler-tmp/release-butler/@1.2.3")
+        .is_none());
+}
+
+#[actix_web::test]
+async fn test_closed_without_merge() {
+    let github = MockGitHub::start().await;
+    single_package_repository(&github, SINGLE_PACKAGE_CONFIG);
+
+    let pull = label_issue(&github, "v1.2.3", "### Fixed\n- Bug Y").await;
+    close_pull(&github, &pull, None).await;
+
+    assert!(github.tag("v1.2.3").is_none());
+    assert!(github.releases().is_empty());
+    github.assert_commented(pull.number, "You should remove the label `release-butler`");
+}
+
+#[actix_web::test]
+async fn test_unauthorized_author() {
+    let github = MockGitHub::start().await;
+    single_package_repository(&github, SINGLE_PACKAGE_CONFIG);
+    github.open_issue(7, "v1.2.3", "", "mallory", &["release-butler"]);
+
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("v1.2.3", "", "mallory"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert!(github.pulls().is_empty());
+    assert!(github.issue(7).unwrap().labels.is_empty());
+    github.assert_commented(7, "you can't use the label `release-butler`");
+}
+
+#[actix_web::test]
+async fn test_malformed_config_opens_issue() {
+    let github = MockGitHub::start().await;
+    single_package_repository(&github, "version = ");
+
+    for _ in 0..2 {
+        let req = webhook_request(
+            "issues",
+            payload_template::issues_labeled("v1.2.3", "", "jdoe"),
+        );
+        test_endpoint_with_github(req, &github).await;
+    }
+
+    // The issue is only opened once
+    let issues = github.issues();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].author, APP_USERNAME);
+    assert!(issues[0].title.contains("malformatted"));
+    assert!(github.pulls().is_empty());
+}
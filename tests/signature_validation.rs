@@ -1,4 +1,4 @@
-use release_butler::tests_utils::*;
+use release_butler::{tests_utils::*, State};
 
 #[actix_web::test]
 async fn test_empty_signature_header() {
@@ -65,3 +65,87 @@ async fn test_signature_validation() {
     let body = test::read_body(resp).await;
     assert_eq!(body, WebhookError::SerializationFailed.to_bytes());
 }
+
+#[actix_web::test]
+async fn test_signature_without_prefix() {
+    let signature_header = TestHeader(
+        "x-hub-signature-256",
+        "2299e6c07452bec21c4b8c341de2052b60571d52e1df6c938a9c49d6dad95111",
+    );
+    let event_header = TestHeader("x-github-event", "issues");
+
+    let req = test::TestRequest::post()
+        .uri(WEBHOOK_ENDPOINT)
+        .set_payload(r"Hello World!")
+        .insert_header(signature_header)
+        .insert_header(event_header)
+        .to_request();
+    let resp = test_endpoint(req).await;
+
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+// HMAC-SHA1 of "Hello World!" with the default key
+static SHA1_SIGNATURE: &str = "sha1=5969f7866b9d6ec8d8b1406826eb688206af2360";
+
+fn sha1_request(signature: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(WEBHOOK_ENDPOINT)
+        .set_payload(r"Hello World!")
+        .insert_header(TestHeader("x-hub-signature", signature))
+        .insert_header(TestHeader("x-github-event", "issues"))
+}
+
+#[actix_web::test]
+async fn test_sha1_signature_disabled() {
+    let resp = test_endpoint(sha1_request(SHA1_SIGNATURE).to_request()).await;
+
+    assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+}
+
+#[actix_web::test]
+async fn test_sha1_signature_validation() {
+    let state = || State::new_basic(String::from(DEFAULT_HMAC_KEY)).with_legacy_sha1_signature();
+
+    let resp = test_endpoint_with_state(sha1_request(SHA1_SIGNATURE).to_request(), state()).await;
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = test::read_body(resp).await;
+    assert_eq!(body, WebhookError::SerializationFailed.to_bytes());
+
+    let resp = test_endpoint_with_state(sha1_request("sha1=123").to_request(), state()).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // SHA-256 takes precedence when both are sent
+    let req = sha1_request(SHA1_SIGNATURE)
+        .insert_header(TestHeader("x-hub-signature-256", "sha256=123"))
+        .to_request();
+    let resp = test_endpoint_with_state(req, state()).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_previous_webhook_secret() {
+    // Signed with the default key
+    let req = || {
+        test::TestRequest::post()
+            .uri(WEBHOOK_ENDPOINT)
+            .set_payload(r"Hello World!")
+            .insert_header(TestHeader(
+                "x-hub-signature-256",
+                "sha256=2299e6c07452bec21c4b8c341de2052b60571d52e1df6c938a9c49d6dad95111",
+            ))
+            .insert_header(TestHeader("x-github-event", "issues"))
+            .to_request()
+    };
+
+    let state = State::new_basic(String::from("rotated-secret"));
+    let resp = test_endpoint_with_state(req(), state).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let state = State::new_basic(String::from("rotated-secret"))
+        .with_previous_webhook_secret(String::from(DEFAULT_HMAC_KEY));
+    let resp = test_endpoint_with_state(req(), state).await;
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = test::read_body(resp).await;
+    assert_eq!(body, WebhookError::SerializationFailed.to_bytes());
+}
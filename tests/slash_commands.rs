@@ -0,0 +1,140 @@
+use release_butler::tests_utils::{github::MockGitHub, payload_template::COMMENT_ID, *};
+
+static CONFIG: &str = r#"
+version = 1
+default_branch = "main"
+
+[issues_meta_data]
+allowed_authors = ["jdoe"]
+
+[packages.example]
+changelog_file = "CHANGELOG.md"
+package_manager = "cargo"
+"#;
+
+async fn repository_with_release_pull() -> MockGitHub {
+    let github = MockGitHub::start().await;
+    github.commit_files(
+        "main",
+        &[
+            (".github/release-butler.toml", CONFIG),
+            (
+                "Cargo.toml",
+                "[package]\nname = \"example\"\nversion = \"1.2.2\"\n",
+            ),
+        ],
+    );
+    github.open_issue(7, "v1.2.3", "- Fix", "jdoe", &["release-butler"]);
+
+    let req = webhook_request(
+        "issues",
+        payload_template::issues_labeled("v1.2.3", "- Fix", "jdoe"),
+    );
+    test_endpoint_with_github(req, &github).await;
+    assert_eq!(github.pulls().len(), 1);
+    github
+}
+
+#[actix_web::test]
+async fn test_bump_command() {
+    let github = repository_with_release_pull().await;
+
+    let req = webhook_request(
+        "issue_comment",
+        payload_template::issue_comment("v1.2.3", "/release-butler bump minor", "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(github.reactions(COMMENT_ID), ["+1"]);
+    assert_eq!(github.issue(7).unwrap().title, "v1.3.0");
+
+    let pulls = github.pulls();
+    assert_eq!(pulls[0].state, "closed");
+    assert_eq!(pulls[1].title, "RELEASE @v1.3.0");
+    assert_eq!(pulls[1].state, "open");
+    assert!(github.file("release-butler/@1.2.3", "Cargo.toml").is_none());
+    assert_eq!(
+        github.file("release-butler/@1.3.0", "Cargo.toml").unwrap(),
+        "[package]\nname = \"example\"\nversion = \"1.3.0\"\n"
+    );
+}
+
+#[actix_web::test]
+async fn test_bump_command_prepares_once() {
+    let github = repository_with_release_pull().await;
+    let commits = github.request_count("POST", "/git/commits");
+
+    let req = webhook_request(
+        "issue_comment",
+        payload_template::issue_comment("v1.2.3", "/release-butler bump minor", "jdoe"),
+    );
+    test_endpoint_with_github(req, &github).await;
+
+    // The rename is delivered back to the app as an `edited` event
+    let mut payload: serde_json::Value =
+        serde_json::from_slice(&payload_template::issues_labeled("v1.3.0", "- Fix", "jdoe"))
+            .unwrap();
+    payload["action"] = "edited".into();
+    payload["sender"]["type"] = "Bot".into();
+    let req = webhook_request("issues", serde_json::to_vec(&payload).unwrap());
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(github.request_count("POST", "/git/commits"), commits + 1);
+    assert_eq!(github.request_count("POST", "/pulls"), 2);
+    assert_eq!(github.pulls().len(), 2);
+    assert_eq!(github.comments(7).len(), 0);
+}
+
+#[actix_web::test]
+async fn test_cancel_command_on_pull() {
+    let github = repository_with_release_pull().await;
+    let pull = github.pulls()[0].clone();
+
+    let req = webhook_request(
+        "issue_comment",
+        payload_template::pull_request_comment(
+            pull.number,
+            &pull.title,
+            &pull.body,
+            "/release-butler cancel",
+            "jdoe",
+        ),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(github.reactions(COMMENT_ID), ["+1"]);
+    assert_eq!(github.pulls()[0].state, "closed");
+    assert!(github.file("release-butler/@1.2.3", "Cargo.toml").is_none());
+    assert!(github.issue(7).unwrap().labels.is_empty());
+}
+
+#[actix_web::test]
+async fn test_command_from_unauthorized_author() {
+    let github = repository_with_release_pull().await;
+
+    let req = webhook_request(
+        "issue_comment",
+        payload_template::issue_comment("v1.2.3", "/release-butler cancel", "mallory"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(github.reactions(COMMENT_ID), ["-1"]);
+    assert_eq!(github.pulls()[0].state, "open");
+}
+
+#[actix_web::test]
+async fn test_comment_without_command() {
+    let github = MockGitHub::start().await;
+
+    let req = webhook_request(
+        "issue_comment",
+        payload_template::issue_comment("v1.2.3", "When is this released?", "jdoe"),
+    );
+    let resp = test_endpoint_with_github(req, &github).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(github.requests().is_empty());
+}
@@ -0,0 +1,103 @@
+use actix_web::{web::Data, App, HttpServer};
+use release_butler::{
+    replay::Delivery,
+    tests_utils::{
+        github::{MockGitHub, APP_USERNAME},
+        *,
+    },
+    webhook::parse_event,
+    State,
+};
+use serde_json::json;
+
+static CONFIG: &str = r#"
+version = 1
+default_branch = "main"
+
+[issues_meta_data]
+allowed_authors = ["jdoe"]
+
+[packages.example]
+changelog_file = "CHANGELOG.md"
+package_manager = "cargo"
+"#;
+
+/// Delivery as returned by the GitHub deliveries API
+fn saved_delivery(event: &str, payload: &[u8]) -> Vec<u8> {
+    let payload: serde_json::Value = serde_json::from_slice(payload).unwrap();
+    serde_json::to_vec(&json!({
+        "id": 12345678,
+        "guid": "0b989ba4-242f-11e5-81e1-c7b6966d2516",
+        "event": event,
+        "request": {
+            "headers": {
+                "X-GitHub-Event": event,
+                "X-GitHub-Delivery": "0b989ba4-242f-11e5-81e1-c7b6966d2516",
+                "X-Hub-Signature-256": "sha256=signed-with-the-production-secret",
+            },
+            "payload": payload,
+        },
+    }))
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn test_replay_in_process() {
+    let github = MockGitHub::start().await;
+    github.commit_files(
+        "main",
+        &[
+            (".github/release-butler.toml", CONFIG),
+            (
+                "Cargo.toml",
+                "[package]\nname = \"example\"\nversion = \"1.2.2\"\n",
+            ),
+        ],
+    );
+    github.open_issue(7, "v1.2.3", "", "jdoe", &["release-butler"]);
+
+    let content = saved_delivery(
+        "issues",
+        &payload_template::issues_labeled("v1.2.3", "", "jdoe"),
+    );
+    let delivery = Delivery::parse(&content, None).unwrap();
+    let state = State::from_token(
+        String::from("ghp_token"),
+        String::from(APP_USERNAME),
+        github.uri(),
+    )
+    .unwrap();
+
+    let response = delivery.replay(state).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(github.pulls().len(), 1);
+}
+
+#[actix_web::test]
+async fn test_replay_post() {
+    let server = HttpServer::new(|| {
+        App::new()
+            .app_data(Data::new(State::new_basic(String::from(DEFAULT_HMAC_KEY))))
+            .service(parse_event)
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let url = format!("http://{}{}", server.addrs()[0], WEBHOOK_ENDPOINT);
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+
+    let content = saved_delivery("fork", payload_template::GITHUB_FORK);
+    let delivery = Delivery::parse(&content, None).unwrap();
+
+    // The signature is recomputed, so the event gets past the signature check
+    let response = delivery.post(&url, DEFAULT_HMAC_KEY).await.unwrap();
+    assert_eq!(response.status, 501);
+    assert_eq!(response.body, WebhookError::UnsupportedEvent.to_string());
+
+    let response = delivery.post(&url, "another-secret").await.unwrap();
+    assert_eq!(response.status, 401);
+
+    handle.stop(true).await;
+}